@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use bonsai_bt::BT;
+
+use crate::blackboard::{BBValue, get_f32};
+use crate::boid::{Boid, BoidBT};
+use crate::integrator::Integrator;
+use crate::metrics::{Metrics, Spacing};
+use crate::simulation::{self, NO_CURSOR, Simulation};
+use crate::{OBJECT_COUNT, WORLD_HEIGHT, WORLD_WIDTH};
+
+const BATCH_DT: f64 = 1.0 / 60.0; // Seconds
+
+/// Options for `--batch ticks=N runs=M [seed=S] [integrator=NAME]`
+pub struct BatchConfig {
+    pub ticks: usize,
+    pub runs: usize,
+    pub seed: u64,
+    pub integrator: Integrator,
+}
+
+impl BatchConfig {
+    pub fn parse(args: &[String]) -> Result<BatchConfig, String> {
+        let mut config = BatchConfig { ticks: 1000, runs: 10, seed: 0, integrator: Integrator::default() };
+        for arg in args {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", arg))?;
+            let bad = |_| format!("invalid value for {}: '{}'", key, value);
+            match key {
+                "ticks" => config.ticks = value.parse().map_err(bad)?,
+                "runs" => config.runs = value.parse().map_err(bad)?,
+                "seed" => config.seed = value.parse().map_err(bad)?,
+                "integrator" => config.integrator = Integrator::parse(value)?,
+                _ => return Err(format!("unknown batch option '{}'", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Outcome of one headless run
+pub struct RunResult {
+    pub polarization: f32,
+    /// Normalized angular momentum about the centroid
+    pub angular_momentum: f32,
+    pub mean_speed: f32,
+    pub clusters: f32,
+    pub collisions: f32,
+    pub spacing: Spacing,
+}
+
+fn mean_stddev(values: &[f32]) -> (f32, f32) {
+    let n = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (n - 1.0);
+    (mean, var.sqrt())
+}
+
+/// Simulate `ticks` steps headless with the given world parameter overrides
+pub fn run_once(ticks: usize, seed: u64, integrator: Integrator, overrides: &[(String, f32)]) -> RunResult {
+    let boid_bt: BoidBT = BT::new(Boid::create_bt(), Boid::create_blackboard());
+    let mut world = simulation::create_world_blackboard(WORLD_WIDTH, WORLD_HEIGHT);
+    for (key, value) in overrides {
+        world.insert(key.clone(), BBValue::F32(*value));
+    }
+    let mut sim = Simulation::new(boid_bt, world, WORLD_WIDTH, WORLD_HEIGHT, seed);
+    sim.integrator = integrator;
+    sim.spawn(OBJECT_COUNT);
+    for _ in 0..ticks {
+        sim.step(Duration::from_secs_f64(BATCH_DT), NO_CURSOR);
+        // a migration that has arrived ends the run
+        if sim.migration.as_ref().is_some_and(|m| m.finished) {
+            break;
+        }
+    }
+    let cluster_distance = get_f32(&sim.world_bb.read().unwrap(), "cluster_distance");
+    let metrics = Metrics::measure(&sim.boids, cluster_distance);
+    RunResult {
+        polarization: metrics.polarization,
+        angular_momentum: metrics.milling,
+        mean_speed: metrics.mean_speed,
+        clusters: metrics.clusters as f32,
+        collisions: sim.collision_events as f32,
+        spacing: metrics.spacing,
+    }
+}
+
+/// Run `runs` headless simulations with consecutive seeds and print summary statistics
+pub fn run(config: &BatchConfig) {
+    let results: Vec<RunResult> = (0..config.runs)
+        .map(|r| run_once(config.ticks, config.seed + r as u64, config.integrator, &[]))
+        .collect();
+    if results.is_empty() {
+        return;
+    }
+
+    println!("batch: {} runs x {} ticks, seeds {}..{}, {:?}",
+             config.runs, config.ticks, config.seed, config.seed + config.runs as u64, config.integrator);
+    println!("{:<14}{:>12}{:>12}", "metric", "mean", "stddev");
+    let rows = [
+        ("polarization", results.iter().map(|r| r.polarization).collect::<Vec<f32>>()),
+        ("ang. momentum", results.iter().map(|r| r.angular_momentum).collect()),
+        ("clusters", results.iter().map(|r| r.clusters).collect()),
+        ("collisions", results.iter().map(|r| r.collisions).collect()),
+        ("nn distance", results.iter().map(|r| r.spacing.mean).collect()),
+    ];
+    for (name, values) in rows {
+        let (mean, stddev) = mean_stddev(&values);
+        println!("{:<14}{:>12.3}{:>12.3}", name, mean, stddev);
+    }
+}
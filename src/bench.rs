@@ -0,0 +1,137 @@
+//! Benchmark scene: a big flock from a fixed seed, run for a fixed time with
+//! every tick and draw timed, for comparing machines and optimizations. The
+//! soak test instead grows the flock until frames get too slow.
+use std::time::Duration;
+
+use serde::Serialize;
+
+pub const BENCH_BOIDS: usize = 10_000;
+pub const BENCH_SEED: u64 = 0x5eed_b01d;
+pub const BENCH_SECONDS: f32 = 30.0;
+pub const REPORT_FILE: &str = "bench.json";
+/// Flock the soak test starts from, and the fewest boids it adds at a step
+pub const SOAK_START: usize = 100;
+const SOAK_STEP: usize = 100;
+/// Seconds the soak test holds each flock size for
+const SOAK_INTERVAL: f32 = 3.0;
+/// Seconds after a step before frames count, while the new flock settles
+const SOAK_SETTLE: f32 = 1.0;
+/// Frame time the soak test stops past unless told otherwise, 30 fps
+pub const SOAK_FRAME_MS: f32 = 1000.0 / 30.0;
+
+/// Timings gathered so far
+#[derive(Clone, Debug, Default)]
+pub struct Bench {
+    /// Wall-clock seconds since the scene started
+    pub elapsed: f32,
+    frames: usize,
+    ticks: usize,
+    tick_time: Duration,
+    draw_time: Duration,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub boids: usize,
+    pub seed: u64,
+    pub seconds: f32,
+    pub frames: usize,
+    pub mean_tick_ms: f64,
+    pub mean_draw_ms: f64,
+    pub fps: f64,
+    pub pipelined: bool,
+    pub optimized: bool,
+    pub os: &'static str,
+    pub arch: &'static str,
+}
+
+impl Bench {
+    /// Count a frame `dt` seconds after the last; true once the scene has run its time
+    pub fn frame(&mut self, dt: f32) -> bool {
+        self.elapsed += dt;
+        self.frames += 1;
+        self.elapsed >= BENCH_SECONDS
+    }
+    pub fn record_tick(&mut self, time: Duration) {
+        self.ticks += 1;
+        self.tick_time += time;
+    }
+    pub fn record_draw(&mut self, time: Duration) {
+        self.draw_time += time;
+    }
+    pub fn report(&self, boids: usize, pipelined: bool) -> Report {
+        let mean_ms = |total: Duration, count: usize| total.as_secs_f64() * 1000.0 / count.max(1) as f64;
+        Report {
+            boids,
+            seed: BENCH_SEED,
+            seconds: self.elapsed,
+            frames: self.frames,
+            mean_tick_ms: mean_ms(self.tick_time, self.ticks),
+            mean_draw_ms: mean_ms(self.draw_time, self.frames),
+            fps: self.frames as f64 / (self.elapsed as f64).max(f64::EPSILON),
+            pipelined,
+            optimized: !cfg!(debug_assertions),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+        }
+    }
+}
+
+/// Print `report` and write it to `REPORT_FILE` as JSON
+pub fn write(report: &Report) -> Result<(), String> {
+    println!("bench: {} boids for {:.1}s", report.boids, report.seconds);
+    println!("  tick {:.2} ms  draw {:.2} ms  {:.1} fps", report.mean_tick_ms, report.mean_draw_ms, report.fps);
+    let text = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(REPORT_FILE, text + "\n").map_err(|e| format!("{}: {}", REPORT_FILE, e))
+}
+
+/// What the soak test wants after a frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoakStep {
+    Hold,
+    /// Spawn a flock this big
+    Grow(usize),
+    /// Frames got too slow; the largest flock that kept up, if any did
+    Done(Option<usize>),
+}
+
+/// Soak test: every `SOAK_INTERVAL` seconds the flock grows by a quarter, or
+/// by `SOAK_STEP` if that's more, for as long as the mean frame time stays
+/// under the threshold
+#[derive(Clone, Debug)]
+pub struct Soak {
+    pub boids: usize,
+    /// Milliseconds a frame may take on average
+    pub frame_ms: f32,
+    /// Seconds at the current size
+    held: f32,
+    frames: usize,
+    frame_time: f32,
+    /// Largest flock so far whose frames kept under the threshold
+    pub sustained: Option<usize>,
+}
+
+impl Soak {
+    pub fn new(frame_ms: f32) -> Soak {
+        Soak { boids: SOAK_START, frame_ms, held: 0.0, frames: 0, frame_time: 0.0, sustained: None }
+    }
+    /// Count a frame that took `dt` seconds
+    pub fn frame(&mut self, dt: f32) -> SoakStep {
+        self.held += dt;
+        if self.held > SOAK_SETTLE {
+            self.frames += 1;
+            self.frame_time += dt;
+        }
+        if self.held < SOAK_INTERVAL {
+            return SoakStep::Hold;
+        }
+        let mean = self.frame_time / self.frames.max(1) as f32;
+        if mean * 1000.0 > self.frame_ms {
+            return SoakStep::Done(self.sustained);
+        }
+        self.sustained = Some(self.boids);
+        self.boids += (self.boids / 4).max(SOAK_STEP);
+        (self.held, self.frames, self.frame_time) = (0.0, 0, 0.0);
+        SoakStep::Grow(self.boids)
+    }
+}
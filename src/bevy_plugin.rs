@@ -0,0 +1,178 @@
+//! Bevy adapter (`--features bevy`): the simulation core as a Bevy plugin.
+//! `BoidsPlugin` puts the `Simulation` in the app as the `Flock` resource,
+//! steps it in `FixedUpdate`, and keeps one entity per boid with a `FlockBoid`
+//! component and a `Transform`, spawned and despawned as boids hatch and die.
+//! Games draw or query those entities however they like; the behavior trees
+//! and world parameters are all still there on the resource.
+//!
+//! `--bevy [boids=N] [ticks=N] [seed=S]` runs the plugin in a headless app.
+use std::collections::HashMap;
+
+use bevy::app::{App, FixedUpdate, Plugin};
+use bevy::ecs::prelude::*;
+use bevy::math::{Quat, Vec2};
+use bevy::time::{Fixed, Time, TimePlugin, TimeUpdateStrategy};
+use bevy::transform::components::Transform;
+use bonsai_bt::BT;
+use ggez::mint::Point2;
+
+use crate::blackboard::get_f32;
+use crate::boid::{Boid, BoidBT};
+use crate::metrics::Metrics;
+use crate::simulation::{self, NO_CURSOR, Simulation};
+use crate::{OBJECT_COUNT, WORLD_HEIGHT, WORLD_WIDTH};
+
+/// Adds the flock to an app
+pub struct BoidsPlugin {
+    pub boids: usize,
+    pub seed: u64,
+    /// World size, in world units
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for BoidsPlugin {
+    fn default() -> Self {
+        BoidsPlugin { boids: OBJECT_COUNT, seed: 0, width: WORLD_WIDTH, height: WORLD_HEIGHT }
+    }
+}
+
+/// The simulation the plugin steps
+#[derive(Resource)]
+pub struct Flock(pub Simulation);
+
+/// Point the boids keep away from and the flock catches boids at, in world
+/// units, like the mouse cursor in the window; none by default
+#[derive(Resource, Default)]
+pub struct FlockCursor(pub Option<Vec2>);
+
+/// A boid's entity. Its `Transform` is the boid's position in world units,
+/// y pointing down as in the simulation, turned to face its heading.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FlockBoid {
+    pub id: usize,
+    /// World units per second
+    pub velocity: Vec2,
+}
+
+impl Plugin for BoidsPlugin {
+    fn build(&self, app: &mut App) {
+        let boid_bt: BoidBT = BT::new(Boid::create_bt(), Boid::create_blackboard());
+        let world = simulation::create_world_blackboard(self.width, self.height);
+        let mut sim = Simulation::new(boid_bt, world, self.width, self.height, self.seed);
+        sim.spawn(self.boids);
+        app.insert_resource(Flock(sim))
+            .init_resource::<FlockCursor>()
+            .add_systems(FixedUpdate, (step_flock, sync_boids).chain());
+    }
+}
+
+fn step_flock(time: Res<Time>, cursor: Res<FlockCursor>, mut flock: ResMut<Flock>) {
+    let cursor = cursor.0.map_or(NO_CURSOR, |c| Point2 { x: c.x, y: c.y });
+    flock.0.step(time.delta(), cursor);
+}
+
+fn transform(boid: &Boid) -> Transform {
+    let (pos, vel) = (boid.pos(), boid.vel());
+    Transform::from_xyz(pos.x, pos.y, 0.0).with_rotation(Quat::from_rotation_z(vel.y.atan2(vel.x)))
+}
+
+/// Bring the boid entities in line with the simulation's boids
+fn sync_boids(mut commands: Commands, flock: Res<Flock>, mut entities: Query<(Entity, &mut FlockBoid, &mut Transform)>) {
+    let mut boids: HashMap<usize, &Boid> = flock.0.boids.iter().map(|b| (b.id, b)).collect();
+    for (entity, mut member, mut at) in &mut entities {
+        match boids.remove(&member.id) {
+            Some(boid) => {
+                member.velocity = Vec2::new(boid.vel().x, boid.vel().y);
+                *at = transform(boid);
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+    for boid in boids.into_values() {
+        let velocity = Vec2::new(boid.vel().x, boid.vel().y);
+        commands.spawn((FlockBoid { id: boid.id, velocity }, transform(boid)));
+    }
+}
+
+/// Options for `--bevy [boids=N] [ticks=N] [seed=S]`
+pub struct BevyConfig {
+    pub boids: usize,
+    pub ticks: usize,
+    pub seed: u64,
+}
+
+impl BevyConfig {
+    pub fn parse(args: &[String]) -> Result<BevyConfig, String> {
+        let mut config = BevyConfig { boids: OBJECT_COUNT, ticks: 600, seed: 0 };
+        for arg in args {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", arg))?;
+            let bad = |_| format!("invalid value for {}: '{}'", key, value);
+            match key {
+                "boids" => config.boids = value.parse().map_err(bad)?,
+                "ticks" => config.ticks = value.parse().map_err(bad)?,
+                "seed" => config.seed = value.parse().map_err(bad)?,
+                _ => return Err(format!("unknown bevy option '{}'", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Headless app with the plugin, its clock advancing one fixed step per update
+fn headless(plugin: BoidsPlugin) -> App {
+    let mut app = App::new();
+    let step = Time::<Fixed>::default().timestep();
+    app.add_plugins((TimePlugin, plugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(step));
+    app
+}
+
+/// Run the plugin for `ticks` fixed steps and print how the flock ended up
+pub fn run(config: &BevyConfig) {
+    let mut app = headless(BoidsPlugin { boids: config.boids, seed: config.seed, ..Default::default() });
+    // the first update only starts the clock
+    for _ in 0..=config.ticks {
+        app.update();
+    }
+    let entities = app.world_mut().query::<&FlockBoid>().iter(app.world()).count();
+    let sim = &app.world().resource::<Flock>().0;
+    let metrics = Metrics::measure(&sim.boids, get_f32(&sim.world_bb.read().unwrap(), "cluster_distance"));
+    println!("bevy: {} boids as {} entities after {:.1}s", sim.boids.len(), entities, sim.clock);
+    println!("  polarization {:.2}  clusters {}", metrics.polarization, metrics.clusters);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn entities_follow_the_boids() {
+        let mut app = headless(BoidsPlugin { boids: 20, width: 400.0, height: 300.0, ..Default::default() });
+        for _ in 0..10 {
+            app.update();
+        }
+        // boids die and hatch between syncs
+        let mut flock = app.world_mut().resource_mut::<Flock>();
+        let ids: Vec<usize> = flock.0.boids.iter().map(|b| b.id).take(5).collect();
+        for id in ids {
+            flock.0.despawn(id);
+        }
+        flock.0.spawn_boid();
+        app.update();
+
+        let world = app.world_mut();
+        let entities: HashMap<usize, Transform> =
+            world.query::<(&FlockBoid, &Transform)>().iter(world).map(|(b, t)| (b.id, *t)).collect();
+        let sim = &world.resource::<Flock>().0;
+        assert!(sim.clock > 0.0, "the flock was never stepped");
+        assert_eq!(entities.len(), sim.boids.len());
+        for boid in &sim.boids {
+            assert_eq!(entities[&boid.id].translation, Vec3::new(boid.pos().x, boid.pos().y, 0.0));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Value of a single blackboard entry
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BBValue {
+    /// Scalar parameter (factors, sizes, timers)
+    F32(f32),
+    /// Point or direction (goal point, wind)
+    Vec2(glam::Vec2),
+    /// Flag (fleeing, frozen)
+    Bool(bool),
+    /// RGB color (palette bounds)
+    Color([f32; 3]),
+}
+
+pub type Blackboard = HashMap<String, BBValue>;
+
+/// Blackboard shared by every boid (window size, global weights). Boids read it
+/// each tick, so edits take effect without touching their own BTs.
+pub type WorldBlackboard = Arc<RwLock<Blackboard>>;
+
+impl BBValue {
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            BBValue::F32(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_vec2(&self) -> Option<glam::Vec2> {
+        match *self {
+            BBValue::Vec2(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            BBValue::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_color(&self) -> Option<[f32; 3]> {
+        match *self {
+            BBValue::Color(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Overwrite `key` in place, only allocating the key when it is new
+pub fn set(db: &mut Blackboard, key: &str, value: BBValue) {
+    match db.get_mut(key) {
+        Some(entry) => *entry = value,
+        None => {
+            db.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// Typed lookups, panicking when the key is missing or holds another type
+pub fn get_f32(db: &Blackboard, key: &str) -> f32 {
+    db.get(key)
+        .and_then(BBValue::as_f32)
+        .unwrap_or_else(|| panic!("blackboard: no f32 entry '{}'", key))
+}
+
+pub fn get_vec2(db: &Blackboard, key: &str) -> glam::Vec2 {
+    db.get(key)
+        .and_then(BBValue::as_vec2)
+        .unwrap_or_else(|| panic!("blackboard: no vec2 entry '{}'", key))
+}
+
+pub fn get_bool(db: &Blackboard, key: &str) -> bool {
+    db.get(key)
+        .and_then(BBValue::as_bool)
+        .unwrap_or_else(|| panic!("blackboard: no bool entry '{}'", key))
+}
+
+pub fn get_color(db: &Blackboard, key: &str) -> [f32; 3] {
+    db.get(key)
+        .and_then(BBValue::as_color)
+        .unwrap_or_else(|| panic!("blackboard: no color entry '{}'", key))
+}
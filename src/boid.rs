@@ -1,16 +1,100 @@
 use std::collections::HashMap;
-use bonsai_bt::{Action, Behavior, BT, Event, RUNNING, State, Status::Success, UpdateArgs};
+
+use bonsai_bt::{Action, Behavior, BT, Event, RUNNING, Status, Status::{Failure, Success}, UpdateArgs};
 use ggez::mint;
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::blackboard::{BBValue, Blackboard, get_bool, get_f32, get_vec2, set};
+use crate::epidemic::Health;
+use crate::events::SimEvent;
+use crate::food::FoodPatches;
+use crate::formation::Slot;
+use crate::navgrid::{FlowField, NavGrid};
+use crate::pheromone::PheromoneGrid;
+use crate::detmath;
+use crate::real::{RVec2, Real, real, rvec2, to_f32, vec2_f32};
+use crate::roost;
+use crate::scenario::{Lane, Obstacle};
+use crate::subtrees;
+use crate::theme::Palette;
 
 //algorithm stuff
-const SPEED_LIMIT: f32 = 400.0;
-// Pixels per second
+pub const SPEED_LIMIT: f32 = 400.0;
+// World units per second
 const VISUAL_RANGE: f32 = 32.0;
-// Pixels
-const MIN_DISTANCE: f32 = 16.0; // Pixels
+// World units
+const MIN_DISTANCE: f32 = 16.0; // World units
+const DRAG: f32 = 0.2; // Per second
+/// Mass and drag vary by up to this fraction either side of the average
+const AGILITY_VARIATION: f32 = 0.3;
+/// Sizes range over 1 +/- this
+const SIZE_VARIATION: f32 = 0.4;
+/// Personal blackboard entries, set afresh at hatching, which set one boid apart from the next.
+/// The scales multiply the world's separation, cohesion and alignment factors.
+pub const TRAITS: [&str; 8] = ["speed_limit", "visual_range", "min_distance", "mass", "drag",
+                               "avoid_scale", "centering_scale", "matching_scale"];
+/// How long the ripple after a sharp turn lasts
+pub const FLASH_DURATION: f32 = 0.3; // Seconds
+/// Most a wandering boid's heading drifts per second
+const WANDER_JITTER: f32 = 3.0; // Radians per second
+
+pub type BoidBT = BT<BoidAction, String, BBValue>;
+
+/// Standard normal sample (Box-Muller)
+fn gaussian(rng: &mut StdRng) -> Real {
+    let u1 = real(rng.gen::<f32>().max(f32::MIN_POSITIVE));
+    let u2 = real(rng.gen::<f32>());
+    let (_, cos) = detmath::sin_cos(real(std::f32::consts::TAU) * u2);
+    (-2.0 * detmath::ln(u1)).sqrt() * cos
+}
+
+/// Questions a tree can ask about a boid. Checking one succeeds when it holds
+/// and fails otherwise, so trees guard actions with them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoidCondition {
+    /// A threat was seen or an alarm heard recently
+    PredatorNear,
+    /// A neighbor is set to pass within collision_distance in the next reaction_time
+    CollisionImminent,
+    /// Some neighbor is inside the boid's personal space
+    Crowded,
+    /// Within edge_buffer of an edge of the world
+    OutOfBounds,
+    /// The cursor is right on top of the boid
+    CursorClose,
+    /// Sent somewhere with a right click and not there yet
+    HasGoal,
+    /// On the wandering branch
+    Wandering,
+    /// On the foraging branch
+    Foraging,
+    /// Low on energy, not yet refilled, and with food left to find
+    Hungry,
+    /// Past dusk, with a roost to go to
+    Night,
+    /// Close to the roost, or already circling it
+    AtRoost,
+    /// Circled the roost long enough to settle on it
+    Perched,
+    /// Following a migration that hasn't finished
+    Migrating,
+    /// Flying in formation, with a slot behind another boid
+    InFormation,
+    /// Milling mode is on
+    Milling,
+    /// Within obstacle_margin of an obstacle
+    NearObstacle,
+    /// Assigned to a lane of the scenario
+    InLane,
+    /// Taking a load of food back to the scenario's nest
+    Carrying,
+}
 
 #[derive(Clone, Debug)]
 pub enum BoidAction {
+    /// Succeed if the condition holds, fail otherwise
+    Check(BoidCondition),
     /// avoid others
     AvoidOthers,
     /// Fly towards center
@@ -19,125 +103,827 @@ pub enum BoidAction {
     MatchVelocity,
     /// Limit speed
     LimitSpeed,
-    /// Keep within bounds
+    /// Turn back from the edges and brake
     KeepWithinBounds,
+    /// Get out from under the cursor
+    AvoidCursor,
+    /// Flee from a detected or signalled threat
+    Flee,
+    /// Swerve out of the way of the neighbor it is about to collide with
+    Dodge,
+    /// Steer up the pheromone gradient
+    FollowTrail,
+    /// Blend color toward neighbors
+    BlendColor,
+    /// Steer toward a temporary goal point
+    SeekGoal,
+    /// Decision point: every so often pick flocking, wandering or foraging at random
+    PickBranch,
+    /// Drift along a slowly meandering heading
+    Wander,
+    /// Run up the pheromone gradient, away from the flock
+    Forage,
+    /// Head for the nearest food patch and settle on it
+    SeekFood,
+    /// Take the load of food being carried straight back to the nest
+    ReturnToNest,
+    /// Head for the roost, slowing down on the approach
+    Arrive,
+    /// Circle the roost, all boids the same way round
+    CircleRoost,
+    /// Settle on a spot of the boid's own on the roost
+    Perch,
+    /// Pull toward the migration target from any distance
+    Migrate,
+    /// Fly to the formation slot at the leader's speed
+    HoldSlot,
+    /// Steer around the flock's centroid
+    Mill,
+    /// Steer away from nearby obstacles, harder the closer they are
+    AvoidObstacles,
+    /// Cruise along the lane at lane_speed, keeping to its center line
+    KeepLane,
+    /// Cap how far the heading turned this tick
+    LimitTurnRate,
+    /// Integrate the accumulated steering force into velocity
+    ApplyForce,
+}
+
+/// Force each steering rule added on the last tick, world units per second squared
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Steering {
+    pub separation: glam::Vec2,
+    pub cohesion: glam::Vec2,
+    pub alignment: glam::Vec2,
+    pub goal: glam::Vec2,
+    pub trail: glam::Vec2,
+    pub flee: glam::Vec2,
+    pub dodge: glam::Vec2,
+    pub wander: glam::Vec2,
+    pub forage: glam::Vec2,
+    pub food: glam::Vec2,
+    pub roost: glam::Vec2,
+    pub migration: glam::Vec2,
+    pub formation: glam::Vec2,
+    pub mill: glam::Vec2,
+    pub obstacle: glam::Vec2,
+    pub lane: glam::Vec2,
+    /// Turning back from the edges and braking outside them
+    pub bounds: glam::Vec2,
+    /// Push off the cursor when it is right on top of the boid
+    pub cursor: glam::Vec2,
+}
+
+impl Steering {
+    /// The force recorded by `action`, for the rules that can be cooled down
+    fn rule_mut(&mut self, action: &BoidAction) -> Option<&mut glam::Vec2> {
+        match action {
+            BoidAction::AvoidOthers => Some(&mut self.separation),
+            BoidAction::FlyTowardsCenter => Some(&mut self.cohesion),
+            BoidAction::MatchVelocity => Some(&mut self.alignment),
+            BoidAction::FollowTrail => Some(&mut self.trail),
+            _ => None,
+        }
+    }
+}
+
+/// World entry holding how many milliseconds `action` waits between
+/// evaluations; meanwhile its last force and status are replayed
+fn cooldown_key(action: &BoidAction) -> Option<&'static str> {
+    match action {
+        BoidAction::AvoidOthers => Some("separation_cooldown"),
+        BoidAction::FlyTowardsCenter => Some("cohesion_cooldown"),
+        BoidAction::MatchVelocity => Some("alignment_cooldown"),
+        BoidAction::FollowTrail => Some("trail_cooldown"),
+        _ => None,
+    }
+}
+
+/// A boid's size and the values of its `TRAITS`, to hand on to others
+#[derive(Clone, Copy, Debug)]
+pub struct Traits {
+    size: f32,
+    values: [f32; TRAITS.len()],
+}
+
+impl Traits {
+    /// These traits with each value, size included, scaled by a random factor
+    /// up to `amount` either side of 1
+    pub fn mutated(mut self, amount: f32, rng: &mut StdRng) -> Traits {
+        let mut factors = std::iter::repeat_with(|| 1.0 + (rng.gen::<f32>() * 2.0 - 1.0) * amount);
+        self.size *= factors.next().unwrap();
+        for (value, factor) in self.values.iter_mut().zip(factors) {
+            *value *= factor;
+        }
+        self
+    }
+}
+
+/// Last output of a cooled-down action
+#[derive(Clone, Copy, Debug)]
+pub struct Held {
+    /// Seconds until the action is evaluated again
+    timer: f32,
+    force: RVec2,
+    status: Status,
+}
+
+/// What steering sees of another boid. The simulation copies these once a
+/// tick into a reused buffer instead of cloning whole boids for each one.
+#[derive(Clone, Copy, Debug)]
+pub struct Neighbor {
+    pub x: Real,
+    pub y: Real,
+    pub dx: Real,
+    pub dy: Real,
+    pub color: [f32; 4],
 }
 
 #[derive(Debug, Clone)]
 pub struct Boid {
-    pub x: f32,
-    pub y: f32,
-    pub dx: f32,
-    pub dy: f32,
+    pub id: usize,
+    pub x: Real,
+    pub y: Real,
+    pub dx: Real,
+    pub dy: Real,
     pub color: [f32; 4],
-    pub bt: BT<BoidAction, String, f32>,
+    /// Scale of the drawn polygon and of the boid's personal distances
+    pub size: f32,
+    /// Seconds since hatching
+    pub age: f32,
+    /// Heading change over the last tick, radians per second (positive is clockwise on screen)
+    pub turn_rate: f32,
+    /// Seconds left of the sharp-turn ripple
+    pub flash: f32,
+    pub steering: Steering,
+    pub bt: BoidBT,
+    /// Status each action returned on the last tick, keyed by action name
+    pub trace: HashMap<String, Status>,
+    /// Outputs of cooled-down actions, keyed by action name
+    pub held: HashMap<String, Held>,
+    /// Status of the whole tree on the last tick
+    pub status: Status,
+    /// Waypoints left on the way to a goal, the one being flown to last
+    pub path: Vec<glam::Vec2>,
+    /// Held in place at its heading, not ageing; neighbors still see and follow it
+    pub frozen: bool,
+    /// Id of the boid this one is pair-bonded to, with mates on
+    pub mate: Option<usize>,
+    /// Id of the boid ahead of this one in its chain, with chains on. Such a
+    /// segment doesn't flock, it trails that boid.
+    pub leader: Option<usize>,
+    /// Where the boid stands in the epidemic, with it on
+    pub health: Health,
+    /// Which of the opinions the boid holds, with opinion dynamics on
+    pub opinion: Option<usize>,
 }
 
 impl Boid {
-    pub fn new(spawn_area_width: f32, spawn_area_height: f32,
-               bt: BT<BoidAction, String, f32>) -> Boid {
-        Boid {
-            x: (rand::random::<f32>() * spawn_area_width / 2.0 + spawn_area_width / 4.0),
-            y: (rand::random::<f32>() * spawn_area_height / 2.0 + spawn_area_height / 4.0),
-            dx: (rand::random::<f32>() - 0.5) * SPEED_LIMIT,
-            dy: (rand::random::<f32>() - 0.5) * SPEED_LIMIT,
-            color: [
-                //rgb
-                (rand::random::<f32>() * 128.0 + 128.0) / 255.0,
-                (rand::random::<f32>() * 128.0 + 128.0) / 255.0,
-                (rand::random::<f32>() * 128.0 + 128.0) / 255.0,
-                0.5,
-            ],
+    pub fn new(id: usize, spawn_area_width: f32, spawn_area_height: f32,
+               bt: BoidBT, palette: &Palette, rng: &mut StdRng) -> Boid {
+        let mut boid = Boid {
+            id,
+            x: 0.0,
+            y: 0.0,
+            dx: 0.0,
+            dy: 0.0,
+            color: [0.0; 4],
+            size: 1.0,
+            age: 0.0,
+            turn_rate: 0.0,
+            flash: 0.0,
+            steering: Steering::default(),
             bt,
+            trace: HashMap::new(),
+            held: HashMap::new(),
+            status: Status::Running,
+            path: vec![],
+            frozen: false,
+            mate: None,
+            leader: None,
+            health: Health::Susceptible,
+            opinion: None,
+        };
+        boid.hatch(id, spawn_area_width, spawn_area_height, palette, rng);
+        boid
+    }
+    /// Take on everything `from` shows on screen, reusing this boid's allocations.
+    /// The tree stays this boid's own; with `blackboard` its entries are copied over.
+    pub fn copy_view(&mut self, from: &mut Boid, blackboard: bool) {
+        if blackboard {
+            self.bt.get_blackboard().get_db().clone_from(from.bt.get_blackboard().get_db());
         }
+        // spelled out so a new field has to be thought about here
+        let Boid {
+            id, x, y, dx, dy, color, size, age, turn_rate, flash, steering, bt: _, trace, held: _, status, path,
+            frozen, mate, leader, health, opinion,
+        } = from;
+        self.id = *id;
+        (self.x, self.y, self.dx, self.dy) = (*x, *y, *dx, *dy);
+        (self.color, self.size, self.age, self.turn_rate, self.flash) = (*color, *size, *age, *turn_rate, *flash);
+        self.steering = *steering;
+        self.trace.clone_from(trace);
+        self.status = *status;
+        self.path.clone_from(path);
+        (self.frozen, self.mate, self.leader, self.health, self.opinion) = (*frozen, *mate, *leader, *health, *opinion);
+    }
+    /// Reinitialize as a newborn with fresh traits, reusing this boid's allocations
+    pub fn hatch(&mut self, id: usize, spawn_area_width: f32, spawn_area_height: f32, palette: &Palette,
+                 rng: &mut StdRng) {
+        let mut vary = |value: f32| value * (1.0 + (rng.gen::<f32>() * 2.0 - 1.0) * AGILITY_VARIATION);
+        let mass = vary(1.0);
+        let drag = vary(DRAG);
+        let size = 1.0 + (rng.gen::<f32>() * 2.0 - 1.0) * SIZE_VARIATION;
+        let db = self.bt.get_blackboard().get_db();
+        set(db, "mass", BBValue::F32(mass));
+        set(db, "drag", BBValue::F32(drag));
+        // big boids keep more space, see further and fly slower
+        set(db, "min_distance", BBValue::F32(MIN_DISTANCE * size));
+        set(db, "visual_range", BBValue::F32(VISUAL_RANGE * size));
+        set(db, "speed_limit", BBValue::F32(SPEED_LIMIT / size.sqrt()));
+        set(db, "avoid_scale", BBValue::F32(1.0));
+        set(db, "centering_scale", BBValue::F32(1.0));
+        set(db, "matching_scale", BBValue::F32(1.0));
+        // forget the previous life
+        set(db, "fleeing", BBValue::Bool(false));
+        set(db, "threat", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "alarm_timer", BBValue::F32(0.0));
+        set(db, "has_goal", BBValue::Bool(false));
+        set(db, "goal", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "goal_direction", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "wandering", BBValue::Bool(false));
+        set(db, "foraging", BBValue::Bool(false));
+        set(db, "branch_timer", BBValue::F32(0.0));
+        set(db, "wander_angle", BBValue::F32(0.0));
+        set(db, "energy", BBValue::F32(1.0));
+        set(db, "hungry", BBValue::Bool(false));
+        set(db, "food", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "food_seen", BBValue::Bool(false));
+        set(db, "carrying", BBValue::Bool(false));
+        set(db, "left_flock_at", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "night", BBValue::Bool(false));
+        set(db, "roost", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "circle_timer", BBValue::F32(0.0));
+        set(db, "migrating", BBValue::Bool(false));
+        set(db, "migration_target", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "in_formation", BBValue::Bool(false));
+        set(db, "slot", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "slot_velocity", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "near_obstacle", BBValue::Bool(false));
+        set(db, "obstacle_away", BBValue::Vec2(glam::Vec2::ZERO));
+        set(db, "in_lane", BBValue::Bool(false));
+
+        self.id = id;
+        self.x = real(rng.gen::<f32>() * spawn_area_width / 2.0 + spawn_area_width / 4.0);
+        self.y = real(rng.gen::<f32>() * spawn_area_height / 2.0 + spawn_area_height / 4.0);
+        self.dx = real((rng.gen::<f32>() - 0.5) * SPEED_LIMIT);
+        self.dy = real((rng.gen::<f32>() - 0.5) * SPEED_LIMIT);
+        self.color = palette.sample(rng);
+        self.size = size;
+        self.age = 0.0;
+        self.turn_rate = 0.0;
+        self.flash = 0.0;
+        self.steering = Steering::default();
+        self.trace.clear();
+        self.held.clear();
+        self.path.clear();
+        self.frozen = false;
+        self.mate = None;
+        self.leader = None;
+        self.health = Health::Susceptible;
+        self.opinion = None;
+        self.status = Status::Running;
     }
     pub fn create_boids(
-        bt: &BT<BoidAction, String, f32>,
+        bt: &BoidBT,
         count: usize,
         world_width: f32,
         world_height: f32,
+        palette: &Palette,
+        rng: &mut StdRng,
     ) -> Vec<Boid> {
-        std::iter::repeat_with(|| Boid::new(
+        (0..count).map(|id| Boid::new(
+            id,
             world_width,
-            world_height, bt.clone()))
-            .take(count)
+            world_height, bt.clone(), palette, rng))
             .collect()
     }
+    /// Position narrowed to f32, for rendering and measurements
+    pub fn neighbor(&self) -> Neighbor {
+        Neighbor { x: self.x, y: self.y, dx: self.dx, dy: self.dy, color: self.color }
+    }
+    pub fn pos(&self) -> glam::Vec2 {
+        vec2_f32(RVec2::new(self.x, self.y))
+    }
+    pub fn vel(&self) -> glam::Vec2 {
+        vec2_f32(RVec2::new(self.dx, self.dy))
+    }
+    /// 1 for a hatchling, falling to 0 once the boid reaches `maturity_age`
+    pub fn juvenility(&self, maturity_age: f32) -> f32 {
+        (1.0 - self.age / maturity_age).clamp(0.0, 1.0)
+    }
+    /// Drawn scale, including growth from hatchling to adult
+    pub fn drawn_size(&self, maturity_age: f32) -> f32 {
+        self.size * (1.0 - 0.5 * self.juvenility(maturity_age))
+    }
+    /// Record how fast the heading turned from `v0` over `dt`, starting a
+    /// ripple when it beats `flash_turn_rate` (radians per second)
+    pub fn track_turn(&mut self, v0: glam::Vec2, dt: f32, flash_turn_rate: f32) {
+        let v1 = self.vel();
+        self.flash = (self.flash - dt).max(0.0);
+        if dt <= 0.0 || v0 == glam::Vec2::ZERO || v1 == glam::Vec2::ZERO {
+            self.turn_rate = 0.0;
+            return;
+        }
+        // visual only, so std trig is fine here
+        self.turn_rate = v0.perp_dot(v1).atan2(v0.dot(v1)) / dt;
+        if self.turn_rate.abs() > flash_turn_rate {
+            self.flash = FLASH_DURATION;
+        }
+    }
+    /// Alpha multiplier fading the boid out over the last 30% of its life
+    pub fn vitality(&self, lifespan: f32) -> f32 {
+        ((1.0 - self.age / lifespan) / 0.3).clamp(0.0, 1.0)
+    }
+    /// Personal parameters kept in each boid's own blackboard
+    pub fn create_blackboard() -> Blackboard {
+        let mut db = Blackboard::new();
+        db.insert("speed_limit".to_string(), BBValue::F32(SPEED_LIMIT));
+        db.insert("visual_range".to_string(), BBValue::F32(VISUAL_RANGE));
+        db.insert("min_distance".to_string(), BBValue::F32(MIN_DISTANCE));
+        db.insert("mass".to_string(), BBValue::F32(1.0));
+        db.insert("drag".to_string(), BBValue::F32(DRAG));
+        db.insert("avoid_scale".to_string(), BBValue::F32(1.0));
+        db.insert("centering_scale".to_string(), BBValue::F32(1.0));
+        db.insert("matching_scale".to_string(), BBValue::F32(1.0));
+        // memory
+        db.insert("fleeing".to_string(), BBValue::Bool(false));
+        db.insert("threat".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        db.insert("alarm_timer".to_string(), BBValue::F32(0.0));
+        db.insert("has_goal".to_string(), BBValue::Bool(false));
+        db.insert("goal".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        // which way a flow field says to head for the goal, zero to fly straight at it
+        db.insert("goal_direction".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        // branch picked at the last decision point, flocking when neither is set
+        db.insert("wandering".to_string(), BBValue::Bool(false));
+        db.insert("foraging".to_string(), BBValue::Bool(false));
+        db.insert("branch_timer".to_string(), BBValue::F32(0.0));
+        db.insert("wander_angle".to_string(), BBValue::F32(0.0));
+        // energy from 0 to 1, and the food patch a hungry boid is heading for
+        db.insert("energy".to_string(), BBValue::F32(1.0));
+        db.insert("hungry".to_string(), BBValue::Bool(false));
+        db.insert("food".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        db.insert("food_seen".to_string(), BBValue::Bool(false));
+        db.insert("left_flock_at".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        // ant foraging: a load of food on its way to the nest
+        db.insert("carrying".to_string(), BBValue::Bool(false));
+        db.insert("nest".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        // overnight roost, and how long the boid has circled it tonight
+        db.insert("night".to_string(), BBValue::Bool(false));
+        db.insert("roost".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        db.insert("circle_timer".to_string(), BBValue::F32(0.0));
+        db.insert("migrating".to_string(), BBValue::Bool(false));
+        db.insert("migration_target".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        // formation slot this tick, and the velocity of the boid it trails
+        db.insert("in_formation".to_string(), BBValue::Bool(false));
+        db.insert("slot".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        db.insert("slot_velocity".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        // which way to get clear of obstacles, scaled 0 to 1 by how close they are
+        db.insert("near_obstacle".to_string(), BBValue::Bool(false));
+        db.insert("obstacle_away".to_string(), BBValue::Vec2(glam::Vec2::ZERO));
+        // scenario lane, if the boid was given one
+        db.insert("in_lane".to_string(), BBValue::Bool(false));
+        db.insert("lane_y".to_string(), BBValue::F32(0.0));
+        db.insert("lane_direction".to_string(), BBValue::F32(0.0));
+        db
+    }
     pub fn create_bt() -> Behavior<BoidAction> {
-        let avoid_others = Action(BoidAction::AvoidOthers);
         let fly_towards_center = Action(BoidAction::FlyTowardsCenter);
-        let limit_speed = Action(BoidAction::LimitSpeed);
-        let match_velocity = Action(BoidAction::MatchVelocity);
-        let keep_within_bounds = Action(BoidAction::KeepWithinBounds);
-
-        // Run both behaviors in parallell, WhenAll will always return (Running, 0.0) because
-        // both behaviors would have to return (Success, dt) to the WhenAll condition to succeed.
-        let avoid_and_fly = bonsai_bt::WhenAll(vec![fly_towards_center, avoid_others]);
-        let behavior = bonsai_bt::While(
-            Box::new(avoid_and_fly),
+        let follow_trail = Action(BoidAction::FollowTrail);
+        let blend_color = Action(BoidAction::BlendColor);
+
+        // Run the behaviors in parallel. While calm, WhenAll returns (Running, 0.0) because
+        // every behavior would have to return (Success, dt) for the WhenAll condition to succeed;
+        // the calm check failing fails the condition and ends the loop.
+        let calm_avoid_and_fly = bonsai_bt::WhenAll(vec![subtrees::calm(), subtrees::daytime(), subtrees::sated(),
+                                                         subtrees::empty_handed(), fly_towards_center,
+                                                         subtrees::keep_apart()]);
+        let flocking = bonsai_bt::While(
+            Box::new(calm_avoid_and_fly),
             // vec![Succees, Success, Running] -> sequence is always returning running
-            vec![match_velocity, limit_speed, keep_within_bounds],
+            // steering rules add to the force, which is applied before the velocity limits
+            vec![subtrees::choose_branch(), subtrees::steer_to_point(), subtrees::migrate(), subtrees::mill(),
+                 follow_trail, blend_color,
+                 subtrees::stay_in_bounds(), subtrees::move_and_limit()],
         );
-        behavior
+        // emergencies drop the flock entirely until they are over
+        let emergency = bonsai_bt::Sequence(vec![subtrees::evade(), subtrees::stay_in_bounds(),
+                                                 subtrees::move_and_limit()]);
+        bonsai_bt::Select(vec![subtrees::follow_lane(), flocking, subtrees::roost(), subtrees::feed(),
+                               subtrees::carry_home(), emergency])
+    }
+    /// Send the boid to `goal`; it resumes normal flocking once it arrives
+    pub fn set_goal(&mut self, goal: glam::Vec2) {
+        let db = self.bt.get_blackboard().get_db();
+        db.insert("goal".to_string(), BBValue::Vec2(goal));
+        db.insert("has_goal".to_string(), BBValue::Bool(true));
     }
-    pub fn game_tick(dt: f32, cursor: mint::Point2<f32>, boid: &mut Boid, other_boids: Vec<Boid>) {
+    /// Once the current waypoint is reached, head for the next one on the path
+    pub fn next_waypoint(&mut self) {
+        let db = self.bt.get_blackboard().get_db();
+        if self.path.is_empty() || get_bool(db, "has_goal") {
+            return;
+        }
+        self.path.pop();
+        if let Some(&next) = self.path.last() {
+            self.set_goal(next);
+        }
+    }
+    /// Check for the threat directly or through alarms heard last tick, and
+    /// update the boid's fleeing memory. Returns the alarm to relay, if any.
+    pub fn listen(&mut self, dt: f32, threat: glam::Vec2, alarms: &[SimEvent],
+                  world: &Blackboard) -> Option<SimEvent> {
+        let threat_range = get_f32(world, "threat_range");
+        let hearing_range = get_f32(world, "hearing_range");
+        let alarm_duration = get_f32(world, "alarm_duration");
+        let alarm_hops = get_f32(world, "alarm_hops") as u32;
+
+        let pos = self.pos();
+        let db = self.bt.get_blackboard().get_db();
+        let mut timer = get_f32(db, "alarm_timer") - dt;
+        let mut relay = None;
+
+        if pos.distance(threat) < threat_range {
+            // seen first-hand
+            timer = alarm_duration;
+            db.insert("threat".to_string(), BBValue::Vec2(threat));
+            relay = Some(SimEvent::Alarm { pos, threat, hops: alarm_hops });
+        } else if timer <= 0.0 {
+            for alarm in alarms {
+                let SimEvent::Alarm { pos: from, threat, hops } = *alarm;
+                if hops > 0 && pos.distance(from) < hearing_range {
+                    timer = alarm_duration;
+                    db.insert("threat".to_string(), BBValue::Vec2(threat));
+                    relay = Some(SimEvent::Alarm { pos, threat, hops: hops - 1 });
+                    break;
+                }
+            }
+        }
+
+        db.insert("alarm_timer".to_string(), BBValue::F32(timer.max(0.0)));
+        db.insert("fleeing".to_string(), BBValue::Bool(timer > 0.0));
+        relay
+    }
+    /// Burn energy, eat from any patch the boid is over, and switch between
+    /// flocking and feeding. Hunger sets in below `hunger_threshold` and lasts
+    /// until energy is back above `sated_threshold`; the boid then returns to
+    /// where it left the flock.
+    pub fn metabolize(&mut self, dt: f32, food: &mut FoodPatches, world: &Blackboard) {
+        let energy_drain = get_f32(world, "energy_drain");
+        let eat_rate = get_f32(world, "eat_rate");
+        let food_radius = get_f32(world, "food_radius");
+        let hunger_threshold = get_f32(world, "hunger_threshold");
+        let sated_threshold = get_f32(world, "sated_threshold");
+
+        let pos = self.pos();
+        let db = self.bt.get_blackboard().get_db();
+        let mut energy = (get_f32(db, "energy") - energy_drain * dt).max(0.0);
+        let eaten = food.eat(pos, food_radius, (eat_rate * dt).min(1.0 - energy));
+        energy += eaten;
+
+        let mut hungry = get_bool(db, "hungry");
+        if !hungry && energy < hunger_threshold {
+            hungry = true;
+            set(db, "left_flock_at", BBValue::Vec2(pos));
+        } else if hungry && energy >= sated_threshold {
+            hungry = false;
+            let back = get_vec2(db, "left_flock_at");
+            set(db, "goal", BBValue::Vec2(back));
+            set(db, "has_goal", BBValue::Bool(true));
+        }
+        // nothing to look for while every patch is grazed bare
+        let nearest = food.nearest(pos);
+        set(db, "food", BBValue::Vec2(nearest.unwrap_or(pos)));
+        set(db, "food_seen", BBValue::Bool(nearest.is_some()));
+        set(db, "hungry", BBValue::Bool(hungry));
+        set(db, "energy", BBValue::F32(energy));
+        // with growth on, what is eaten also goes into size, up to the size boids split at
+        if get_bool(world, "growth") && eaten > 0.0 {
+            let size = (self.size * (1.0 + eaten * get_f32(world, "growth_rate"))).min(get_f32(world, "split_size"));
+            self.grow(size / self.size);
+        }
+    }
+    /// Scale the boid's size by `factor`, and with it how much space it keeps,
+    /// how far it sees and, the other way, how fast it flies
+    pub fn grow(&mut self, factor: f32) {
+        self.size *= factor;
+        let db = self.bt.get_blackboard().get_db();
+        for key in ["min_distance", "visual_range"] {
+            let value = get_f32(db, key) * factor;
+            set(db, key, BBValue::F32(value));
+        }
+        let speed_limit = get_f32(db, "speed_limit") / factor.sqrt();
+        set(db, "speed_limit", BBValue::F32(speed_limit));
+    }
+    /// Ant foraging around `nest`: pick up `load` of food at any patch the
+    /// boid is over and put it down back at the nest. Returns whether a load
+    /// was delivered. Without a nest there is nothing to carry.
+    pub fn haul(&mut self, nest: Option<glam::Vec2>, food: &mut FoodPatches, world: &Blackboard) -> bool {
+        let pos = self.pos();
+        let db = self.bt.get_blackboard().get_db();
+        let Some(nest) = nest else {
+            set(db, "carrying", BBValue::Bool(false));
+            return false;
+        };
+        set(db, "nest", BBValue::Vec2(nest));
+        let carrying = get_bool(db, "carrying");
+        if !carrying && food.eat(pos, get_f32(world, "food_radius"), get_f32(world, "ant_load")) > 0.0 {
+            set(db, "carrying", BBValue::Bool(true));
+        } else if carrying && pos.distance(nest) < get_f32(world, "nest_radius") {
+            set(db, "carrying", BBValue::Bool(false));
+            return true;
+        }
+        false
+    }
+    /// Whether the boid is taking food back to the nest
+    pub fn is_carrying(&mut self) -> bool {
+        get_bool(self.bt.get_blackboard().get_db(), "carrying")
+    }
+    /// Note whether it is night and which roost is nearest; by day the boid
+    /// forgets last night's circling
+    pub fn observe_sky(&mut self, night: bool, roosts: &[glam::Vec2]) {
+        let nearest = roost::nearest(roosts, self.pos());
+        let db = self.bt.get_blackboard().get_db();
+        set(db, "night", BBValue::Bool(night && nearest.is_some()));
+        if let Some(roost) = nearest {
+            set(db, "roost", BBValue::Vec2(roost));
+        }
+        if !night {
+            set(db, "circle_timer", BBValue::F32(0.0));
+        }
+    }
+    /// Note the formation slot the boid was given, if any
+    pub fn observe_formation(&mut self, slot: Option<Slot>) {
+        let db = self.bt.get_blackboard().get_db();
+        set(db, "in_formation", BBValue::Bool(slot.is_some()));
+        if let Some(slot) = slot {
+            set(db, "slot", BBValue::Vec2(slot.pos));
+            set(db, "slot_velocity", BBValue::Vec2(slot.leader_velocity));
+        }
+    }
+    /// Sample `flow` for the way to the goal, when it leads to the boid's goal
+    pub fn observe_flow(&mut self, nav: &NavGrid, flow: Option<&FlowField>) {
+        let pos = self.pos();
+        let db = self.bt.get_blackboard().get_db();
+        let direction = flow
+            .filter(|field| get_bool(db, "has_goal") && field.goal == get_vec2(db, "goal"))
+            .and_then(|field| nav.flow_direction(field, pos));
+        set(db, "goal_direction", BBValue::Vec2(direction.unwrap_or(glam::Vec2::ZERO)));
+    }
+    /// Note which way to get clear of the obstacles within `margin`, if any
+    pub fn observe_obstacles(&mut self, obstacles: &[Obstacle], margin: f32) {
+        let pos = self.pos();
+        let mut away = glam::Vec2::ZERO;
+        for obstacle in obstacles {
+            if obstacle.contains(pos) {
+                away += obstacle.exit(pos).normalize_or_zero();
+                continue;
+            }
+            let offset = pos - obstacle.closest_point(pos);
+            if offset.length() < margin {
+                away += offset.normalize_or_zero() * (1.0 - offset.length() / margin);
+            }
+        }
+        let db = self.bt.get_blackboard().get_db();
+        set(db, "near_obstacle", BBValue::Bool(away != glam::Vec2::ZERO));
+        set(db, "obstacle_away", BBValue::Vec2(away));
+    }
+    /// Put the boid on `lane`, or take it off lanes for free flocking
+    pub fn join_lane(&mut self, lane: Option<&Lane>) {
+        let db = self.bt.get_blackboard().get_db();
+        set(db, "in_lane", BBValue::Bool(lane.is_some()));
+        if let Some(lane) = lane {
+            set(db, "lane_y", BBValue::F32(lane.y));
+            set(db, "lane_direction", BBValue::F32(lane.direction));
+        }
+    }
+    /// Direction of travel of the boid's lane, if it has one
+    pub fn lane_direction(&mut self) -> Option<f32> {
+        let db = self.bt.get_blackboard().get_db();
+        get_bool(db, "in_lane").then(|| get_f32(db, "lane_direction"))
+    }
+    /// What sets this boid apart, to give to another with `set_traits`
+    pub fn traits(&mut self) -> Traits {
+        let db = self.bt.get_blackboard().get_db();
+        Traits { size: self.size, values: TRAITS.map(|key| get_f32(db, key)) }
+    }
+    /// Take on `traits` in place of the boid's own
+    pub fn set_traits(&mut self, traits: Traits) {
+        self.size = traits.size;
+        let db = self.bt.get_blackboard().get_db();
+        for (key, value) in TRAITS.iter().zip(traits.values) {
+            set(db, key, BBValue::F32(value));
+        }
+    }
+    /// How far the boid sees its neighbors, for its size
+    pub fn visual_range(&mut self) -> f32 {
+        get_f32(self.bt.get_blackboard().get_db(), "visual_range")
+    }
+    /// How much space the boid keeps around itself, for its size
+    pub fn min_distance(&mut self) -> f32 {
+        get_f32(self.bt.get_blackboard().get_db(), "min_distance")
+    }
+    /// Note the target of the migration under way, if any
+    pub fn observe_migration(&mut self, target: Option<glam::Vec2>) {
+        let db = self.bt.get_blackboard().get_db();
+        set(db, "migrating", BBValue::Bool(target.is_some()));
+        if let Some(target) = target {
+            set(db, "migration_target", BBValue::Vec2(target));
+        }
+    }
+    /// Run the boid's tree for one tick against `neighbors`, every boid as of
+    /// now. `noisy` is scratch space for the neighbors as imperfect senses see them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn game_tick(dt: f32, cursor: mint::Point2<f32>, boid: &mut Boid, neighbors: &[Neighbor],
+                     noisy: &mut Vec<Neighbor>, world: &Blackboard, trails: &PheromoneGrid, rng: &mut StdRng) {
         // proceed to next iteration in event loop
         let e: Event = UpdateArgs { dt: dt.into() }.into();
 
-        // unwrap bt for boid
-        let mut bt = boid.bt.clone();
-        let db = &*bt.get_blackboard().get_db();
-        let win_width: f32 = *db.get("win_width").unwrap();
-        let win_height: f32 = *db.get("win_height").unwrap();
+        // the tree runs from its stored state every tick; only that state is
+        // copied, not the whole tree with its blackboard
+        let mut state = boid.bt.state.clone();
+        // juveniles keep closer, cling to the flock and turn faster
+        let juvenility = real(boid.juvenility(get_f32(world, "maturity_age")));
+        let db = &*boid.bt.get_blackboard().get_db();
+        let speed_limit: Real = real(get_f32(db, "speed_limit") * get_f32(world, "speed_scale"));
+        let visual_range: Real = real(get_f32(db, "visual_range"));
+        let min_distance: Real = real(get_f32(db, "min_distance")) * (1.0 - 0.5 * juvenility);
+        let mass: Real = real(get_f32(db, "mass"));
+        let drag: Real = real(get_f32(db, "drag"));
+        let fleeing: bool = get_bool(db, "fleeing");
+        let threat: RVec2 = rvec2(get_vec2(db, "threat"));
+        let has_goal: bool = get_bool(db, "has_goal");
+        let goal: RVec2 = rvec2(get_vec2(db, "goal"));
+        let goal_direction: RVec2 = rvec2(get_vec2(db, "goal_direction"));
+        let hungry: bool = get_bool(db, "hungry") && get_bool(db, "food_seen");
+        let food: RVec2 = rvec2(get_vec2(db, "food"));
+        let carrying: bool = get_bool(db, "carrying");
+        let nest: RVec2 = rvec2(get_vec2(db, "nest"));
+        let night: bool = get_bool(db, "night");
+        let roost: RVec2 = rvec2(get_vec2(db, "roost"));
+        let mut circle_timer: f32 = get_f32(db, "circle_timer");
+        let migrating: bool = get_bool(db, "migrating");
+        let migration_target: RVec2 = rvec2(get_vec2(db, "migration_target"));
+        let in_formation: bool = get_bool(db, "in_formation");
+        let slot: RVec2 = rvec2(get_vec2(db, "slot"));
+        let slot_velocity: RVec2 = rvec2(get_vec2(db, "slot_velocity"));
+        let near_obstacle: bool = get_bool(db, "near_obstacle");
+        let obstacle_away: RVec2 = rvec2(get_vec2(db, "obstacle_away"));
+        let in_lane: bool = get_bool(db, "in_lane");
+        let lane_y: Real = real(get_f32(db, "lane_y"));
+        let lane_direction: Real = real(get_f32(db, "lane_direction"));
+        let mut arrived = false;
+        let mut wandering: bool = get_bool(db, "wandering");
+        let mut foraging: bool = get_bool(db, "foraging");
+        let mut branch_timer: f32 = get_f32(db, "branch_timer");
+        let mut wander_angle: Real = real(get_f32(db, "wander_angle"));
 
+        // shared world parameters
+        let win_width: Real = real(get_f32(world, "win_width"));
+        let win_height: Real = real(get_f32(world, "win_height"));
+        let avoid_factor: Real = real(get_f32(world, "avoid_factor") * get_f32(db, "avoid_scale"));
+        let centering_factor: Real = real(get_f32(world, "centering_factor") * get_f32(db, "centering_scale"))
+            * (1.0 + juvenility); // adjust velocity by this %
+        let matching_factor: Real = real(get_f32(world, "matching_factor") * get_f32(db, "matching_scale"));
+        let edge_buffer: Real = real(get_f32(world, "edge_buffer"));
+        let turn_factor: Real = real(get_f32(world, "turn_factor"));
+        let flee_factor: Real = real(get_f32(world, "flee_factor"));
+        let edge_brake: Real = real(get_f32(world, "edge_brake"));
+        let cursor_push: Real = real(get_f32(world, "cursor_push"));
+        let trail_factor: Real = real(get_f32(world, "trail_factor"));
+        let branch_interval: f32 = get_f32(world, "branch_interval");
+        let flock_weight: f32 = get_f32(world, "flock_weight").max(0.0);
+        let wander_weight: f32 = get_f32(world, "wander_weight").max(0.0);
+        let forage_weight: f32 = get_f32(world, "forage_weight").max(0.0);
+        let wander_factor: Real = real(get_f32(world, "wander_factor"));
+        let forage_factor: Real = real(get_f32(world, "forage_factor"));
+        let food_factor: Real = real(get_f32(world, "food_factor"));
+        let food_radius: Real = real(get_f32(world, "food_radius"));
+        let nest_factor: Real = real(get_f32(world, "nest_factor"));
+        let roost_radius: Real = real(get_f32(world, "roost_radius"));
+        let roost_factor: Real = real(get_f32(world, "roost_factor"));
+        let perch_delay: f32 = get_f32(world, "perch_delay");
+        let migration_factor: Real = real(get_f32(world, "migration_factor"));
+        let formation_factor: Real = real(get_f32(world, "formation_factor"));
+        let milling: bool = get_bool(world, "milling");
+        let mill_factor: Real = real(get_f32(world, "mill_factor"));
+        let obstacle_factor: Real = real(get_f32(world, "obstacle_factor"));
+        let lane_speed: Real = real(get_f32(world, "lane_speed"));
+        let lane_factor: Real = real(get_f32(world, "lane_factor"));
+        let color_blend: bool = get_bool(world, "color_blend");
+        let color_blend_rate: f32 = get_f32(world, "color_blend_rate");
+        let color_mutation_rate: f32 = get_f32(world, "color_mutation_rate");
+        let seek_factor: Real = real(get_f32(world, "seek_factor"));
+        let goal_radius: Real = real(get_f32(world, "goal_radius"));
+        let collision_distance: Real = real(get_f32(world, "collision_distance"));
+        let reaction_time: Real = real(get_f32(world, "reaction_time"));
+        let dodge_factor: Real = real(get_f32(world, "dodge_factor"));
+        let position_noise: Real = real(get_f32(world, "position_noise"));
+        let velocity_noise: Real = real(get_f32(world, "velocity_noise"));
+        let max_force: Real = real(get_f32(world, "max_force"));
+        let wind: RVec2 = rvec2(get_vec2(world, "wind"));
+        let max_turn_rate: Real = real(get_f32(world, "max_turn_rate")).to_radians() * (1.0 + juvenility);
+        let cursor = RVec2::new(real(cursor.x), real(cursor.y));
+        let step: Real = real(dt);
+
+        // heading before any rule steers this tick
+        let start_velocity = RVec2::new(boid.dx, boid.dy);
+        // steering acceleration accumulated by the rules, world units per second squared
+        let mut force = RVec2::ZERO;
+        let mut steering = Steering::default();
+
+        // imperfect senses: every rule sees the same noisy neighbors this tick
+        let other_boids: &[Neighbor] = if position_noise > 0.0 || velocity_noise > 0.0 {
+            noisy.clear();
+            noisy.extend_from_slice(neighbors);
+            for other in noisy.iter_mut() {
+                other.x += gaussian(rng) * position_noise;
+                other.y += gaussian(rng) * position_noise;
+                other.dx += gaussian(rng) * velocity_noise;
+                other.dy += gaussian(rng) * velocity_noise;
+            }
+            noisy
+        } else {
+            neighbors
+        };
+
+        for held in boid.held.values_mut() {
+            held.timer -= dt;
+        }
+        // which way to swerve from an imminent collision, if any
+        let swerve = boid.collision_swerve(other_boids, visual_range, collision_distance, reaction_time);
+        let mut trace = HashMap::new();
         #[rustfmt::skip]
-        bt.state.tick(&e, &mut |args: bonsai_bt::ActionArgs<Event, BoidAction>| {
-            match args.action {
+        let (status, _) = state.tick(&e, &mut |args: bonsai_bt::ActionArgs<Event, BoidAction>| {
+            let label = format!("{:?}", args.action);
+            let cooldown = cooldown_key(args.action).map_or(0.0, |key| get_f32(world, key) / 1000.0);
+            if cooldown > 0.0 {
+                if let Some(held) = boid.held.get(&label).filter(|h| h.timer > 0.0) {
+                    force += held.force;
+                    if let Some(rule) = steering.rule_mut(args.action) {
+                        *rule = vec2_f32(held.force);
+                    }
+                    trace.insert(label, held.status);
+                    return (held.status, if held.status == Status::Running { 0.0 } else { args.dt });
+                }
+            }
+            let force_before = force;
+            let result = match args.action {
                 BoidAction::AvoidOthers => {
-                    let avoid_factor = 0.5;
                     let mut move_x = 0.0;
                     let mut move_y = 0.0;
-                    for other in &other_boids {
+                    for other in other_boids {
                         let dist = boid.distance(other);
-                        if dist < MIN_DISTANCE && dist > 0.0 {
+                        if dist < min_distance && dist > 0.0 {
                             move_x += boid.x - other.x;
                             move_y += boid.y - other.y;
                         }
                     }
-                    boid.dx += move_x * avoid_factor;
-                    boid.dy += move_y * avoid_factor;
+                    let separation = RVec2::new(move_x, move_y) * avoid_factor;
+                    force += separation;
+                    steering.separation = vec2_f32(separation);
 
                     RUNNING
                 }
                 BoidAction::FlyTowardsCenter => {
-                    let centering_factor = 0.05; // adjust velocity by this %
                     let mut center_x = 0.0;
                     let mut center_y = 0.0;
                     let mut num_neighbors = 0.0;
-                    for other in &other_boids {
-                        if boid.distance(other) < VISUAL_RANGE {
+                    for other in other_boids {
+                        if boid.distance(other) < visual_range {
                             center_x += other.x;
                             center_y += other.y;
                             num_neighbors += 1.0;
                         }
                     }
-                    if num_neighbors > 0.0 {
+                    // boids off on their own branch don't keep to the flock
+                    if num_neighbors > 0.0 && !wandering && !foraging {
                         center_x /= num_neighbors;
                         center_y /= num_neighbors;
 
-                        boid.dx += (center_x - boid.x) * centering_factor;
-                        boid.dy += (center_y - boid.y) * centering_factor;
+                        let cohesion = RVec2::new(center_x - boid.x, center_y - boid.y) * centering_factor;
+                        force += cohesion;
+                        steering.cohesion = vec2_f32(cohesion);
                     }
 
                     RUNNING
                 }
                 BoidAction::MatchVelocity => {
-                    let matching_factor = 0.1;
                     let mut avg_dx = 0.0;
                     let mut avg_dy = 0.0;
                     let mut num_neighbors = 0.0;
-                    for other in &other_boids {
-                        if boid.distance(other) < VISUAL_RANGE {
+                    for other in other_boids {
+                        if boid.distance(other) < visual_range {
                             avg_dx += other.dx;
                             avg_dy += other.dy;
                             num_neighbors += 1.0;
@@ -147,59 +933,369 @@ impl Boid {
                         avg_dx /= num_neighbors;
                         avg_dy /= num_neighbors;
 
-                        boid.dx += (avg_dx - boid.dx) * matching_factor;
-                        boid.dy += (avg_dy - boid.dy) * matching_factor;
+                        let alignment = RVec2::new(avg_dx - boid.dx, avg_dy - boid.dy) * matching_factor;
+                        force += alignment;
+                        steering.alignment = vec2_f32(alignment);
                     }
                     (Success, args.dt)
                 }
                 BoidAction::LimitSpeed => {
                     let speed = (boid.dx * boid.dx + boid.dy * boid.dy).sqrt();
-                    if speed > SPEED_LIMIT {
-                        boid.dx = (boid.dx / speed) * SPEED_LIMIT;
-                        boid.dy = (boid.dy / speed) * SPEED_LIMIT;
+                    if speed > speed_limit {
+                        boid.dx = (boid.dx / speed) * speed_limit;
+                        boid.dy = (boid.dy / speed) * speed_limit;
                     }
 
-                    (Success, args.dt)
+                    RUNNING
                 }
                 BoidAction::KeepWithinBounds => {
-                    let edge_buffer: f32 = 40.0;
-                    let turn_factor: f32 = 16.0;
+                    let before = force;
                     let mut x_bounded = true;
                     let mut y_bounded = true;
 
                     if boid.x < win_width - edge_buffer {
-                        boid.dx += turn_factor;
+                        force.x += turn_factor;
                         x_bounded = !x_bounded;
                     }
                     if boid.x > edge_buffer {
-                        boid.dx -= turn_factor;
+                        force.x -= turn_factor;
                         x_bounded = !x_bounded;
                     }
                     if boid.y < win_height - edge_buffer {
-                        boid.dy += turn_factor;
+                        force.y += turn_factor;
                         y_bounded = !y_bounded
                     }
                     if boid.y > edge_buffer {
-                        boid.dy -= turn_factor;
+                        force.y -= turn_factor;
                         y_bounded = !y_bounded
                     }
+                    // brake while outside the edge buffer
                     if !x_bounded {
-                        boid.dx *= 0.8;
+                        force.x -= boid.dx * edge_brake;
                     }
                     if !y_bounded {
-                        boid.dy *= 0.8;
+                        force.y -= boid.dy * edge_brake;
+                    }
+                    steering.bounds = vec2_f32(force - before);
+
+                    (Success, args.dt)
+                }
+                BoidAction::AvoidCursor => {
+                    let push = RVec2::new(boid.x - cursor.x, boid.y - cursor.y) * cursor_push;
+                    force += push;
+                    steering.cursor = vec2_f32(push);
+
+                    (Success, args.dt)
+                }
+                BoidAction::Flee => {
+                    let away = (RVec2::new(boid.x, boid.y) - threat).normalize_or_zero();
+                    force += away * flee_factor;
+                    steering.flee = vec2_f32(away * flee_factor);
+
+                    (Success, args.dt)
+                }
+                BoidAction::FollowTrail => {
+                    let gradient = trails.gradient(boid.pos());
+                    if gradient.length() > f32::EPSILON {
+                        let trail = rvec2(gradient.normalize()) * trail_factor;
+                        force += trail;
+                        steering.trail = vec2_f32(trail);
                     }
-                    if ((boid.x - cursor.x).powi(2) + (boid.y - cursor.y).powi(2)).sqrt() < 20.0 {
-                        boid.dx += (boid.x - cursor.x) * 1.0;
-                        boid.dy += (boid.y - cursor.y) * 1.0;
+
+                    (Success, args.dt)
+                }
+                BoidAction::BlendColor => {
+                    if color_blend {
+                        if rng.gen::<f32>() < color_mutation_rate * dt {
+                            boid.color = Palette::from_world(world).sample(rng);
+                        } else {
+                            let mut avg = [0.0; 3];
+                            let mut num_neighbors = 0.0;
+                            for other in other_boids {
+                                if boid.distance(other) < visual_range {
+                                    for (c, o) in avg.iter_mut().zip(other.color.iter()) {
+                                        *c += o;
+                                    }
+                                    num_neighbors += 1.0;
+                                }
+                            }
+                            if num_neighbors > 0.0 {
+                                let t = (color_blend_rate * dt).min(1.0);
+                                for (c, a) in boid.color.iter_mut().zip(avg.iter()) {
+                                    *c += (a / num_neighbors - *c) * t;
+                                }
+                            }
+                        }
                     }
 
-                    RUNNING
+                    (Success, args.dt)
                 }
+                BoidAction::SeekGoal => {
+                    let to_goal = goal - RVec2::new(boid.x, boid.y);
+                    if to_goal.length() < goal_radius {
+                        arrived = true;
+                    } else {
+                        let heading = if goal_direction != RVec2::ZERO { goal_direction } else { to_goal.normalize() };
+                        force += heading * seek_factor;
+                        steering.goal = vec2_f32(heading * seek_factor);
+                    }
+
+                    (Success, args.dt)
+                }
+                BoidAction::Check(condition) => {
+                    let holds = match condition {
+                        BoidCondition::PredatorNear => fleeing,
+                        BoidCondition::CollisionImminent => swerve.is_some(),
+                        BoidCondition::Crowded => other_boids.iter().any(|other| {
+                            let dist = boid.distance(other);
+                            dist < min_distance && dist > 0.0
+                        }),
+                        BoidCondition::OutOfBounds => boid.x < edge_buffer || boid.x > win_width - edge_buffer
+                            || boid.y < edge_buffer || boid.y > win_height - edge_buffer,
+                        BoidCondition::CursorClose => (RVec2::new(boid.x, boid.y) - cursor).length() < 20.0,
+                        BoidCondition::HasGoal => has_goal,
+                        BoidCondition::Wandering => wandering,
+                        BoidCondition::Foraging => foraging,
+                        BoidCondition::Hungry => hungry,
+                        BoidCondition::Carrying => carrying,
+                        BoidCondition::Night => night,
+                        BoidCondition::AtRoost => circle_timer > 0.0
+                            || (roost - RVec2::new(boid.x, boid.y)).length() < roost_radius * 1.5,
+                        // boids settle one by one rather than all at once
+                        BoidCondition::Migrating => migrating,
+                        BoidCondition::InFormation => in_formation,
+                        BoidCondition::Milling => milling,
+                        BoidCondition::NearObstacle => near_obstacle,
+                        BoidCondition::InLane => in_lane,
+                        BoidCondition::Perched => circle_timer > perch_delay * (0.5 + (boid.id as f32 * 0.618034).fract()),
+                    };
+                    (if holds { Success } else { Failure }, args.dt)
+                }
+                BoidAction::Dodge => {
+                    if let Some(away) = swerve {
+                        force += away * dodge_factor;
+                        steering.dodge = vec2_f32(away * dodge_factor);
+                    }
+
+                    (Success, args.dt)
+                }
+                BoidAction::PickBranch => {
+                    branch_timer -= dt;
+                    if branch_timer <= 0.0 {
+                        branch_timer = branch_interval;
+                        // nothing to draw when flocking is the only weighted branch
+                        if wander_weight > 0.0 || forage_weight > 0.0 {
+                            let was_wandering = wandering;
+                            let pick = rng.gen::<f32>() * (flock_weight + wander_weight + forage_weight);
+                            wandering = pick >= flock_weight && pick < flock_weight + wander_weight;
+                            foraging = pick >= flock_weight + wander_weight;
+                            if wandering && !was_wandering {
+                                wander_angle = detmath::atan2(boid.dy, boid.dx);
+                            }
+                            // vary the interval so boids don't all decide together
+                            branch_timer *= 0.5 + rng.gen::<f32>();
+                        } else {
+                            wandering = false;
+                            foraging = false;
+                        }
+                    }
+
+                    (Success, args.dt)
+                }
+                BoidAction::Wander => {
+                    wander_angle += real((rng.gen::<f32>() * 2.0 - 1.0) * WANDER_JITTER * dt);
+                    let (sin, cos) = detmath::sin_cos(wander_angle);
+                    let wander = RVec2::new(cos, sin) * wander_factor;
+                    force += wander;
+                    steering.wander = vec2_f32(wander);
+
+                    (Success, args.dt)
+                }
+                BoidAction::Forage => {
+                    let gradient = trails.gradient(boid.pos());
+                    if gradient.length() > f32::EPSILON {
+                        let forage = rvec2(gradient.normalize()) * forage_factor;
+                        force += forage;
+                        steering.forage = vec2_f32(forage);
+                    }
+
+                    (Success, args.dt)
+                }
+                BoidAction::SeekFood => {
+                    let to_food = food - RVec2::new(boid.x, boid.y);
+                    // hover over the patch once there
+                    let seek = if to_food.length() < food_radius {
+                        -RVec2::new(boid.dx, boid.dy) * 4.0
+                    } else {
+                        to_food.normalize() * food_factor
+                    };
+                    force += seek;
+                    steering.food = vec2_f32(seek);
+
+                    (Success, args.dt)
+                }
+                BoidAction::ReturnToNest => {
+                    let home = (nest - RVec2::new(boid.x, boid.y)).normalize_or_zero() * nest_factor;
+                    force += home;
+                    steering.food = vec2_f32(home);
+
+                    (Success, args.dt)
+                }
+                BoidAction::Arrive => {
+                    let to_roost = roost - RVec2::new(boid.x, boid.y);
+                    // full speed until a few circles out
+                    let slowing = (to_roost.length() / (roost_radius * 4.0)).min(1.0);
+                    let desired = to_roost.normalize_or_zero() * speed_limit * slowing;
+                    let arrive = (desired - RVec2::new(boid.dx, boid.dy)) * roost_factor;
+                    force += arrive;
+                    steering.roost = vec2_f32(arrive);
+
+                    (Success, args.dt)
+                }
+                BoidAction::CircleRoost => {
+                    circle_timer += dt;
+                    let out = RVec2::new(boid.x, boid.y) - roost;
+                    let dir = out.normalize_or_zero();
+                    // around at half speed, springing back onto the circle
+                    let desired = dir.perp() * speed_limit * 0.5 + dir * (roost_radius - out.length()) * 2.0;
+                    let circle = (desired - RVec2::new(boid.dx, boid.dy)) * roost_factor;
+                    force += circle;
+                    steering.roost = vec2_f32(circle);
+
+                    (Success, args.dt)
+                }
+                BoidAction::Perch => {
+                    // spots laid out like sunflower seeds, so perched boids don't pile up
+                    let (sin, cos) = detmath::sin_cos(real(boid.id as f32 * 2.399963));
+                    let spread = roost_radius * real((boid.id as f32 * 0.618034).fract().sqrt());
+                    let spot = roost + RVec2::new(cos, sin) * spread;
+                    let desired = (spot - RVec2::new(boid.x, boid.y)) * 2.0;
+                    let perch = (desired - RVec2::new(boid.dx, boid.dy)) * roost_factor;
+                    force += perch;
+                    steering.roost = vec2_f32(perch);
+
+                    (Success, args.dt)
+                }
+                BoidAction::Migrate => {
+                    let migrate = (migration_target - RVec2::new(boid.x, boid.y)).normalize_or_zero() * migration_factor;
+                    force += migrate;
+                    steering.migration = vec2_f32(migrate);
+
+                    (Success, args.dt)
+                }
+                BoidAction::HoldSlot => {
+                    let desired = slot_velocity + (slot - RVec2::new(boid.x, boid.y)) * 2.0;
+                    let hold = (desired - RVec2::new(boid.dx, boid.dy)) * formation_factor;
+                    force += hold;
+                    steering.formation = vec2_f32(hold);
+
+                    (Success, args.dt)
+                }
+                BoidAction::Mill => {
+                    // every boid counts, not only visible neighbors, so the flock turns about one center
+                    let count = real(other_boids.len().max(1) as f32);
+                    let centroid = other_boids.iter().fold(RVec2::ZERO, |sum, o| sum + RVec2::new(o.x, o.y)) / count;
+                    let mill = (RVec2::new(boid.x, boid.y) - centroid).normalize_or_zero().perp() * mill_factor;
+                    force += mill;
+                    steering.mill = vec2_f32(mill);
+
+                    (Success, args.dt)
+                }
+                BoidAction::AvoidObstacles => {
+                    let avoid = obstacle_away * obstacle_factor;
+                    force += avoid;
+                    steering.obstacle = vec2_f32(avoid);
+
+                    (Success, args.dt)
+                }
+                BoidAction::KeepLane => {
+                    let desired = RVec2::new(lane_direction * lane_speed, (lane_y - boid.y) * 2.0);
+                    let keep = (desired - RVec2::new(boid.dx, boid.dy)) * lane_factor;
+                    force += keep;
+                    steering.lane = vec2_f32(keep);
+
+                    (Success, args.dt)
+                }
+                BoidAction::ApplyForce => {
+                    // heavier boids respond more slowly, drag bleeds off speed; wind isn't steering so isn't capped
+                    let velocity = RVec2::new(boid.dx, boid.dy);
+                    let applied = (force.clamp_length_max(max_force) + wind - velocity * drag) / mass * step;
+                    boid.dx += applied.x;
+                    boid.dy += applied.y;
+
+                    (Success, args.dt)
+                }
+                BoidAction::LimitTurnRate => {
+                    let velocity = RVec2::new(boid.dx, boid.dy);
+                    let max_turn = max_turn_rate * step;
+                    if start_velocity.length() > Real::EPSILON && velocity.length() > Real::EPSILON {
+                        let turn = detmath::atan2(start_velocity.perp_dot(velocity), start_velocity.dot(velocity));
+                        if turn.abs() > max_turn {
+                            let (sin, cos) = detmath::sin_cos(max_turn.copysign(turn));
+                            let start = start_velocity.normalize();
+                            let heading = RVec2::new(cos * start.x - sin * start.y, sin * start.x + cos * start.y);
+                            let limited = heading * velocity.length();
+                            boid.dx = limited.x;
+                            boid.dy = limited.y;
+                        }
+                    }
+
+                    (Success, args.dt)
+                }
+            };
+            if cooldown > 0.0 {
+                // boids start out of phase so evaluations spread over the cooldown
+                let timer = match boid.held.get(&label) {
+                    Some(_) => cooldown,
+                    None => cooldown * (boid.id as f32 * 0.618034).fract(),
+                };
+                boid.held.insert(label.clone(), Held { timer, force: force - force_before, status: result.0 });
             }
+            trace.insert(label, result.0);
+            result
         });
+        boid.trace = trace;
+        boid.status = status;
+        boid.steering = steering;
+
+        let db = boid.bt.get_blackboard().get_db();
+        if arrived {
+            db.insert("has_goal".to_string(), BBValue::Bool(false));
+        }
+        set(db, "wandering", BBValue::Bool(wandering));
+        set(db, "foraging", BBValue::Bool(foraging));
+        set(db, "branch_timer", BBValue::F32(branch_timer));
+        set(db, "wander_angle", BBValue::F32(to_f32(wander_angle)));
+        set(db, "circle_timer", BBValue::F32(circle_timer));
+    }
+    /// Which way to swerve from the neighbor among `others` whose closest
+    /// approach in the next `reaction_time` seconds comes within
+    /// `collision_distance` soonest, if there is one
+    fn collision_swerve(&self, others: &[Neighbor], visual_range: Real, collision_distance: Real,
+                        reaction_time: Real) -> Option<RVec2> {
+        let pos = RVec2::new(self.x, self.y);
+        let vel = RVec2::new(self.dx, self.dy);
+        let mut soonest: Option<(Real, RVec2, RVec2)> = None;
+        for other in others {
+            let offset = RVec2::new(other.x, other.y) - pos;
+            let closing = RVec2::new(other.dx, other.dy) - vel;
+            let closing_speed = closing.length_squared();
+            if offset.length() > visual_range || closing_speed <= Real::EPSILON {
+                continue;
+            }
+            let t = -offset.dot(closing) / closing_speed;
+            let miss = offset + closing * t;
+            if t > 0.0 && t < reaction_time && miss.length() < collision_distance
+                && soonest.is_none_or(|(s, _, _)| t < s) {
+                soonest = Some((t, miss, closing));
+            }
+        }
+        // away from where the neighbor would pass, or sideways if it is coming head on
+        soonest.map(|(_, miss, closing)| {
+            if miss.length() > Real::EPSILON { -miss.normalize() } else { closing.perp().normalize() }
+        })
     }
-    fn distance(&self, boid: &Boid) -> f32 {
-        ((self.x - boid.x).powi(2) + (self.y - boid.y).powi(2)).sqrt()
+    pub fn distance(&self, other: &Neighbor) -> Real {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
     }
 }
\ No newline at end of file
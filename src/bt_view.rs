@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use bonsai_bt::{Behavior, Status};
+
+use crate::blackboard::{BBValue, Blackboard};
+
+/// Name of a behavior node, actions shown by their debug name
+pub fn node_label<A: Debug>(behavior: &Behavior<A>) -> String {
+    match behavior {
+        Behavior::Wait(t) => format!("Wait({})", t),
+        Behavior::WaitForever => "WaitForever".to_string(),
+        Behavior::Action(a) => format!("{:?}", a),
+        Behavior::Invert(_) => "Invert".to_string(),
+        Behavior::AlwaysSucceed(_) => "AlwaysSucceed".to_string(),
+        Behavior::Select(_) => "Select".to_string(),
+        Behavior::If(..) => "If".to_string(),
+        Behavior::Sequence(_) => "Sequence".to_string(),
+        Behavior::While(..) => "While".to_string(),
+        Behavior::WhenAll(_) => "WhenAll".to_string(),
+        Behavior::WhenAny(_) => "WhenAny".to_string(),
+        Behavior::After(_) => "After".to_string(),
+    }
+}
+
+/// Child nodes in evaluation order
+pub fn children<A>(behavior: &Behavior<A>) -> Vec<&Behavior<A>> {
+    match behavior {
+        Behavior::Invert(b) | Behavior::AlwaysSucceed(b) => vec![b.as_ref()],
+        Behavior::If(c, s, f) => vec![c.as_ref(), s.as_ref(), f.as_ref()],
+        Behavior::While(c, body) => std::iter::once(c.as_ref()).chain(body.iter()).collect(),
+        Behavior::Select(v) | Behavior::Sequence(v) | Behavior::WhenAll(v) | Behavior::WhenAny(v)
+        | Behavior::After(v) => v.iter().collect(),
+        Behavior::Wait(_) | Behavior::WaitForever | Behavior::Action(_) => vec![],
+    }
+}
+
+/// Indented tree with each child numbered in evaluation order, and what the
+/// children of `While` and `If` are for
+pub fn structure_lines<A: Debug>(behavior: &Behavior<A>) -> Vec<String> {
+    let mut lines = vec![];
+    push_structure(behavior, "", 0, &mut lines);
+    lines
+}
+
+fn push_structure<A: Debug>(behavior: &Behavior<A>, prefix: &str, depth: usize, lines: &mut Vec<String>) {
+    let kind = match behavior {
+        Behavior::Action(_) => "action ",
+        _ => "",
+    };
+    lines.push(format!("{}{}{}{}", "  ".repeat(depth), prefix, kind, node_label(behavior)));
+    for (i, child) in children(behavior).into_iter().enumerate() {
+        push_structure(child, &format!("{}: ", child_role(behavior, i)), depth + 1, lines);
+    }
+}
+
+/// What child `i` of `behavior` is: its position, or its part in `While` and `If`
+fn child_role<A>(behavior: &Behavior<A>, i: usize) -> String {
+    match (behavior, i) {
+        (Behavior::While(..), 0) | (Behavior::If(..), 0) => "condition".to_string(),
+        (Behavior::While(..), i) => format!("body {}", i),
+        (Behavior::If(..), 1) => "then".to_string(),
+        (Behavior::If(..), _) => "else".to_string(),
+        (_, i) => (i + 1).to_string(),
+    }
+}
+
+/// Graphviz cluster for one tree, with actions filled by the status they
+/// returned in `trace`. `id` keeps node names apart between clusters.
+pub fn dot_cluster<A: Debug>(id: usize, name: &str, behavior: &Behavior<A>, trace: &HashMap<String, Status>) -> String {
+    let mut out = format!("  subgraph cluster_{} {{\n    label=\"{}\";\n", id, name);
+    let mut next = 0;
+    push_dot(behavior, trace, &format!("t{}", id), &mut next, &mut out);
+    out.push_str("  }\n");
+    out
+}
+
+fn push_dot<A: Debug>(behavior: &Behavior<A>, trace: &HashMap<String, Status>, prefix: &str, next: &mut usize,
+                      out: &mut String) -> String {
+    let node = format!("{}_{}", prefix, next);
+    *next += 1;
+    let label = node_label(behavior);
+    let (shape, status) = match behavior {
+        Behavior::Action(_) => ("box", trace.get(&label)),
+        _ => ("ellipse", None),
+    };
+    let (text, fill) = match status {
+        Some(Status::Success) => (format!("{}\\nSuccess", label), "palegreen"),
+        Some(Status::Failure) => (format!("{}\\nFailure", label), "lightpink"),
+        Some(Status::Running) => (format!("{}\\nRunning", label), "khaki"),
+        None => (label, "white"),
+    };
+    out.push_str(&format!(
+        "    {} [label=\"{}\", shape={}, style=filled, fillcolor={}];\n",
+        node, text.replace('"', "\\\""), shape, fill
+    ));
+    for (i, child) in children(behavior).into_iter().enumerate() {
+        let child_node = push_dot(child, trace, prefix, next, out);
+        out.push_str(&format!("    {} -> {} [label=\"{}\"];\n", node, child_node, child_role(behavior, i)));
+    }
+    node
+}
+
+/// Graphviz file holding the given clusters
+pub fn dot(clusters: &[String]) -> String {
+    format!("digraph behavior_trees {{\n  fontname=\"sans\";\n  node [fontname=\"sans\"];\n{}}}\n", clusters.concat())
+}
+
+/// Names of the actions in `behavior`, in evaluation order
+fn action_labels<A: Debug>(behavior: &Behavior<A>, labels: &mut Vec<String>) {
+    if let Behavior::Action(_) = behavior {
+        labels.push(node_label(behavior));
+    }
+    for child in children(behavior) {
+        action_labels(child, labels);
+    }
+}
+
+/// How many of `traces` saw each action of `behavior` return success, failure
+/// and running. Actions that were never reached count zero everywhere.
+pub fn status_counts<'a, A: Debug>(behavior: &Behavior<A>, traces: impl Iterator<Item = &'a HashMap<String, Status>>)
+                                   -> Vec<(String, [usize; 3])> {
+    let mut labels = vec![];
+    action_labels(behavior, &mut labels);
+    let mut counts = vec![[0; 3]; labels.len()];
+    for trace in traces {
+        for (label, count) in labels.iter().zip(counts.iter_mut()) {
+            match trace.get(label) {
+                Some(Status::Success) => count[0] += 1,
+                Some(Status::Failure) => count[1] += 1,
+                Some(Status::Running) => count[2] += 1,
+                None => {}
+            }
+        }
+    }
+    labels.into_iter().zip(counts).collect()
+}
+
+/// Indented tree, annotating actions with the status they returned last tick
+pub fn tree_lines<A: Debug>(behavior: &Behavior<A>, trace: &HashMap<String, Status>) -> Vec<String> {
+    let mut lines = vec![];
+    push_lines(behavior, trace, 0, &mut lines);
+    lines
+}
+
+fn push_lines<A: Debug>(behavior: &Behavior<A>, trace: &HashMap<String, Status>, depth: usize,
+                        lines: &mut Vec<String>) {
+    let label = node_label(behavior);
+    let status = match behavior {
+        Behavior::Action(_) => trace.get(&label).map(|s| format!(" [{:?}]", s)).unwrap_or_default(),
+        _ => String::new(),
+    };
+    lines.push(format!("{}{}{}", "  ".repeat(depth), label, status));
+    for child in children(behavior) {
+        push_lines(child, trace, depth + 1, lines);
+    }
+}
+
+/// Blackboard entries sorted by key
+pub fn blackboard_lines(db: &Blackboard) -> Vec<String> {
+    let mut keys: Vec<&String> = db.keys().collect();
+    keys.sort();
+    keys.iter()
+        .map(|k| {
+            let value = match db[*k] {
+                BBValue::F32(v) => format!("{:.2}", v),
+                BBValue::Vec2(v) => format!("({:.0}, {:.0})", v.x, v.y),
+                BBValue::Bool(v) => v.to_string(),
+                BBValue::Color([r, g, b]) => format!("({:.2}, {:.2}, {:.2})", r, g, b),
+            };
+            format!("{} = {}", k, value)
+        })
+        .collect()
+}
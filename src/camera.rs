@@ -0,0 +1,127 @@
+//! View onto the world: which world point sits in the middle of the viewport,
+//! how far it is zoomed and turned, eased towards wherever the camera mode wants it
+use ggez::graphics::Rect;
+
+use crate::boid::Boid;
+
+/// World units kept clear around the flock when framing it
+const FRAME_MARGIN: f32 = 80.0;
+/// Zoom limits while framing, and zoom while following a boid, as multiples
+/// of the zoom that fits the whole world in the viewport
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 3.0;
+const FOLLOW_ZOOM: f32 = 2.0;
+/// How quickly the view closes in on its target, per second
+const EASING: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// The whole world, as without a camera
+    Fixed,
+    /// Pan and zoom to keep every boid in view
+    Frame,
+    /// Keep the selected boid in the middle
+    Follow,
+    /// Follow the selected boid with the view turned so it always flies up
+    FollowHeading,
+}
+
+impl CameraMode {
+    pub fn next(self) -> CameraMode {
+        match self {
+            CameraMode::Fixed => CameraMode::Frame,
+            CameraMode::Frame => CameraMode::Follow,
+            CameraMode::Follow => CameraMode::FollowHeading,
+            CameraMode::FollowHeading => CameraMode::Fixed,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Fixed => "fixed",
+            CameraMode::Frame => "frame the flock",
+            CameraMode::Follow => "follow the selected boid",
+            CameraMode::FollowHeading => "follow the selected boid, heading up",
+        }
+    }
+}
+
+pub struct Camera {
+    pub mode: CameraMode,
+    /// World point drawn at the middle of the viewport
+    pub center: glam::Vec2,
+    /// Screen pixels per world unit
+    pub zoom: f32,
+    /// How far the world is turned on screen, radians clockwise
+    pub rotation: f32,
+    /// Part of the screen the world is drawn into, logical pixels
+    pub viewport: Rect,
+    /// Screen offset on top of the view, logical pixels, for screen shake
+    pub shake: glam::Vec2,
+}
+
+impl Camera {
+    /// Fixed camera showing all of a world of `world_size` in `viewport`
+    pub fn new(viewport: Rect, world_size: glam::Vec2) -> Camera {
+        let zoom = Camera::fit(viewport, world_size);
+        Camera { mode: CameraMode::Fixed, center: world_size / 2.0, zoom, rotation: 0.0, viewport, shake: glam::Vec2::ZERO }
+    }
+    /// Zoom at which `size` world units just fit in `viewport`
+    fn fit(viewport: Rect, size: glam::Vec2) -> f32 {
+        (viewport.w / size.x).min(viewport.h / size.y)
+    }
+    /// World to screen transform. Everything drawn in the world goes through
+    /// this, and picking goes back through its inverse, so what is under the
+    /// cursor is what gets picked.
+    pub fn matrix(&self) -> glam::Mat4 {
+        let middle = glam::vec2(self.viewport.x + self.viewport.w / 2.0, self.viewport.y + self.viewport.h / 2.0);
+        glam::Mat4::from_translation((middle + self.shake).extend(0.0))
+            * glam::Mat4::from_rotation_z(self.rotation)
+            * glam::Mat4::from_scale(glam::vec3(self.zoom, self.zoom, 1.0))
+            * glam::Mat4::from_translation(-self.center.extend(0.0))
+    }
+    pub fn to_world(&self, screen: glam::Vec2) -> glam::Vec2 {
+        self.matrix().inverse().transform_point3(screen.extend(0.0)).truncate()
+    }
+    pub fn to_screen(&self, world: glam::Vec2) -> glam::Vec2 {
+        self.matrix().transform_point3(world.extend(0.0)).truncate()
+    }
+    /// World length that comes out `pixels` long on screen
+    pub fn world_length(&self, pixels: f32) -> f32 {
+        pixels / self.zoom
+    }
+    /// Ease `dt` seconds' worth towards the view the mode asks for: the world
+    /// of `world_size` at rest when fixed, `boids` and a margin when framing,
+    /// and `followed` close up when following. Without a boid to follow the
+    /// camera goes back to rest.
+    pub fn update(&mut self, dt: f32, boids: &[Boid], followed: Option<&Boid>, world_size: glam::Vec2) {
+        let rest = Camera::fit(self.viewport, world_size);
+        let mut rotation = 0.0;
+        let (center, zoom) = match (self.mode, followed) {
+            (CameraMode::Follow, Some(boid)) => (boid.pos(), rest * FOLLOW_ZOOM),
+            (CameraMode::FollowHeading, Some(boid)) => {
+                let heading = boid.vel();
+                if heading != glam::Vec2::ZERO {
+                    rotation = -std::f32::consts::FRAC_PI_2 - heading.y.atan2(heading.x);
+                } else {
+                    rotation = self.rotation;
+                }
+                (boid.pos(), rest * FOLLOW_ZOOM)
+            }
+            (CameraMode::Frame, _) if !boids.is_empty() => {
+                let (min, max) = boids.iter().fold((glam::Vec2::splat(f32::MAX), glam::Vec2::splat(f32::MIN)),
+                                                   |(min, max), b| (min.min(b.pos()), max.max(b.pos())));
+                let size = max - min + glam::Vec2::splat(2.0 * FRAME_MARGIN);
+                let zoom = Camera::fit(self.viewport, size).clamp(rest * MIN_ZOOM, rest * MAX_ZOOM);
+                ((min + max) / 2.0, zoom)
+            }
+            _ => (world_size / 2.0, rest),
+        };
+        let t = 1.0 - (-EASING * dt).exp();
+        self.center = self.center.lerp(center, t);
+        // zoom eases in log space so zooming in and out feel the same
+        self.zoom = (self.zoom.ln() + (zoom.ln() - self.zoom.ln()) * t).exp();
+        // turn the short way round
+        let turn = (rotation - self.rotation + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        self.rotation += turn * t;
+    }
+}
@@ -0,0 +1,111 @@
+//! Snake mode: boids link up into chains that slither as one. Only the head
+//! of a chain flocks; every other segment trails the one ahead of it on a
+//! fixed-length link, so the body traces the path the head took.
+use std::collections::HashMap;
+
+use crate::boid::Boid;
+use crate::real::real;
+
+/// Break links to boids that are gone, then hang every boid that is on its
+/// own behind the nearest tail within `range` of a chain shorter than
+/// `length`. A boid with no such tail nearby starts a chain of its own.
+pub fn link(boids: &mut [Boid], length: usize, range: f32) {
+    let index: HashMap<usize, usize> = boids.iter().enumerate().map(|(i, b)| (b.id, i)).collect();
+    for boid in boids.iter_mut() {
+        if boid.leader.is_some_and(|leader| !index.contains_key(&leader)) {
+            boid.leader = None;
+        }
+    }
+    let mut follower: HashMap<usize, usize> = HashMap::new();
+    for (i, boid) in boids.iter().enumerate() {
+        if let Some(leader) = boid.leader {
+            follower.insert(index[&leader], i);
+        }
+    }
+    // tail of every chain still short of `length`
+    let mut open: Vec<usize> = vec![];
+    for head in (0..boids.len()).filter(|&i| boids[i].leader.is_none() && follower.contains_key(&i)) {
+        let (mut tail, mut size) = (head, 1);
+        while let Some(&next) = follower.get(&tail) {
+            (tail, size) = (next, size + 1);
+        }
+        if size < length {
+            open.push(tail);
+        }
+    }
+    for i in 0..boids.len() {
+        if boids[i].leader.is_some() || follower.contains_key(&i) {
+            continue;
+        }
+        let pos = boids[i].pos();
+        let nearest = open
+            .iter()
+            .enumerate()
+            .map(|(k, &tail)| (k, boids[tail].pos().distance(pos)))
+            .filter(|&(_, d)| d < range)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match nearest {
+            Some((k, _)) => {
+                boids[i].leader = Some(boids[open[k]].id);
+                follower.insert(open[k], i);
+                if chain_size(boids, &index, i) < length {
+                    open[k] = i;
+                } else {
+                    open.swap_remove(k);
+                }
+            }
+            None if length > 1 => open.push(i),
+            None => {}
+        }
+    }
+}
+
+/// Segments from the head of `i`'s chain down to `i`
+fn chain_size(boids: &[Boid], index: &HashMap<usize, usize>, mut i: usize) -> usize {
+    let mut size = 1;
+    while let Some(leader) = boids[i].leader {
+        i = index[&leader];
+        size += 1;
+    }
+    size
+}
+
+/// Pairs of indices into `boids`, segment and the one it trails
+pub fn links(boids: &[Boid]) -> Vec<(usize, usize)> {
+    let index: HashMap<usize, usize> = boids.iter().enumerate().map(|(i, b)| (b.id, i)).collect();
+    boids
+        .iter()
+        .enumerate()
+        .filter_map(|(i, b)| b.leader.and_then(|leader| index.get(&leader)).map(|&j| (i, j)))
+        .collect()
+}
+
+/// Drag every segment to `link_length` behind the one ahead of it, head first
+/// so each follows where its leader has just moved to, and give it the
+/// velocity that took it there over `dt` seconds. Frozen segments stay put.
+pub fn follow(boids: &mut [Boid], dt: f32, link_length: f32) {
+    let index: HashMap<usize, usize> = boids.iter().enumerate().map(|(i, b)| (b.id, i)).collect();
+    let mut follower: HashMap<usize, usize> = HashMap::new();
+    for (i, boid) in boids.iter().enumerate() {
+        if let Some(leader) = boid.leader {
+            follower.insert(index[&leader], i);
+        }
+    }
+    for head in 0..boids.len() {
+        if boids[head].leader.is_some() {
+            continue;
+        }
+        let mut ahead = head;
+        while let Some(&i) = follower.get(&ahead) {
+            let (target, pos) = (boids[ahead].pos(), boids[i].pos());
+            if !boids[i].frozen && target != pos {
+                let new = target + (pos - target).normalize() * link_length;
+                let vel = if dt > 0.0 { (new - pos) / dt } else { glam::Vec2::ZERO };
+                let boid = &mut boids[i];
+                (boid.x, boid.y) = (real(new.x), real(new.y));
+                (boid.dx, boid.dy) = (real(vel.x), real(vel.y));
+            }
+            ahead = i;
+        }
+    }
+}
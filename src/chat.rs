@@ -0,0 +1,111 @@
+//! IRC chat control (`--features irc`), so a stream's chat can steer the flock.
+//! Works with any IRC server, including Twitch's gateway at irc.chat.twitch.tv:6667,
+//! which accepts anonymous `justinfanNNNN` nicks for reading chat.
+//!
+//! Commands: `!scatter`, `!wind left|right|up|down|calm` and `!spawn <count>`.
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::blackboard::{BBValue, set};
+use crate::real::real;
+use crate::simulation::Simulation;
+
+const WIND_STRENGTH: f32 = 300.0; // World units per second squared
+/// Most boids one `!spawn` may ask for
+const MAX_SPAWN: u32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChatCommand {
+    Scatter,
+    Wind(glam::Vec2),
+    Spawn(u32),
+}
+
+/// Parse a chat message, ignoring anything that isn't a command
+pub fn parse_command(text: &str) -> Option<ChatCommand> {
+    let mut words = text.split_whitespace();
+    match words.next()? {
+        "!scatter" => Some(ChatCommand::Scatter),
+        "!wind" => {
+            let direction = match words.next()? {
+                "left" => glam::vec2(-1.0, 0.0),
+                "right" => glam::vec2(1.0, 0.0),
+                "up" => glam::vec2(0.0, -1.0),
+                "down" => glam::vec2(0.0, 1.0),
+                "calm" | "off" => glam::Vec2::ZERO,
+                _ => return None,
+            };
+            Some(ChatCommand::Wind(direction * WIND_STRENGTH))
+        }
+        "!spawn" => {
+            let count: u32 = words.next().map_or(Some(1), |n| n.parse().ok())?;
+            Some(ChatCommand::Spawn(count.min(MAX_SPAWN)))
+        }
+        _ => None,
+    }
+}
+
+/// Text of a PRIVMSG line, skipping Twitch's `@tags` and the sender prefix
+fn privmsg_text(line: &str) -> Option<&str> {
+    let line = match line.strip_prefix('@') {
+        Some(tagged) => tagged.split_once(' ')?.1,
+        None => line,
+    };
+    let line = match line.strip_prefix(':') {
+        Some(prefixed) => prefixed.split_once(' ')?.1,
+        None => line,
+    };
+    let rest = line.strip_prefix("PRIVMSG ")?;
+    Some(rest.split_once(" :")?.1)
+}
+
+/// Join `channel` on `server` (host:port) and read commands on a background thread
+pub fn connect(server: &str, channel: &str, nick: &str) -> io::Result<Receiver<ChatCommand>> {
+    let mut stream = TcpStream::connect(server)?;
+    write!(stream, "NICK {}\r\nUSER {} 0 * :{}\r\nJOIN {}\r\n", nick, nick, nick, channel)?;
+    let reader = BufReader::new(stream.try_clone()?);
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    eprintln!("irc: {}", e);
+                    break;
+                }
+            };
+            if let Some(token) = line.strip_prefix("PING ") {
+                if stream.write_all(format!("PONG {}\r\n", token).as_bytes()).is_err() {
+                    break;
+                }
+            } else if let Some(command) = privmsg_text(&line).and_then(parse_command) {
+                // the game has gone away
+                if sender.send(command).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+/// Send every boid flying away from the flock's center, at least at half the top speed
+pub fn scatter(sim: &mut Simulation) {
+    if sim.boids.is_empty() {
+        return;
+    }
+    let center = sim.boids.iter().fold(glam::Vec2::ZERO, |sum, b| sum + b.pos()) / sim.boids.len() as f32;
+    for boid in &mut sim.boids {
+        let away = (boid.pos() - center).normalize_or_zero();
+        let speed = boid.vel().length().max(crate::boid::SPEED_LIMIT / 2.0);
+        boid.dx = real(away.x * speed);
+        boid.dy = real(away.y * speed);
+    }
+}
+
+/// Set the wind blowing over a simulation
+pub fn set_wind(sim: &Simulation, wind: glam::Vec2) {
+    set(&mut sim.world_bb.write().unwrap(), "wind", BBValue::Vec2(wind));
+}
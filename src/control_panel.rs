@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use ggez::{Context, GameResult, graphics};
+use ggez::winit::event::VirtualKeyCode;
+
+use crate::blackboard::{BBValue, Blackboard};
+use crate::boid::TRAITS;
+use crate::keymap::{self, Command};
+
+const MIN_WIDTH: f32 = 260.0;
+const MAX_WIDTH: f32 = 640.0;
+const RESIZE_STEP: f32 = 40.0;
+const FONT_SIZE: f32 = 16.0;
+const MARGIN: f32 = 8.0;
+/// Relative step applied by left/right
+const ADJUST_FACTOR: f32 = 1.1;
+/// Entries describing the window rather than tuning the flock
+const HIDDEN_KEYS: [&str; 2] = ["win_width", "win_height"];
+
+/// Panel docked to the right of the simulation holding every tunable world
+/// blackboard entry, so the playfield itself stays free of UI, followed by the
+/// personal entries of the inspected boid when there is one
+pub struct ControlPanel {
+    pub open: bool,
+    pub width: f32,
+    selected: usize,
+}
+
+impl Default for ControlPanel {
+    fn default() -> Self {
+        ControlPanel {
+            open: false,
+            width: 320.0,
+            selected: 0,
+        }
+    }
+}
+
+impl ControlPanel {
+    /// Horizontal space the panel takes up next to the simulation
+    pub fn docked_width(&self) -> f32 {
+        if self.open { self.width } else { 0.0 }
+    }
+    fn keys(world: &Blackboard) -> Vec<String> {
+        let mut keys: Vec<String> = world
+            .iter()
+            .filter(|(k, v)| !HIDDEN_KEYS.contains(&k.as_str())
+                && matches!(v, BBValue::F32(_) | BBValue::Bool(_)))
+            .map(|(k, _)| k.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+    /// Up/down pick an entry, left/right change it, -/= resize the panel.
+    /// Entries past the world's belong to `boid`.
+    pub fn handle_keys(&mut self, just_pressed: &HashSet<VirtualKeyCode>, world: &mut Blackboard,
+                       boid: Option<&mut Blackboard>) {
+        if !self.open {
+            return;
+        }
+        let mut keys = Self::keys(world);
+        let world_count = keys.len();
+        if boid.is_some() {
+            keys.extend(TRAITS.iter().map(|k| k.to_string()));
+        }
+        if keys.is_empty() {
+            return;
+        }
+        if keymap::pressed(just_pressed, Command::PanelUp) {
+            self.selected = (self.selected + keys.len() - 1) % keys.len();
+        }
+        if keymap::pressed(just_pressed, Command::PanelDown) {
+            self.selected = (self.selected + 1) % keys.len();
+        }
+        self.selected = self.selected.min(keys.len() - 1);
+
+        let key = &keys[self.selected];
+        let up = keymap::pressed(just_pressed, Command::PanelIncrease);
+        let down = keymap::pressed(just_pressed, Command::PanelDecrease);
+        if up || down {
+            let db = match boid {
+                Some(db) if self.selected >= world_count => db,
+                _ => world,
+            };
+            let value = match db[key] {
+                BBValue::F32(0.0) => BBValue::F32(if up { 0.1 } else { 0.0 }),
+                BBValue::F32(v) => BBValue::F32(if up { v * ADJUST_FACTOR } else { v / ADJUST_FACTOR }),
+                BBValue::Bool(v) => BBValue::Bool(!v),
+                other => other,
+            };
+            db.insert(key.clone(), value);
+        }
+
+        if keymap::pressed(just_pressed, Command::PanelNarrow) {
+            self.width = (self.width - RESIZE_STEP).max(MIN_WIDTH);
+        }
+        if keymap::pressed(just_pressed, Command::PanelWiden) {
+            self.width = (self.width + RESIZE_STEP).min(MAX_WIDTH);
+        }
+    }
+    /// Draw the panel starting at `left`: the world's entries, those of `boid`
+    /// (its id and blackboard) if given, then the `stats` lines. Returns the y
+    /// where free space begins.
+    pub fn draw(&self, ctx: &mut Context, world: &Blackboard, boid: Option<(usize, &Blackboard)>,
+                stats: &[String], left: f32, height: f32) -> GameResult<f32> {
+        let background = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(left, 0.0, self.width, height),
+            [0.08, 0.1, 0.12, 1.0].into(),
+        )?;
+        graphics::draw(ctx, &background, graphics::DrawParam::new())?;
+
+        let mut text = graphics::Text::default();
+        text.add(graphics::TextFragment {
+            text: "controls (up/down, left/right)\n".to_string(),
+            color: Some(graphics::Color::WHITE),
+            font: Some(graphics::Font::default()),
+            scale: Some(graphics::PxScale::from(FONT_SIZE)),
+        });
+        let world_keys = Self::keys(world);
+        let boid_keys = boid.iter().flat_map(|(_, db)| TRAITS.iter().map(move |k| (k.to_string(), *db)));
+        let entries = world_keys.iter().map(|k| (k.clone(), world)).chain(boid_keys);
+        for (i, (key, db)) in entries.enumerate() {
+            if let Some((id, _)) = boid.filter(|_| i == world_keys.len()) {
+                text.add(graphics::TextFragment {
+                    text: format!("boid #{}\n", id),
+                    color: Some(graphics::Color::WHITE),
+                    font: Some(graphics::Font::default()),
+                    scale: Some(graphics::PxScale::from(FONT_SIZE)),
+                });
+            }
+            let value = match db[&key] {
+                BBValue::F32(v) => format!("{:.3}", v),
+                BBValue::Bool(v) => v.to_string(),
+                _ => continue,
+            };
+            let color = if i == self.selected {
+                graphics::Color::new(1.0, 0.8, 0.3, 1.0)
+            } else {
+                graphics::Color::new(0.8, 0.8, 0.8, 1.0)
+            };
+            text.add(graphics::TextFragment {
+                text: format!("{} {} = {}\n", if i == self.selected { ">" } else { " " }, key, value),
+                color: Some(color),
+                font: Some(graphics::Font::default()),
+                scale: Some(graphics::PxScale::from(FONT_SIZE)),
+            });
+        }
+        for line in stats {
+            text.add(graphics::TextFragment {
+                text: format!("{}\n", line),
+                color: Some(graphics::Color::new(0.8, 0.8, 0.8, 1.0)),
+                font: Some(graphics::Font::default()),
+                scale: Some(graphics::PxScale::from(FONT_SIZE)),
+            });
+        }
+        text.set_bounds(glam::vec2(self.width - 2.0 * MARGIN, height), graphics::Align::Left);
+        graphics::draw(ctx, &text, graphics::DrawParam::default().dest(glam::vec2(left + MARGIN, MARGIN)))?;
+        Ok(text.height(ctx) + 2.0 * MARGIN)
+    }
+}
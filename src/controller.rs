@@ -0,0 +1,100 @@
+//! Closed-loop control of an emergent property: a PID loop retunes one world
+//! factor until a flock-level measurement sits at its target
+use crate::blackboard::{BBValue, Blackboard, get_f32};
+
+/// Furthest the factor is scaled from where it started, as a power of e
+const MAX_SCALE: f32 = 3.0;
+
+#[derive(Clone, Debug, Default)]
+struct Pid {
+    integral: f32,
+    last_error: Option<f32>,
+}
+
+impl Pid {
+    /// Output for `error` after `dt` seconds with gains `[p, i, d]`
+    fn update(&mut self, error: f32, dt: f32, [p, i, d]: [f32; 3]) -> f32 {
+        self.integral += error * dt;
+        // no winding up past what the output can use
+        if i > 0.0 {
+            self.integral = self.integral.clamp(-MAX_SCALE / i, MAX_SCALE / i);
+        }
+        let derivative = match self.last_error {
+            Some(last) if dt > 0.0 => (error - last) / dt,
+            _ => 0.0,
+        };
+        self.last_error = Some(error);
+        p * error + i * self.integral + d * derivative
+    }
+}
+
+/// Loop scaling world entry `factor` up from where it was when the loop
+/// started for positive errors, and down for negative ones
+#[derive(Clone, Debug)]
+pub struct Loop {
+    pub factor: &'static str,
+    base: f32,
+    pid: Pid,
+}
+
+impl Loop {
+    pub fn start(factor: &'static str, world: &Blackboard) -> Loop {
+        Loop { factor, base: get_f32(world, factor), pid: Pid::default() }
+    }
+    /// Retune the factor for `error`, `dt` seconds after the last update
+    pub fn update(&mut self, world: &mut Blackboard, error: f32, dt: f32, gains: [f32; 3]) {
+        let output = self.pid.update(error, dt, gains).clamp(-MAX_SCALE, MAX_SCALE);
+        world.insert(self.factor.to_string(), BBValue::F32(self.base * output.exp()));
+    }
+    /// Put the factor back as it was before the loop started
+    pub fn stop(self, world: &mut Blackboard) {
+        world.insert(self.factor.to_string(), BBValue::F32(self.base));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world(factor: f32) -> Blackboard {
+        let mut world = Blackboard::new();
+        world.insert("avoid_factor".to_string(), BBValue::F32(factor));
+        world
+    }
+
+    #[test]
+    fn integral_stops_winding_up_at_what_the_output_can_use() {
+        let mut pid = Pid::default();
+        let gains = [0.0, 0.5, 0.0];
+        for _ in 0..1000 {
+            pid.update(10.0, 0.1, gains);
+        }
+        assert_eq!(pid.integral, MAX_SCALE / 0.5);
+        // so a few seconds of the opposite error turn the output round
+        let mut output = 0.0;
+        for _ in 0..70 {
+            output = pid.update(-1.0, 0.1, gains);
+        }
+        assert!(output < 0.0, "still wound up: {}", output);
+    }
+
+    #[test]
+    fn output_is_clamped_to_max_scale() {
+        let mut world = world(2.0);
+        let mut control = Loop::start("avoid_factor", &world);
+        control.update(&mut world, 100.0, 0.1, [1.0, 0.0, 0.0]);
+        assert_eq!(get_f32(&world, "avoid_factor"), 2.0 * MAX_SCALE.exp());
+        control.update(&mut world, -100.0, 0.1, [1.0, 0.0, 0.0]);
+        assert_eq!(get_f32(&world, "avoid_factor"), 2.0 * (-MAX_SCALE).exp());
+    }
+
+    #[test]
+    fn stop_restores_the_factor() {
+        let mut world = world(2.0);
+        let mut control = Loop::start("avoid_factor", &world);
+        control.update(&mut world, 0.4, 0.1, [1.0, 0.2, 0.1]);
+        assert_ne!(get_f32(&world, "avoid_factor"), 2.0);
+        control.stop(&mut world);
+        assert_eq!(get_f32(&world, "avoid_factor"), 2.0);
+    }
+}
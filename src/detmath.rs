@@ -0,0 +1,256 @@
+//! Transcendental functions used by the steering core.
+//!
+//! Basic float arithmetic and `sqrt` are correctly rounded on every platform,
+//! but `sin`, `atan2`, `ln` and friends come from the platform libm and can
+//! differ in the last bit. With `--features deterministic` these are replaced
+//! by polynomial versions built only from `+ - * /` and `sqrt`, evaluated in
+//! a fixed order in f64, so runs are bit-identical across platforms.
+//! Without the feature they forward to std.
+
+use crate::real::Real;
+
+#[cfg(not(feature = "deterministic"))]
+pub fn sin_cos(x: Real) -> (Real, Real) {
+    x.sin_cos()
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub fn atan2(y: Real, x: Real) -> Real {
+    y.atan2(x)
+}
+
+#[cfg(not(feature = "deterministic"))]
+pub fn ln(x: Real) -> Real {
+    x.ln()
+}
+
+#[cfg(feature = "deterministic")]
+pub fn sin_cos(x: Real) -> (Real, Real) {
+    let (s, c) = portable::sin_cos(widen(x));
+    (narrow(s), narrow(c))
+}
+
+#[cfg(feature = "deterministic")]
+pub fn atan2(y: Real, x: Real) -> Real {
+    narrow(portable::atan2(widen(y), widen(x)))
+}
+
+#[cfg(feature = "deterministic")]
+pub fn ln(x: Real) -> Real {
+    narrow(portable::ln(widen(x)))
+}
+
+#[cfg(feature = "deterministic")]
+#[allow(clippy::unnecessary_cast)]
+fn widen(x: Real) -> f64 {
+    x as f64
+}
+
+#[cfg(feature = "deterministic")]
+#[allow(clippy::unnecessary_cast)]
+fn narrow(x: f64) -> Real {
+    x as Real
+}
+
+#[cfg(feature = "deterministic")]
+mod portable {
+    use std::f64::consts::{FRAC_PI_2, LN_2, PI, SQRT_2, TAU};
+
+    /// Horner evaluation of `c[0] + c[1] x + c[2] x^2 + ...`
+    fn poly(x: f64, c: &[f64]) -> f64 {
+        c.iter().rev().fold(0.0, |acc, &k| acc * x + k)
+    }
+
+    /// Taylor series for sin and cos on [-pi/4, pi/4]
+    fn sin_cos_reduced(x: f64) -> (f64, f64) {
+        let x2 = x * x;
+        let s = x * poly(x2, &[
+            1.0,
+            -1.0 / 6.0,
+            1.0 / 120.0,
+            -1.0 / 5040.0,
+            1.0 / 362880.0,
+            -1.0 / 39916800.0,
+            1.0 / 6227020800.0,
+            -1.0 / 1307674368000.0,
+        ]);
+        let c = poly(x2, &[
+            1.0,
+            -1.0 / 2.0,
+            1.0 / 24.0,
+            -1.0 / 720.0,
+            1.0 / 40320.0,
+            -1.0 / 3628800.0,
+            1.0 / 479001600.0,
+            -1.0 / 87178291200.0,
+            1.0 / 20922789888000.0,
+        ]);
+        (s, c)
+    }
+
+    pub fn sin_cos(x: f64) -> (f64, f64) {
+        // into [-pi, pi], then by quadrant into [-pi/4, pi/4]
+        let x = x - (x / TAU).round() * TAU;
+        let quadrant = (x / FRAC_PI_2).round();
+        let (s, c) = sin_cos_reduced(x - quadrant * FRAC_PI_2);
+        match quadrant as i32 {
+            1 => (c, -s),
+            -1 => (-c, s),
+            2 | -2 => (-s, -c),
+            _ => (s, c),
+        }
+    }
+
+    /// atan for |t| <= 1
+    fn atan_unit(t: f64) -> f64 {
+        // three argument halvings, atan(t) = 2 atan(t / (1 + sqrt(1 + t^2))), leave |t| < 0.1
+        let mut t = t;
+        for _ in 0..3 {
+            t /= 1.0 + (1.0 + t * t).sqrt();
+        }
+        let t2 = t * t;
+        let series = t * poly(t2, &[
+            1.0,
+            -1.0 / 3.0,
+            1.0 / 5.0,
+            -1.0 / 7.0,
+            1.0 / 9.0,
+            -1.0 / 11.0,
+            1.0 / 13.0,
+            -1.0 / 15.0,
+            1.0 / 17.0,
+        ]);
+        series * 8.0
+    }
+
+    pub fn atan2(y: f64, x: f64) -> f64 {
+        if x == 0.0 && y == 0.0 {
+            return 0.0;
+        }
+        let angle = if x.abs() >= y.abs() {
+            atan_unit(y.abs() / x.abs())
+        } else {
+            FRAC_PI_2 - atan_unit(x.abs() / y.abs())
+        };
+        let angle = if x < 0.0 { PI - angle } else { angle };
+        if y < 0.0 { -angle } else { angle }
+    }
+
+    /// Natural log of a positive normal number
+    pub fn ln(x: f64) -> f64 {
+        if x <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        // x = m * 2^e with m in [sqrt(1/2), sqrt(2))
+        let bits = x.to_bits();
+        let mut e = ((bits >> 52) & 0x7ff) as i64 - 1023;
+        let mut m = f64::from_bits((bits & 0x000f_ffff_ffff_ffff) | 0x3ff0_0000_0000_0000);
+        if m > SQRT_2 {
+            m /= 2.0;
+            e += 1;
+        }
+        // ln m = 2 atanh(s), s = (m - 1) / (m + 1), |s| < 0.172
+        let s = (m - 1.0) / (m + 1.0);
+        let s2 = s * s;
+        let atanh = s * poly(s2, &[
+            1.0,
+            1.0 / 3.0,
+            1.0 / 5.0,
+            1.0 / 7.0,
+            1.0 / 9.0,
+            1.0 / 11.0,
+            1.0 / 13.0,
+            1.0 / 15.0,
+            1.0 / 17.0,
+            1.0 / 19.0,
+            1.0 / 21.0,
+        ]);
+        e as f64 * LN_2 + 2.0 * atanh
+    }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Far below f32 resolution, so narrowing hides any difference from std
+        const TOLERANCE: f64 = 1e-12;
+
+        fn samples(from: f64, to: f64, n: usize) -> impl Iterator<Item = f64> {
+            (0..=n).map(move |i| from + (to - from) * i as f64 / n as f64)
+        }
+
+        #[test]
+        fn sin_cos_close_to_std() {
+            // headings, and the golden-angle spread of boid ids; reducing by
+            // a rounded TAU loses precision in proportion to x
+            for x in samples(-10_000.0, 10_000.0, 100_000) {
+                let (s, c) = sin_cos(x);
+                let bound = TOLERANCE * x.abs().max(1.0);
+                assert!((s - x.sin()).abs() < bound, "sin {}", x);
+                assert!((c - x.cos()).abs() < bound, "cos {}", x);
+            }
+        }
+
+        #[test]
+        fn atan2_close_to_std() {
+            for y in samples(-500.0, 500.0, 300) {
+                for x in samples(-500.0, 500.0, 300) {
+                    assert!((atan2(y, x) - y.atan2(x)).abs() < TOLERANCE, "atan2 {} {}", y, x);
+                }
+            }
+        }
+
+        #[test]
+        fn ln_close_to_std() {
+            // uniform samples in (0, 1] for gaussian noise, and a few above
+            for x in samples(1e-12, 1.0, 100_000).chain(samples(1.0, 1e6, 1000)) {
+                assert!((ln(x) - x.ln()).abs() < TOLERANCE * x.ln().abs().max(1.0), "ln {}", x);
+            }
+        }
+
+        #[test]
+        fn bit_identical_to_reference() {
+            // what the polynomials give on x86-64; a platform or run that gets
+            // other bits would break lockstep sessions and replays
+            let (s, c) = sin_cos(2.5);
+            assert_eq!([s.to_bits(), c.to_bits()], [0x3fe3_26af_0dcf_caaf, 0xbfe9_a2f7_ef85_8b7e]);
+            assert_eq!(atan2(-3.0, 7.0).to_bits(), 0xbfd9_e9bf_3d20_dc71);
+            assert_eq!(ln(0.3).to_bits(), 0xbff3_4378_fcbd_a720);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::real::real;
+
+    /// Within f32 rounding of std, whichever build this is
+    const TOLERANCE: f64 = 1e-5;
+
+    #[test]
+    #[allow(clippy::unnecessary_cast)]
+    fn close_to_std_in_the_simulation_scalar() {
+        for i in -1000..=1000 {
+            let x = real(i as f32 * 0.37);
+            let (s, c) = sin_cos(x);
+            assert!((s as f64 - (x as f64).sin()).abs() < TOLERANCE, "sin {}", x);
+            assert!((c as f64 - (x as f64).cos()).abs() < TOLERANCE, "cos {}", x);
+            let y = real(i as f32 * 0.5 - 3.0);
+            assert!((atan2(y, x) as f64 - (y as f64).atan2(x as f64)).abs() < TOLERANCE, "atan2 {} {}", y, x);
+        }
+        for i in 1..=1000 {
+            let x = real(i as f32 / 1000.0);
+            assert!((ln(x) as f64 - (x as f64).ln()).abs() < TOLERANCE, "ln {}", x);
+        }
+    }
+
+    #[test]
+    fn same_result_every_call() {
+        for i in 0..100 {
+            let x = real(i as f32 * 1.3 - 60.0);
+            assert_eq!(sin_cos(x), sin_cos(x));
+            assert_eq!(atan2(x, 1.0).to_bits(), atan2(x, 1.0).to_bits());
+            assert_eq!(ln(x.abs() + 1.0).to_bits(), ln(x.abs() + 1.0).to_bits());
+        }
+    }
+}
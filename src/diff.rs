@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+use crate::replay::{self, ReplayError, ReplayFrame, ReplayHeader};
+use crate::rewind::BoidState;
+
+/// Differences at or below this are treated as float noise
+const EPSILON: f32 = 1e-4;
+
+/// How one boid differs between the two files at a given tick
+pub struct BoidDiff {
+    pub id: usize,
+    pub position: f32,
+    pub velocity: f32,
+    pub color: bool,
+}
+
+fn boid_diff(a: &BoidState, b: &BoidState) -> BoidDiff {
+    BoidDiff {
+        id: a.id,
+        position: glam::vec2(a.x - b.x, a.y - b.y).length(),
+        velocity: glam::vec2(a.dx - b.dx, a.dy - b.dy).length(),
+        color: a.color != b.color,
+    }
+}
+
+/// Boids that differ between two frames, matched by id, plus any id found in only one
+fn frame_diff(a: &ReplayFrame, b: &ReplayFrame) -> (Vec<BoidDiff>, Vec<usize>) {
+    let by_id: HashMap<usize, &BoidState> = b.boids.iter().map(|s| (s.id, s)).collect();
+    let mut diffs = vec![];
+    let mut unmatched = vec![];
+    for state in &a.boids {
+        match by_id.get(&state.id) {
+            Some(other) => {
+                let d = boid_diff(state, other);
+                if d.position > EPSILON || d.velocity > EPSILON || d.color {
+                    diffs.push(d);
+                }
+            }
+            None => unmatched.push(state.id),
+        }
+    }
+    let ids: Vec<usize> = a.boids.iter().map(|s| s.id).collect();
+    unmatched.extend(b.boids.iter().map(|s| s.id).filter(|id| !ids.contains(id)));
+    diffs.sort_by(|x, y| y.position.total_cmp(&x.position));
+    (diffs, unmatched)
+}
+
+fn load(path: &str) -> Result<(ReplayHeader, Vec<ReplayFrame>), ReplayError> {
+    replay::read(File::open(path)?)
+}
+
+/// Compare two saved states or replays tick by tick. Returns whether they match.
+pub fn run(a_path: &str, b_path: &str) -> Result<bool, ReplayError> {
+    let (a_header, a) = load(a_path)?;
+    let (b_header, b) = load(b_path)?;
+
+    if a_header.seed != b_header.seed {
+        println!("seed: {} vs {}", a_header.seed, b_header.seed);
+    }
+    if a_header.config_hash != b_header.config_hash {
+        println!("config hash: {:016x} vs {:016x}", a_header.config_hash, b_header.config_hash);
+    }
+    if a.len() != b.len() {
+        println!("frames: {} vs {}, comparing the first {}", a.len(), b.len(), a.len().min(b.len()));
+    }
+
+    let mut divergence = None;
+    let mut max_delta = (0.0, 0);
+    for (tick, (fa, fb)) in a.iter().zip(&b).enumerate() {
+        let (diffs, unmatched) = frame_diff(fa, fb);
+        if let Some(d) = diffs.first() {
+            if d.position > max_delta.0 {
+                max_delta = (d.position, tick);
+            }
+        }
+        if divergence.is_none() && (!diffs.is_empty() || !unmatched.is_empty()) {
+            divergence = Some((tick, diffs, unmatched));
+        }
+    }
+
+    match divergence {
+        None => {
+            println!("identical");
+            Ok(a.len() == b.len())
+        }
+        Some((tick, diffs, unmatched)) => {
+            println!("diverged at tick {}", tick);
+            println!("max positional delta {:.4} at tick {}", max_delta.0, max_delta.1);
+            if !unmatched.is_empty() {
+                println!("boids in only one file: {:?}", unmatched);
+            }
+            println!("{:>6} {:>10} {:>10} {:>6}", "id", "position", "velocity", "color");
+            for d in &diffs {
+                println!("{:>6} {:>10.4} {:>10.4} {:>6}", d.id, d.position, d.velocity, if d.color { "yes" } else { "" });
+            }
+            Ok(false)
+        }
+    }
+}
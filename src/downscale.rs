@@ -0,0 +1,62 @@
+//! Resolution-scaled rendering: the world is drawn into an offscreen canvas a
+//! fraction of the window's size and stretched back up to fill it, trading
+//! sharpness for fill rate on weak GPUs. Text and panels stay sharp, drawn
+//! after the world straight onto the window.
+use ggez::{conf, Context, GameResult, graphics};
+
+/// Share of the window's resolution used when none is given
+pub const DEFAULT_SCALE: f32 = 0.5;
+/// Smallest share allowed, so the canvas keeps at least a few pixels
+pub const MIN_SCALE: f32 = 0.1;
+
+pub struct Downscale {
+    /// Share of the window's resolution the world is drawn at
+    pub scale: f32,
+    pub enabled: bool,
+    /// Created on first use and again whenever the window or the scale changes
+    canvas: Option<graphics::Canvas>,
+    /// Whether drawing is going to the canvas
+    capturing: bool,
+}
+
+impl Default for Downscale {
+    fn default() -> Downscale {
+        Downscale { scale: DEFAULT_SCALE, enabled: false, canvas: None, capturing: false }
+    }
+}
+
+impl Downscale {
+    /// Send drawing to the small canvas, cleared to `background`, until `finish`.
+    /// Does nothing unless enabled at a scale below full resolution.
+    pub fn begin(&mut self, ctx: &mut Context, background: graphics::Color) -> GameResult {
+        if !self.enabled || self.scale >= 1.0 {
+            return Ok(());
+        }
+        let (width, height) = graphics::drawable_size(ctx);
+        let size = ((width * self.scale).max(1.0) as u16, (height * self.scale).max(1.0) as u16);
+        let stale = self.canvas.as_ref().is_none_or(|c| (c.width(), c.height()) != size);
+        if stale {
+            let format = graphics::get_window_color_format(ctx);
+            let mut canvas = graphics::Canvas::new(ctx, size.0, size.1, conf::NumSamples::One, format)?;
+            canvas.set_filter(graphics::FilterMode::Linear);
+            self.canvas = Some(canvas);
+        }
+        graphics::set_canvas(ctx, self.canvas.as_ref());
+        graphics::clear(ctx, background);
+        self.capturing = true;
+        Ok(())
+    }
+    /// Go back to drawing on the window, stretching what was drawn to fill it
+    pub fn finish(&mut self, ctx: &mut Context) -> GameResult {
+        if !std::mem::take(&mut self.capturing) {
+            return Ok(());
+        }
+        graphics::set_canvas(ctx, None);
+        let Some(canvas) = &self.canvas else { return Ok(()) };
+        let screen = graphics::screen_coordinates(ctx);
+        let param = graphics::DrawParam::default()
+            .dest(glam::vec2(screen.x, screen.y))
+            .scale(glam::vec2(screen.w / canvas.width() as f32, screen.h / canvas.height() as f32));
+        graphics::draw(ctx, canvas, param)
+    }
+}
@@ -0,0 +1,70 @@
+//! SIR epidemic among the flock: boids in contact pass an infection on, and
+//! the infected recover after a while, immune from then on
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::boid::Boid;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Health {
+    #[default]
+    Susceptible,
+    /// Seconds left until recovery
+    Infected(f32),
+    Recovered,
+}
+
+impl Health {
+    pub const COLORS: [[f32; 4]; 3] = [[0.5, 0.7, 1.0, 1.0], [1.0, 0.25, 0.2, 1.0], [0.55, 0.55, 0.55, 1.0]];
+    /// Position in `COLORS` and in the counts
+    pub fn index(self) -> usize {
+        match self {
+            Health::Susceptible => 0,
+            Health::Infected(_) => 1,
+            Health::Recovered => 2,
+        }
+    }
+}
+
+/// How many boids are susceptible, infected and recovered
+pub fn counts(boids: &[Boid]) -> [usize; 3] {
+    let mut counts = [0; 3];
+    for boid in boids {
+        counts[boid.health.index()] += 1;
+    }
+    counts
+}
+
+/// Infect one boid picked at random, sick for `recovery_time` seconds
+pub fn infect_one(boids: &mut [Boid], recovery_time: f32, rng: &mut StdRng) {
+    if !boids.is_empty() {
+        let i = rng.gen_range(0..boids.len());
+        boids[i].health = Health::Infected(recovery_time);
+    }
+}
+
+/// Advance the epidemic by `dt` seconds. A susceptible boid within an
+/// infected one's min_distance catches it at `rate` per second of contact,
+/// and the infected recover once their time is up.
+pub fn spread(boids: &mut [Boid], dt: f32, rate: f32, recovery_time: f32, rng: &mut StdRng) {
+    let carriers: Vec<(glam::Vec2, f32)> = boids
+        .iter_mut()
+        .filter(|b| matches!(b.health, Health::Infected(_)))
+        .map(|b| (b.pos(), b.min_distance()))
+        .collect();
+    let chance = (rate * dt).min(1.0);
+    for boid in boids.iter_mut() {
+        match boid.health {
+            Health::Susceptible => {
+                let pos = boid.pos();
+                let contacts = carriers.iter().filter(|(at, range)| at.distance(pos) < *range).count();
+                if (0..contacts).any(|_| rng.gen::<f32>() < chance) {
+                    boid.health = Health::Infected(recovery_time);
+                }
+            }
+            Health::Infected(left) if left <= dt => boid.health = Health::Recovered,
+            Health::Infected(left) => boid.health = Health::Infected(left - dt),
+            Health::Recovered => {}
+        }
+    }
+}
@@ -0,0 +1,37 @@
+/// Event passed between boids through the simulation
+#[derive(Clone, Debug)]
+pub enum SimEvent {
+    /// Boid at `pos` warns its neighbors about a threat at `threat`.
+    /// `hops` is how many more times the alarm may be relayed.
+    Alarm {
+        pos: glam::Vec2,
+        threat: glam::Vec2,
+        hops: u32,
+    },
+}
+
+/// Per-tick event bus. Events emitted during a tick are delivered on the
+/// next one, so signals spread at most one hop per tick.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    delivered: Vec<SimEvent>,
+    pending: Vec<SimEvent>,
+}
+
+impl EventBus {
+    pub fn emit(&mut self, event: SimEvent) {
+        self.pending.push(event);
+    }
+    /// Events emitted during the previous tick
+    pub fn events(&self) -> &[SimEvent] {
+        &self.delivered
+    }
+    /// Deliver this tick's events and start collecting the next batch
+    pub fn advance(&mut self) {
+        self.delivered = std::mem::take(&mut self.pending);
+    }
+    pub fn clear(&mut self) {
+        self.delivered.clear();
+        self.pending.clear();
+    }
+}
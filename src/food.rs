@@ -0,0 +1,61 @@
+use rand::Rng;
+
+/// Patch boids refill their energy at
+#[derive(Clone, Copy, Debug)]
+pub struct Patch {
+    pub pos: glam::Vec2,
+    /// Energy left to eat
+    pub amount: f32,
+}
+
+/// Food patches scattered over the world; grazed patches grow back slowly
+#[derive(Clone, Debug, Default)]
+pub struct FoodPatches {
+    pub patches: Vec<Patch>,
+}
+
+impl FoodPatches {
+    /// Replace the patches with `count` full ones at random spots away from the edges
+    pub fn scatter<R: Rng>(&mut self, count: usize, width: f32, height: f32, capacity: f32, rng: &mut R) {
+        self.patches.clear();
+        for _ in 0..count {
+            let pos = glam::vec2(
+                (0.1 + rng.gen::<f32>() * 0.8) * width,
+                (0.1 + rng.gen::<f32>() * 0.8) * height);
+            self.patches.push(Patch { pos, amount: capacity });
+        }
+    }
+    pub fn clear(&mut self) {
+        self.patches.clear();
+    }
+    /// Closest patch with anything left on it
+    pub fn nearest(&self, pos: glam::Vec2) -> Option<glam::Vec2> {
+        self.patches
+            .iter()
+            .filter(|p| p.amount > 0.0)
+            .map(|p| p.pos)
+            .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+    }
+    /// Take up to `want` energy from the patches within `radius` of `pos`,
+    /// returning how much was eaten
+    pub fn eat(&mut self, pos: glam::Vec2, radius: f32, want: f32) -> f32 {
+        let mut eaten = 0.0;
+        for patch in &mut self.patches {
+            if eaten >= want {
+                break;
+            }
+            if patch.pos.distance(pos) < radius {
+                let bite = patch.amount.min(want - eaten);
+                patch.amount -= bite;
+                eaten += bite;
+            }
+        }
+        eaten
+    }
+    /// Grow every patch back by `rate` per second, up to `capacity`
+    pub fn regrow(&mut self, dt: f32, rate: f32, capacity: f32) {
+        for patch in &mut self.patches {
+            patch.amount = (patch.amount + rate * dt).min(capacity);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Formation flight: boids take up the upwash slots behind and beside the
+//! boid ahead of them, which lines the flock up into Vs and echelons
+use crate::boid::Boid;
+
+/// Place a boid in formation should fly, and how fast its leader is going
+#[derive(Clone, Copy, Debug)]
+pub struct Slot {
+    pub pos: glam::Vec2,
+    pub leader_velocity: glam::Vec2,
+}
+
+/// Give each boid, indexed like `boids`, a free slot behind a boid ahead of
+/// it within `range`; leaders and stragglers get none. Every boid has two
+/// slots, `spacing` back and to either side, and each takes one follower.
+/// Boids claim slots front to back along the flock's heading, each the
+/// nearest one still free, so chains grow backwards from the leaders.
+pub fn assign_slots(boids: &[Boid], range: f32, spacing: f32) -> Vec<Option<Slot>> {
+    let heading = boids.iter().fold(glam::Vec2::ZERO, |sum, b| sum + b.vel()).normalize_or_zero();
+    let mut order: Vec<usize> = (0..boids.len()).collect();
+    order.sort_by(|&a, &b| boids[b].pos().dot(heading).total_cmp(&boids[a].pos().dot(heading)));
+
+    let mut taken = vec![[false; 2]; boids.len()];
+    let mut slots = vec![None; boids.len()];
+    for (rank, &i) in order.iter().enumerate() {
+        let follower = &boids[i];
+        let (pos, forward) = (follower.pos(), follower.vel().normalize_or_zero());
+        let mut best: Option<(f32, usize, usize, glam::Vec2)> = None;
+        for &j in &order[..rank] {
+            let leader = &boids[j];
+            if (leader.pos() - pos).dot(forward) <= 0.0 || leader.pos().distance(pos) > range {
+                continue;
+            }
+            let back = leader.vel().normalize_or_zero();
+            for (side, offset) in [back.perp(), -back.perp()].into_iter().enumerate() {
+                let slot = leader.pos() - back * spacing + offset * spacing;
+                let dist = slot.distance(pos);
+                if !taken[j][side] && best.is_none_or(|(d, ..)| dist < d) {
+                    best = Some((dist, j, side, slot));
+                }
+            }
+        }
+        if let Some((_, j, side, slot)) = best {
+            taken[j][side] = true;
+            slots[i] = Some(Slot { pos: slot, leader_velocity: boids[j].vel() });
+        }
+    }
+    slots
+}
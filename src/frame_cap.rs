@@ -0,0 +1,46 @@
+//! Frame-rate cap: sleeps out the rest of each frame so fast machines don't
+//! spend a full core drawing frames nobody sees. Vsync is the other way to
+//! hold the rate down, but ggez only sets it when the window is made, so it
+//! is chosen at launch (--no-vsync) while the cap can change at any time.
+use std::time::{Duration, Instant};
+
+/// Caps the cycle key steps through, in frames per second; `None` is uncapped
+pub const CAPS: &[Option<u32>] = &[None, Some(30), Some(60), Some(120), Some(144)];
+/// Frame rate while the window sits paused in the background
+pub const BACKGROUND_FPS: u32 = 10;
+
+#[derive(Default)]
+pub struct FrameCap {
+    /// Frames per second to hold to, if any
+    pub fps: Option<u32>,
+    /// Hold to `BACKGROUND_FPS` instead, when that is lower
+    pub background: bool,
+    /// When the last frame was let through
+    last: Option<Instant>,
+}
+
+impl FrameCap {
+    /// Sleep until a whole frame at the cap has passed since the last call
+    pub fn wait(&mut self) {
+        let fps = match self.fps {
+            _ if !self.background => self.fps,
+            Some(fps) => Some(fps.min(BACKGROUND_FPS)),
+            None => Some(BACKGROUND_FPS),
+        };
+        if let (Some(fps), Some(last)) = (fps.filter(|&f| f > 0), self.last) {
+            let frame = Duration::from_secs_f64(1.0 / fps as f64);
+            if let Some(rest) = frame.checked_sub(last.elapsed()) {
+                std::thread::sleep(rest);
+            }
+        }
+        self.last = Some(Instant::now());
+    }
+    /// Step to the next cap in `CAPS`, after the first one above a cap not in the list
+    pub fn cycle(&mut self) {
+        let next = match CAPS.iter().position(|&c| c == self.fps) {
+            Some(i) => CAPS[(i + 1) % CAPS.len()],
+            None => CAPS.iter().copied().find(|c| c.zip(self.fps).is_some_and(|(c, fps)| c > fps)).flatten(),
+        };
+        self.fps = next;
+    }
+}
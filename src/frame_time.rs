@@ -0,0 +1,28 @@
+//! Frame time as the simulation sees it: each frame's delta is clamped, so a
+//! hitch (a window drag, another app stalling the machine) can't fling boids
+//! across the world or blow up the steering, and then averaged over the last
+//! few frames to even out vsync jitter.
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Longest step a single frame may take, in seconds
+pub const MAX_DT: f32 = 0.1;
+/// Frames averaged over
+pub const SMOOTHING_FRAMES: usize = 8;
+
+#[derive(Default)]
+pub struct FrameTime {
+    /// Clamped deltas of the last `SMOOTHING_FRAMES` frames, oldest first
+    recent: VecDeque<f32>,
+}
+
+impl FrameTime {
+    /// Clamp `delta`, add it to the window and return the window's average
+    pub fn smooth(&mut self, delta: Duration) -> Duration {
+        if self.recent.len() == SMOOTHING_FRAMES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(delta.as_secs_f32().min(MAX_DT));
+        Duration::from_secs_f32(self.recent.iter().sum::<f32>() / self.recent.len() as f32)
+    }
+}
@@ -0,0 +1,48 @@
+//! Boid-sized lengths for hit-testing and drawing marks around boids, worked
+//! out from OBJECT_SIZE and the display scale each time the layout changes
+use crate::{OBJECT_SIZE, PIXELS_PER_UNIT};
+
+/// Smallest a boid-sized target gets on the display, in physical pixels, so
+/// boids stay clickable and their marks visible in a window scaled right down
+const MIN_PIXELS: f32 = 16.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Geometry {
+    /// Boid size the lengths below are measured from, in world units
+    pub object_size: f32,
+    /// How close a click must land to a boid to pick it
+    pub pick_radius: f32,
+    /// Boids this far outside the view still get drawn, so that a big one,
+    /// or its flash ripple, doesn't pop in at the edge
+    pub cull_margin: f32,
+}
+
+impl Geometry {
+    /// Lengths for a layout drawn at `scale` physical pixels per logical pixel,
+    /// with `pixels_per_unit` logical pixels per world unit
+    pub fn new(scale: f32, pixels_per_unit: f32) -> Geometry {
+        let object_size = OBJECT_SIZE.max(MIN_PIXELS / (scale * pixels_per_unit));
+        Geometry { object_size, pick_radius: object_size / 2.0, cull_margin: object_size * 3.0 }
+    }
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry::new(1.0, PIXELS_PER_UNIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_object_size_until_too_small_to_hit() {
+        let full = Geometry::new(2.0, 1.0);
+        assert_eq!((full.pick_radius, full.cull_margin), (OBJECT_SIZE / 2.0, OBJECT_SIZE * 3.0));
+        let (scale, pixels_per_unit) = (0.1, 0.5);
+        let tiny = Geometry::new(scale, pixels_per_unit);
+        assert_eq!(tiny.pick_radius * 2.0 * scale * pixels_per_unit, MIN_PIXELS);
+        assert_eq!(tiny.cull_margin, tiny.object_size * 3.0);
+    }
+}
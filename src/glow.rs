@@ -0,0 +1,131 @@
+//! Glow post-process: the world is drawn into an offscreen scene canvas, its
+//! bright pixels are blurred across in one shader pass and down in another,
+//! and the blur is added back over the scene so boids glow against the dark.
+use ggez::{Context, GameResult, graphics};
+use ggez::graphics::{BlendMode, Canvas, Drawable, EmptyConst, Shader};
+
+/// Same as ggez's own vertex shader
+const VERTEX_SHADER: &str = "#version 150 core
+
+in vec2 a_Pos;
+in vec2 a_Uv;
+in vec4 a_VertColor;
+
+in vec4 a_Src;
+in vec4 a_TCol1;
+in vec4 a_TCol2;
+in vec4 a_TCol3;
+in vec4 a_TCol4;
+in vec4 a_Color;
+
+layout (std140) uniform Globals {
+    mat4 u_MVP;
+};
+
+out vec2 v_Uv;
+out vec4 v_Color;
+
+void main() {
+    v_Uv = a_Uv * a_Src.zw + a_Src.xy;
+    v_Color = a_Color * a_VertColor;
+    mat4 instance_transform = mat4(a_TCol1, a_TCol2, a_TCol3, a_TCol4);
+    vec4 position = instance_transform * vec4(a_Pos, 0.0, 1.0);
+
+    gl_Position = u_MVP * position;
+}";
+
+/// Nine-tap gaussian blur along DIRECTION (in texels) of whatever SAMPLE reads
+const BLUR_SHADER: &str = "#version 150 core
+
+uniform sampler2D t_Texture;
+in vec2 v_Uv;
+in vec4 v_Color;
+out vec4 Target0;
+
+layout (std140) uniform Globals {
+    mat4 u_MVP;
+};
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+vec4 plain(vec2 uv) {
+    return texture(t_Texture, uv);
+}
+
+// only pixels brighter than mid-gray glow, fading in above it
+vec4 bright(vec2 uv) {
+    vec4 c = texture(t_Texture, uv);
+    return c * smoothstep(0.3, 0.7, dot(c.rgb, vec3(0.2126, 0.7152, 0.0722)));
+}
+
+void main() {
+    vec2 step = DIRECTION / vec2(textureSize(t_Texture, 0));
+    vec4 sum = SAMPLE(v_Uv) * WEIGHTS[0];
+    for (int i = 1; i < 5; i++) {
+        sum += (SAMPLE(v_Uv + step * float(i)) + SAMPLE(v_Uv - step * float(i))) * WEIGHTS[i];
+    }
+    Target0 = sum * v_Color;
+}";
+
+pub struct Glow {
+    /// Bright pass and blur across, then blur down
+    across: Shader<EmptyConst>,
+    down: Shader<EmptyConst>,
+    /// The scene, and its highlights blurred across; made to the window's size
+    canvases: Option<(Canvas, Canvas)>,
+    /// Whether drawing is going to the scene canvas
+    capturing: bool,
+}
+
+impl Glow {
+    /// Compile the blur shaders; fails on backends without GLSL 1.50
+    pub fn new(ctx: &mut Context) -> GameResult<Glow> {
+        let blur = |direction: &str, sample: &str| {
+            BLUR_SHADER.replace("DIRECTION", direction).replace("SAMPLE", sample)
+        };
+        let across = Shader::from_u8(ctx, VERTEX_SHADER.as_bytes(), blur("vec2(2.0, 0.0)", "bright").as_bytes(),
+                                     EmptyConst, "glow across", None)?;
+        let down = Shader::from_u8(ctx, VERTEX_SHADER.as_bytes(), blur("vec2(0.0, 2.0)", "plain").as_bytes(),
+                                   EmptyConst, "glow down", Some(&[BlendMode::Add, BlendMode::Alpha]))?;
+        Ok(Glow { across, down, canvases: None, capturing: false })
+    }
+    /// Send drawing to the scene canvas, cleared to `background`, until `finish`
+    pub fn begin(&mut self, ctx: &mut Context, background: graphics::Color) -> GameResult {
+        let (width, height) = graphics::drawable_size(ctx);
+        let stale = self.canvases.as_ref().is_none_or(|(scene, _)| {
+            (scene.width(), scene.height()) != (width as u16, height as u16)
+        });
+        if stale {
+            let mut blurred = Canvas::with_window_size(ctx)?;
+            blurred.set_blend_mode(Some(BlendMode::Add));
+            self.canvases = Some((Canvas::with_window_size(ctx)?, blurred));
+        }
+        let (scene, _) = self.canvases.as_ref().unwrap();
+        graphics::set_canvas(ctx, Some(scene));
+        graphics::clear(ctx, background);
+        self.capturing = true;
+        Ok(())
+    }
+    /// Go back to drawing on the screen, putting the scene there with its glow
+    pub fn finish(&mut self, ctx: &mut Context) -> GameResult {
+        if !std::mem::take(&mut self.capturing) {
+            return Ok(());
+        }
+        let (scene, blurred) = self.canvases.as_ref().unwrap();
+        let screen = graphics::screen_coordinates(ctx);
+        let param = graphics::DrawParam::default()
+            .dest(glam::vec2(screen.x, screen.y))
+            .scale(glam::vec2(screen.w / scene.width() as f32, screen.h / scene.height() as f32));
+
+        graphics::set_canvas(ctx, Some(blurred));
+        graphics::clear(ctx, graphics::Color::new(0.0, 0.0, 0.0, 0.0));
+        {
+            let _lock = graphics::use_shader(ctx, &self.across);
+            graphics::draw(ctx, scene, param)?;
+        }
+        graphics::set_canvas(ctx, None);
+        graphics::draw(ctx, scene, param)?;
+        let _lock = graphics::use_shader(ctx, &self.down);
+        graphics::draw(ctx, blurred, param)
+    }
+}
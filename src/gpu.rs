@@ -0,0 +1,352 @@
+//! Compute-shader flock (`--features gpu`): the three flocking rules and the
+//! world bounds run in a wgpu compute shader, src/gpu.wgsl, over every boid at
+//! once, for flocks far past what the behavior trees can step. Only positions
+//! and velocities live on the GPU, read back after each tick; nothing the
+//! trees drive (food, roosts, alarms, ...) runs here.
+//!
+//! `--gpu [boids=N] [ticks=N] [seed=S]` runs a headless flock and reports
+//! the time per tick.
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use wgpu::util::DeviceExt;
+
+use crate::blackboard::{Blackboard, get_f32};
+use crate::boid::{Boid, SPEED_LIMIT};
+use crate::simulation;
+use crate::{WORLD_HEIGHT, WORLD_WIDTH};
+
+const GPU_DT: f32 = 1.0 / 60.0; // Seconds
+/// Boids per workgroup; TILE in the shader
+const WORKGROUP: u32 = 256;
+/// Bytes per boid: x, y, dx, dy
+const STATE_SIZE: u64 = 16;
+
+/// Options for `--gpu [boids=N] [ticks=N] [seed=S]`
+pub struct GpuConfig {
+    pub boids: usize,
+    pub ticks: usize,
+    pub seed: u64,
+}
+
+impl GpuConfig {
+    pub fn parse(args: &[String]) -> Result<GpuConfig, String> {
+        let mut config = GpuConfig { boids: 100_000, ticks: 600, seed: 0 };
+        for arg in args {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", arg))?;
+            let bad = |_| format!("invalid value for {}: '{}'", key, value);
+            match key {
+                "boids" => config.boids = value.parse().map_err(bad)?,
+                "ticks" => config.ticks = value.parse().map_err(bad)?,
+                "seed" => config.seed = value.parse().map_err(bad)?,
+                _ => return Err(format!("unknown gpu option '{}'", key)),
+            }
+        }
+        if config.boids == 0 {
+            return Err("expected at least one boid".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/// Flocking parameters, laid out as the shader's `Params`
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    pub count: u32,
+    pub dt: f32,
+    pub width: f32,
+    pub height: f32,
+    pub visual_range: f32,
+    pub min_distance: f32,
+    pub avoid_factor: f32,
+    pub centering_factor: f32,
+    pub matching_factor: f32,
+    pub edge_buffer: f32,
+    pub turn_factor: f32,
+    pub edge_brake: f32,
+    pub max_force: f32,
+    pub drag: f32,
+    pub speed_limit: f32,
+}
+
+impl Params {
+    /// The world's steering coefficients, with a default boid's personal ones
+    pub fn new(world: &Blackboard, count: u32, dt: f32) -> Params {
+        let boid = Boid::create_blackboard();
+        Params {
+            count,
+            dt,
+            width: get_f32(world, "win_width"),
+            height: get_f32(world, "win_height"),
+            visual_range: get_f32(&boid, "visual_range"),
+            min_distance: get_f32(&boid, "min_distance"),
+            avoid_factor: get_f32(world, "avoid_factor"),
+            centering_factor: get_f32(world, "centering_factor"),
+            matching_factor: get_f32(world, "matching_factor"),
+            edge_buffer: get_f32(world, "edge_buffer"),
+            turn_factor: get_f32(world, "turn_factor"),
+            edge_brake: get_f32(world, "edge_brake"),
+            max_force: get_f32(world, "max_force"),
+            drag: get_f32(&boid, "drag"),
+            speed_limit: get_f32(&boid, "speed_limit") * get_f32(world, "speed_scale"),
+        }
+    }
+    fn bytes(&self) -> Vec<u8> {
+        let floats = [
+            self.dt, self.width, self.height, self.visual_range, self.min_distance, self.avoid_factor,
+            self.centering_factor, self.matching_factor, self.edge_buffer, self.turn_factor, self.edge_brake,
+            self.max_force, self.drag, self.speed_limit,
+            // uniforms are padded out to 16 bytes
+            0.0,
+        ];
+        let mut bytes = self.count.to_le_bytes().to_vec();
+        bytes.extend(floats.iter().flat_map(|f| f.to_le_bytes()));
+        bytes
+    }
+}
+
+fn state_bytes(states: &[[f32; 4]]) -> Vec<u8> {
+    states.iter().flatten().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+/// A flock stepped on the GPU
+pub struct GpuFlock {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    /// The shader reads one and writes the other, swapping every tick
+    bind_groups: [wgpu::BindGroup; 2],
+    states: [wgpu::Buffer; 2],
+    readback: wgpu::Buffer,
+    count: u32,
+    /// Which of `states` holds the latest tick
+    current: usize,
+    /// Name of the adapter in use
+    pub adapter: String,
+}
+
+impl GpuFlock {
+    /// Upload `states`, one `[x, y, dx, dy]` per boid, to the first adapter that has room for them
+    pub fn new(states: &[[f32; 4]], params: &Params) -> Result<GpuFlock, String> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok_or("no GPU adapter")?;
+        let size = states.len() as u64 * STATE_SIZE;
+        if size > adapter.limits().max_storage_buffer_binding_size as u64 {
+            return Err(format!("{} boids don't fit in a storage buffer on {}", states.len(), adapter.get_info().name));
+        }
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("flock"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        }, None))
+        .map_err(|e| e.to_string())?;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("flock"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("flock"),
+            layout: None,
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: &params.bytes(),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let contents = state_bytes(states);
+        let buffer = |label| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: &contents,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+        let states = [buffer("boids a"), buffer("boids b")];
+        let layout = pipeline.get_bind_group_layout(0);
+        let bind_group = |from: &wgpu::Buffer, to: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: from.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: to.as_entire_binding() },
+            ],
+        });
+        let bind_groups = [bind_group(&states[0], &states[1]), bind_group(&states[1], &states[0])];
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Ok(GpuFlock {
+            device,
+            queue,
+            pipeline,
+            bind_groups,
+            states,
+            readback,
+            count: params.count,
+            current: 0,
+            adapter: adapter.get_info().name,
+        })
+    }
+    /// Run one tick and wait for it to finish
+    pub fn step(&mut self) {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None, timestamp_writes: None });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+            pass.dispatch_workgroups(self.count.div_ceil(WORKGROUP), 1, 1);
+        }
+        self.queue.submit([encoder.finish()]);
+        self.current = 1 - self.current;
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+    /// Copy the latest tick back, one `[x, y, dx, dy]` per boid
+    pub fn read(&self) -> Vec<[f32; 4]> {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(&self.states[self.current], 0, &self.readback, 0, self.readback.size());
+        self.queue.submit([encoder.finish()]);
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+        let states = slice
+            .get_mapped_range()
+            .chunks_exact(STATE_SIZE as usize)
+            .map(|boid| std::array::from_fn(|i| f32::from_le_bytes(boid[i * 4..i * 4 + 4].try_into().unwrap())))
+            .collect();
+        self.readback.unmap();
+        states
+    }
+}
+
+/// Boids scattered over the world at random speeds, as `Boid::new` places them
+pub fn scatter(count: usize, width: f32, height: f32, seed: u64) -> Vec<[f32; 4]> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| [
+            rng.gen::<f32>() * width,
+            rng.gen::<f32>() * height,
+            (rng.gen::<f32>() - 0.5) * SPEED_LIMIT,
+            (rng.gen::<f32>() - 0.5) * SPEED_LIMIT,
+        ])
+        .collect()
+}
+
+/// Length of the mean heading, as `metrics::polarization`
+fn polarization(states: &[[f32; 4]]) -> f32 {
+    let sum = states
+        .iter()
+        .map(|&[_, _, dx, dy]| glam::vec2(dx, dy).normalize_or_zero())
+        .fold(glam::Vec2::ZERO, |acc, v| acc + v);
+    sum.length() / states.len().max(1) as f32
+}
+
+/// Step a flock of `boids` on the GPU for `ticks` ticks, reading it back every
+/// tick as a frontend would, and print the time per tick
+pub fn run(config: &GpuConfig) -> Result<(), String> {
+    let world = simulation::create_world_blackboard(WORLD_WIDTH, WORLD_HEIGHT);
+    let states = scatter(config.boids, WORLD_WIDTH, WORLD_HEIGHT, config.seed);
+    let mut flock = GpuFlock::new(&states, &Params::new(&world, config.boids as u32, GPU_DT))?;
+    let (mut tick_time, mut read_time) = (Duration::ZERO, Duration::ZERO);
+    let mut states = states;
+    for _ in 0..config.ticks {
+        let start = Instant::now();
+        flock.step();
+        tick_time += start.elapsed();
+        let start = Instant::now();
+        states = flock.read();
+        read_time += start.elapsed();
+    }
+    let mean_ms = |total: Duration| total.as_secs_f64() * 1000.0 / config.ticks.max(1) as f64;
+    println!("gpu: {} boids for {} ticks on {}", config.boids, config.ticks, flock.adapter);
+    println!("  tick {:.2} ms  read back {:.2} ms  polarization {:.2}",
+             mean_ms(tick_time), mean_ms(read_time), polarization(&states));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_fill_the_shader_struct() {
+        let world = simulation::create_world_blackboard(400.0, 300.0);
+        // 16 four-byte fields
+        assert_eq!(Params::new(&world, 10, GPU_DT).bytes().len(), 64);
+    }
+
+    /// The shader's tick, on the CPU
+    fn reference_tick(states: &[[f32; 4]], p: &Params) -> Vec<[f32; 4]> {
+        states
+            .iter()
+            .enumerate()
+            .map(|(i, &[x, y, dx, dy])| {
+                let (pos, mut vel) = (glam::vec2(x, y), glam::vec2(dx, dy));
+                let (mut separation, mut center, mut heading, mut neighbors) = (glam::Vec2::ZERO, glam::Vec2::ZERO, glam::Vec2::ZERO, 0.0);
+                for (j, &[ox, oy, odx, ody]) in states.iter().enumerate() {
+                    if j == i {
+                        continue;
+                    }
+                    let dist = pos.distance(glam::vec2(ox, oy));
+                    if dist < p.min_distance && dist > 0.0 {
+                        separation += pos - glam::vec2(ox, oy);
+                    }
+                    if dist < p.visual_range {
+                        center += glam::vec2(ox, oy);
+                        heading += glam::vec2(odx, ody);
+                        neighbors += 1.0;
+                    }
+                }
+                let mut force = separation * p.avoid_factor;
+                if neighbors > 0.0 {
+                    force += (center / neighbors - pos) * p.centering_factor + (heading / neighbors - vel) * p.matching_factor;
+                }
+                let bound = |at: f32, size: f32, v: f32| {
+                    let (low, high) = (at < size - p.edge_buffer, at > p.edge_buffer);
+                    let push = if low { p.turn_factor } else { 0.0 } - if high { p.turn_factor } else { 0.0 };
+                    if low == high { push } else { push - v * p.edge_brake }
+                };
+                force += glam::vec2(bound(x, p.width, dx), bound(y, p.height, dy));
+                vel += (force.clamp_length_max(p.max_force) - vel * p.drag) * p.dt;
+                vel = vel.clamp_length_max(p.speed_limit);
+                let pos = pos + vel * p.dt;
+                [pos.x, pos.y, vel.x, vel.y]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_tick_matches_the_rules_on_the_cpu() {
+        // a small world, so the boids crowd and reach the edges
+        let world = simulation::create_world_blackboard(200.0, 150.0);
+        let states = scatter(300, 200.0, 150.0, 5);
+        let params = Params::new(&world, states.len() as u32, GPU_DT);
+        let mut flock = match GpuFlock::new(&states, &params) {
+            Ok(flock) => flock,
+            // nothing to test against on a machine without a GPU
+            Err(e) => {
+                eprintln!("skipped: {}", e);
+                return;
+            }
+        };
+        flock.step();
+        for (gpu, cpu) in flock.read().iter().zip(reference_tick(&states, &params)) {
+            for (a, b) in gpu.iter().zip(cpu) {
+                assert!((a - b).abs() <= 1e-3 * b.abs().max(1.0), "{:?} vs {:?}", gpu, cpu);
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// One tick of the flock for --gpu: separation, cohesion and alignment over
+// every other boid, the world bounds, then drag, the speed limit and the move.
+// Mirrors AvoidOthers, FlyTowardsCenter, MatchVelocity and KeepWithinBounds
+// in src/boid.rs. Each boid is vec4(x, y, dx, dy).
+
+struct Params {
+    count: u32,
+    dt: f32,
+    width: f32,
+    height: f32,
+    visual_range: f32,
+    min_distance: f32,
+    avoid_factor: f32,
+    centering_factor: f32,
+    matching_factor: f32,
+    edge_buffer: f32,
+    turn_factor: f32,
+    edge_brake: f32,
+    max_force: f32,
+    drag: f32,
+    speed_limit: f32,
+    _pad: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> boids_in: array<vec4<f32>>;
+@group(0) @binding(2) var<storage, read_write> boids_out: array<vec4<f32>>;
+
+// boids per workgroup, and per tile of the others loaded into shared memory
+const TILE: u32 = 256u;
+var<workgroup> tile: array<vec4<f32>, TILE>;
+
+@compute @workgroup_size(TILE)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(local_invocation_index) local: u32) {
+    let i = id.x;
+    // boids past the end still help load the tiles
+    var me = vec4<f32>(0.0);
+    if (i < params.count) {
+        me = boids_in[i];
+    }
+    let pos = me.xy;
+    var vel = me.zw;
+
+    var separation = vec2<f32>(0.0);
+    var center = vec2<f32>(0.0);
+    var heading = vec2<f32>(0.0);
+    var neighbors = 0.0;
+    for (var start = 0u; start < params.count; start += TILE) {
+        if (start + local < params.count) {
+            tile[local] = boids_in[start + local];
+        }
+        workgroupBarrier();
+        let end = min(TILE, params.count - start);
+        for (var k = 0u; k < end; k++) {
+            if (start + k == i) {
+                continue;
+            }
+            let other = tile[k];
+            let dist = distance(pos, other.xy);
+            if (dist < params.min_distance && dist > 0.0) {
+                separation += pos - other.xy;
+            }
+            if (dist < params.visual_range) {
+                center += other.xy;
+                heading += other.zw;
+                neighbors += 1.0;
+            }
+        }
+        workgroupBarrier();
+    }
+    if (i >= params.count) {
+        return;
+    }
+
+    var force = separation * params.avoid_factor;
+    if (neighbors > 0.0) {
+        force += (center / neighbors - pos) * params.centering_factor;
+        force += (heading / neighbors - vel) * params.matching_factor;
+    }
+
+    // turn back from the edges, braking while outside the edge buffer
+    var bounds = vec2<f32>(0.0);
+    var x_bounded = true;
+    var y_bounded = true;
+    if (pos.x < params.width - params.edge_buffer) {
+        bounds.x += params.turn_factor;
+        x_bounded = !x_bounded;
+    }
+    if (pos.x > params.edge_buffer) {
+        bounds.x -= params.turn_factor;
+        x_bounded = !x_bounded;
+    }
+    if (pos.y < params.height - params.edge_buffer) {
+        bounds.y += params.turn_factor;
+        y_bounded = !y_bounded;
+    }
+    if (pos.y > params.edge_buffer) {
+        bounds.y -= params.turn_factor;
+        y_bounded = !y_bounded;
+    }
+    if (!x_bounded) {
+        bounds.x -= vel.x * params.edge_brake;
+    }
+    if (!y_bounded) {
+        bounds.y -= vel.y * params.edge_brake;
+    }
+    force += bounds;
+
+    let strength = length(force);
+    if (strength > params.max_force) {
+        force *= params.max_force / strength;
+    }
+    vel += (force - vel * params.drag) * params.dt;
+    let speed = length(vel);
+    if (speed > params.speed_limit) {
+        vel *= params.speed_limit / speed;
+    }
+    boids_out[i] = vec4<f32>(pos + vel * params.dt, vel);
+}
@@ -0,0 +1,145 @@
+use ggez::{Context, GameResult, graphics};
+
+use crate::boid::Boid;
+use crate::epidemic::Health;
+use crate::metrics::{self, MetricHistory, Metrics};
+use crate::theme::HudColors;
+
+const GRAPH_WIDTH: f32 = 240.0;
+const GRAPH_HEIGHT: f32 = 60.0;
+const GRAPH_GAP: f32 = 8.0;
+const LABEL_SIZE: f32 = 14.0;
+
+struct Series {
+    label: &'static str,
+    value: fn(&Metrics) -> f32,
+    color: [f32; 4],
+}
+
+const SERIES: [Series; 5] = [
+    Series { label: "polarization", value: |m| m.polarization, color: [0.4, 0.9, 0.5, 1.0] },
+    Series { label: "mean speed", value: |m| m.mean_speed, color: [0.4, 0.7, 1.0, 1.0] },
+    Series { label: "clusters", value: |m| m.clusters as f32, color: [1.0, 0.7, 0.3, 1.0] },
+    Series { label: "milling", value: |m| m.milling, color: [0.8, 0.5, 1.0, 1.0] },
+    Series { label: "nearest neighbor", value: |m| m.spacing.mean, color: [1.0, 0.5, 0.6, 1.0] },
+];
+
+/// Rolling line charts stacked below `top` against the right edge at `right`
+pub fn draw(ctx: &mut Context, history: &MetricHistory, right: f32, top: f32, hud: &HudColors) -> GameResult {
+    if history.samples.len() < 2 {
+        return Ok(());
+    }
+    let left = right - GRAPH_WIDTH - GRAPH_GAP;
+    let first_top = top;
+    let start = history.now() - history.window;
+    let mb = &mut graphics::MeshBuilder::new();
+    let mut labels = vec![];
+    for (row, series) in SERIES.iter().enumerate() {
+        let top = first_top + GRAPH_GAP + row as f32 * (GRAPH_HEIGHT + GRAPH_GAP);
+        mb.rectangle(
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(left, top, GRAPH_WIDTH, GRAPH_HEIGHT),
+            hud.panel.into(),
+        )?;
+
+        let values: Vec<f32> = history.samples.iter().map(|(_, m)| (series.value)(m)).collect();
+        let max = values.iter().cloned().fold(f32::EPSILON, f32::max);
+        let points: Vec<glam::Vec2> = history
+            .samples
+            .iter()
+            .zip(values.iter())
+            .map(|(&(t, _), v)| glam::vec2(
+                left + (t - start) / history.window * GRAPH_WIDTH,
+                top + GRAPH_HEIGHT - v / max * GRAPH_HEIGHT,
+            ))
+            .collect();
+        mb.line(&points, 1.5, series.color.into())?;
+        labels.push((
+            glam::vec2(left + 4.0, top + 2.0),
+            format!("{}: {:.2}", series.label, values.last().unwrap()),
+        ));
+    }
+    // the epidemic's three counts share a chart once there is one
+    if history.samples.iter().any(|(_, m)| m.health[1] + m.health[2] > 0) {
+        let top = first_top + GRAPH_GAP + SERIES.len() as f32 * (GRAPH_HEIGHT + GRAPH_GAP);
+        mb.rectangle(
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(left, top, GRAPH_WIDTH, GRAPH_HEIGHT),
+            hud.panel.into(),
+        )?;
+        let max = history.samples.iter().map(|(_, m)| m.health.iter().sum::<usize>()).max().unwrap_or(0).max(1) as f32;
+        for (state, color) in Health::COLORS.iter().enumerate() {
+            let points: Vec<glam::Vec2> = history
+                .samples
+                .iter()
+                .map(|(t, m)| glam::vec2(
+                    left + (t - start) / history.window * GRAPH_WIDTH,
+                    top + GRAPH_HEIGHT - m.health[state] as f32 / max * GRAPH_HEIGHT,
+                ))
+                .collect();
+            mb.line(&points, 1.5, (*color).into())?;
+        }
+        let [s, i, r] = history.samples.back().unwrap().1.health;
+        labels.push((glam::vec2(left + 4.0, top + 2.0), format!("S {}  I {}  R {}", s, i, r)));
+    }
+    let m = mb.build(ctx)?;
+    graphics::draw(ctx, &m, graphics::DrawParam::new())?;
+
+    for (pos, label) in labels {
+        let text = graphics::Text::new(graphics::TextFragment {
+            text: label,
+            color: Some(hud.text.into()),
+            font: Some(graphics::Font::default()),
+            scale: Some(graphics::PxScale::from(LABEL_SIZE)),
+        });
+        graphics::draw(ctx, &text, graphics::DrawParam::default().dest(pos))?;
+    }
+    Ok(())
+}
+
+const HISTOGRAM_BINS: usize = 20;
+const HISTOGRAM_HEIGHT: f32 = 80.0;
+
+/// Bars of the current speed distribution along the bottom edge; speeds at
+/// or above `max_speed` land in the last bin
+pub fn draw_speed_histogram(ctx: &mut Context, boids: &[Boid], screen_width: f32,
+                            screen_height: f32, max_speed: f32, hud: &HudColors) -> GameResult {
+    if boids.is_empty() {
+        return Ok(());
+    }
+    let mut bins = [0usize; HISTOGRAM_BINS];
+    for boid in boids {
+        let bin = (metrics::speed(boid) / max_speed * HISTOGRAM_BINS as f32) as usize;
+        bins[bin.min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    let tallest = *bins.iter().max().unwrap() as f32;
+    let bar_width = screen_width / HISTOGRAM_BINS as f32;
+    let [r, g, b, _] = hud.text;
+
+    let mb = &mut graphics::MeshBuilder::new();
+    for (i, &count) in bins.iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let h = count as f32 / tallest * HISTOGRAM_HEIGHT;
+        mb.rectangle(
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(i as f32 * bar_width + 1.0, screen_height - h, bar_width - 2.0, h),
+            [r, g, b, 0.35].into(),
+        )?;
+    }
+    let m = mb.build(ctx)?;
+    graphics::draw(ctx, &m, graphics::DrawParam::new())?;
+
+    let text = graphics::Text::new(graphics::TextFragment {
+        text: format!("speed 0 .. {:.0}", max_speed),
+        color: Some(hud.text.into()),
+        font: Some(graphics::Font::default()),
+        scale: Some(graphics::PxScale::from(LABEL_SIZE)),
+    });
+    graphics::draw(
+        ctx,
+        &text,
+        graphics::DrawParam::default().dest(glam::vec2(4.0, screen_height - HISTOGRAM_HEIGHT - LABEL_SIZE - 2.0)),
+    )
+}
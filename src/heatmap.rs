@@ -0,0 +1,58 @@
+use ggez::{Context, GameResult, graphics};
+
+use crate::boid::Boid;
+
+/// Coarse grid counting how often boids visit each cell over a run
+#[derive(Clone, Debug)]
+pub struct DensityMap {
+    pub cell_size: f32,
+    pub cols: usize,
+    pub rows: usize,
+    pub counts: Vec<u32>,
+}
+
+impl DensityMap {
+    pub fn new(width: f32, height: f32, cell_size: f32) -> DensityMap {
+        let cols = (width / cell_size).ceil() as usize;
+        let rows = (height / cell_size).ceil() as usize;
+        DensityMap {
+            cell_size,
+            cols,
+            rows,
+            counts: vec![0; cols * rows],
+        }
+    }
+    pub fn accumulate(&mut self, boids: &[Boid]) {
+        for boid in boids {
+            let pos = boid.pos();
+            if pos.x < 0.0 || pos.y < 0.0 {
+                continue;
+            }
+            let col = (pos.x / self.cell_size) as usize;
+            let row = (pos.y / self.cell_size) as usize;
+            if col < self.cols && row < self.rows {
+                self.counts[row * self.cols + col] += 1;
+            }
+        }
+    }
+    pub fn clear(&mut self) {
+        for c in &mut self.counts {
+            *c = 0;
+        }
+    }
+    /// Translucent heatmap texture, one texel per cell
+    pub fn to_image(&self, ctx: &mut Context) -> GameResult<graphics::Image> {
+        let max = self.counts.iter().cloned().max().unwrap_or(0).max(1) as f32;
+        let mut rgba = Vec::with_capacity(self.counts.len() * 4);
+        for &c in &self.counts {
+            let t = c as f32 / max;
+            rgba.extend_from_slice(&[
+                (255.0 * t) as u8,
+                (64.0 + 96.0 * t) as u8,
+                (255.0 * (1.0 - t)) as u8,
+                (160.0 * t.sqrt()) as u8,
+            ]);
+        }
+        graphics::Image::from_rgba8(ctx, self.cols as u16, self.rows as u16, &rgba)
+    }
+}
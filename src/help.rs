@@ -0,0 +1,146 @@
+use ggez::{Context, GameResult};
+
+use crate::inspector;
+use crate::keymap::{self, BINDINGS};
+use crate::locale::tr;
+use crate::theme::HudColors;
+
+/// Wrap width of the rule descriptions, in characters
+const WRAP: usize = 96;
+
+const MOUSE: &[(&str, &str)] = &[
+    ("left drag", "select boids"),
+    ("right click", "send the selection there"),
+    ("shift click", "remove a boid"),
+    ("ctrl drag", "move a boid, flinging it on release"),
+];
+
+/// Steering rules in the order the boid tree runs them
+const RULES: &[(&str, &str)] = &[
+    ("emergencies", "Checked first every tick, so they cut flocking off at once. Boids that see the cursor, or \
+      hear an alarm from a neighbor that did, leave the flock and fly directly away from it (flee_factor); \
+      alarms are relayed a few hops through the flock. Boids set to pass within collision_distance of a \
+      neighbor in the next reaction_time seconds swerve aside (dodge_factor)."),
+    ("separation", "Each boid steers away from neighbors closer than its personal space, weighted by avoid_factor. \
+      Hatchlings keep less space, so young boids bunch up more."),
+    ("cohesion", "Boids steer toward the center of the neighbors they can see (centering_factor), which is what \
+      pulls scattered boids into flocks."),
+    ("alignment", "Boids nudge their velocity toward the average velocity of visible neighbors (matching_factor); \
+      together with cohesion this makes flocks move as one."),
+    ("branches", "Every branch_interval seconds or so each boid picks flocking, wandering or foraging at random, \
+      in proportion to flock_weight, wander_weight and forage_weight. Wanderers drift on a meandering heading \
+      (wander_factor) and foragers run up the pheromone trails (forage_factor); neither keeps to the flock."),
+    ("hunger", "Boids burn energy as they fly (energy_drain) and eat it back at the green food patches (eat_rate), \
+      which grow back slowly once grazed (food_regrow). Below hunger_threshold a boid leaves the flock for the \
+      nearest patch (food_factor); once above sated_threshold it heads back to where it left."),
+    ("roosting", "Days last day_length seconds. After dusk boids head for the nearest of the purple roosts, \
+      slowing as they arrive (roost_factor), circle it for a while (perch_delay) and then perch until dawn."),
+    ("migration", "With migration on, a target sets off from the flock and crosses the world (migration_speed), \
+      pulling every boid toward it however far away (migration_factor) while they keep flocking. Once \
+      migration_goal of the flock has reached the far edge the target turns back, or with migration_loop off \
+      the migration ends."),
+    ("formation", "With formation flight on, every boid may trail one other boid on each side. Front to back, each \
+      boid claims the nearest free slot slot_spacing behind and beside a boid ahead within formation_range and \
+      flies there at that boid's speed (formation_factor) instead of matching the flock, forming Vs and echelons."),
+    ("mates", "With mates on (J), each boid pairs off with the nearest single boid within mate_range and \
+      a spring holds the pair spring_length apart (spring_stiffness, spring_damping), drawn as a faint line. \
+      A boid whose mate dies pairs off again."),
+    ("chains", "Snake mode (Q) links boids into chains of up to chain_length: a boid on its own hangs on \
+      behind the nearest chain tail within chain_range. Only the heads flock; each segment trails the one \
+      ahead link_length behind, so the body slithers along the head's path. A chain breaks where a segment dies."),
+    ("growth", "With growth on (U), food a boid eats also makes it bigger (growth_rate), and with its size it \
+      keeps more space, sees further and flies slower. At split_size it splits into two boids half its size, \
+      each with the parent's traits mutated by up to split_mutation, so well-fed lines drift apart over generations."),
+    ("epidemic", "Starting an epidemic (E) infects one boid. A healthy boid within an infected one's \
+      min_distance catches it at transmission_rate per second of contact, and the infected recover after \
+      recovery_time, immune from then on. Boids are colored blue, red and gray for susceptible, infected \
+      and recovered, and the graphs chart the three counts."),
+    ("opinions", "With opinion dynamics on (Y) every boid holds one of opinion_count opinions, shown as its \
+      color. At opinion_rate per second it takes up the opinion most held among the boids in its visual range, \
+      itself included, while flocking as usual. The HUD counts each opinion and how long the flock took to agree."),
+    ("ants", "The ants scenario (--scenario ants) turns the flock into a colony: slow, barely flocking agents \
+      wander out from the brown nest, pick up ant_load of food at a patch and carry it home (nest_factor), \
+      laying pheromone only on the way back. Others following the trails find the food. The HUD counts \
+      the loads delivered."),
+    ("presets", "Presets are named parameter sets: murmuration, fish school and gnat swarm are built in. F6 on \
+      the setup or pause screen steps through them, or --preset NAME starts with one. F7 saves every numeric parameter \
+      changed from the defaults as a new preset, one file each in the presets directory, loaded again on the \
+      next start. Saved presets are listed on the setup and pause screens, where F10 renames the one in use \
+      (type the name, Enter to keep it, Escape to cancel) and Delete deletes it."),
+    ("attract mode", "Left a minute on the setup screen (--attract SECONDS to change, 0 for never), the demo plays \
+      itself: a flock flies each preset in turn, the camera moving to its next mode and following a boid picked \
+      at random. Any key or click goes back to the setup screen as it was."),
+    ("milling", "Milling mode (K) tightens the flock and steers every boid around the flock's centroid \
+      (mill_factor), the circling torus of a fish school. The HUD and transcripts report when the boids' \
+      angular momentum about the centroid is high while their polarization is low."),
+    ("catching", "With catch_radius raised above 0 in the panel, the cursor catches boids that come that close \
+      to it; they die and hatchlings replace them. Every catch shakes the screen and flashes it briefly."),
+    ("lanes", "A scenario (--scenario corridor or a JSON file) can add walls and lanes. Boids steer away from \
+      walls within obstacle_margin (obstacle_factor) and are put back out if they get in anyway. Lane boids keep \
+      to their lane's center line at lane_speed (lane_factor) and come back in at the start after every passage. \
+      Its decorations (lines, labels and zones) are drawn over the world, and solid ones are walls too."),
+    ("seek goal", "Boids given a goal with a right click head for it (seek_factor) until they come within goal_radius. \
+      When the scenario has walls, each boid is routed round them by A* over a grid of nav_cell_size cells and \
+      flies the corners of its path one after another; the route of a selected boid is drawn. With flow_field on, \
+      a single distance field is searched out from the goal instead and every boid heads downhill through it."),
+    ("trails", "Every boid leaves pheromone behind it and is drawn up the trail gradient (trail_factor), so paths \
+      that many boids took get used again."),
+    ("colors", "With color convergence on, boids shift their color toward their neighbors' average and \
+      occasionally mutate, so flocks take on a shared hue."),
+    ("bounds", "Near the edges boids turn back inwards (turn_factor) and brake (edge_brake), and they are pushed \
+      off the cursor if it gets right on top of them (cursor_push)."),
+    ("forces", "All the rules above add to one force, capped at max_force and divided by the boid's mass, with drag \
+      and any wind applied on top."),
+    ("turn rate", "The heading may turn at most max_turn_rate degrees per second, so boids swing around in arcs \
+      instead of flipping direction."),
+    ("speed", "Finally speed is capped at the boid's speed limit; bigger boids are slower."),
+];
+
+/// Break `text` into lines of at most `width` characters at spaces
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Key bindings, from the keymap, and mouse controls
+pub fn key_lines() -> Vec<String> {
+    let mut lines = vec![tr("keys").to_string()];
+    for binding in BINDINGS {
+        lines.push(format!("  {:<12} {}", keymap::key_name(binding.command), tr(binding.description)));
+    }
+    for (input, description) in MOUSE {
+        lines.push(format!("  {:<12} {}", tr(input), tr(description)));
+    }
+    lines
+}
+
+/// A short description of each steering rule
+pub fn rule_lines() -> Vec<String> {
+    let mut lines = vec![tr("steering rules").to_string()];
+    for (name, description) in RULES {
+        lines.push(format!("  {}", tr(name)));
+        lines.extend(wrap(tr(description), WRAP).into_iter().map(|l| format!("    {}", l)));
+    }
+    lines
+}
+
+/// Keys and rules side by side from the top-left corner
+pub fn draw(ctx: &mut Context, hud: &HudColors) -> GameResult {
+    let margin = glam::vec2(inspector::PANEL_MARGIN, inspector::PANEL_MARGIN);
+    let keys = inspector::draw_at(ctx, &key_lines(), margin, hud)?;
+    inspector::draw_at(ctx, &rule_lines(), glam::vec2(keys.right() + 8.0, keys.y), hud)?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Picture-in-picture boid's-eye view: the selected boid's neighborhood is drawn
+//! close up into an offscreen canvas the size of the window, which is then shown
+//! shrunk into a corner over the main view.
+use ggez::{Context, GameResult, graphics};
+
+use crate::camera::{Camera, CameraMode};
+
+/// Inset size as a share of the window's
+const SCALE: f32 = 0.25;
+/// Gap between the inset and the window's edges, in pixels
+const MARGIN: f32 = 12.0;
+/// How much of the inset's height the visual range circle spans
+const FILL: f32 = 0.9;
+
+#[derive(Default)]
+pub struct Inset {
+    /// Created on first use and again whenever the window changes size
+    canvas: Option<graphics::Canvas>,
+}
+
+impl Inset {
+    /// Where on `screen` the inset goes: its bottom right corner
+    fn rect(screen: graphics::Rect) -> graphics::Rect {
+        let (w, h) = (screen.w * SCALE, screen.h * SCALE);
+        graphics::Rect::new(screen.x + screen.w - w - MARGIN, screen.y + screen.h - h - MARGIN, w, h)
+    }
+    /// Camera onto `center` with a circle of `range` around it filling the height of `screen`
+    pub fn camera(center: glam::Vec2, range: f32, screen: graphics::Rect) -> Camera {
+        let zoom = screen.h * FILL / (2.0 * range.max(1.0));
+        Camera { mode: CameraMode::Fixed, center, zoom, rotation: 0.0, viewport: screen, shake: glam::Vec2::ZERO }
+    }
+    /// Send drawing to the inset canvas, cleared to `background`, until `finish`
+    pub fn begin(&mut self, ctx: &mut Context, background: graphics::Color) -> GameResult {
+        let (width, height) = graphics::drawable_size(ctx);
+        let stale = self.canvas.as_ref().is_none_or(|c| {
+            (c.width(), c.height()) != (width as u16, height as u16)
+        });
+        if stale {
+            self.canvas = Some(graphics::Canvas::with_window_size(ctx)?);
+        }
+        graphics::set_canvas(ctx, self.canvas.as_ref());
+        graphics::clear(ctx, background);
+        Ok(())
+    }
+    /// Go back to drawing on the screen and put the inset in its corner, framed
+    pub fn finish(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::set_canvas(ctx, None);
+        let Some(canvas) = &self.canvas else { return Ok(()) };
+        let screen = graphics::screen_coordinates(ctx);
+        let rect = Inset::rect(screen);
+        let scale = glam::vec2(rect.w / canvas.width() as f32, rect.h / canvas.height() as f32);
+        graphics::draw(ctx, canvas, graphics::DrawParam::default().dest(glam::vec2(rect.x, rect.y)).scale(scale))?;
+        let frame = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::stroke(2.0), rect, [1.0, 1.0, 1.0, 0.6].into())?;
+        graphics::draw(ctx, &frame, graphics::DrawParam::new())
+    }
+}
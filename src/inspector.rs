@@ -0,0 +1,43 @@
+use ggez::{Context, GameResult, graphics};
+
+use crate::theme::HudColors;
+
+pub const PANEL_MARGIN: f32 = 8.0;
+const FONT_SIZE: f32 = 18.0;
+
+/// Draw a translucent text panel anchored at the top-left corner
+pub fn draw(ctx: &mut Context, lines: &[String], hud: &HudColors) -> GameResult {
+    draw_at(ctx, lines, glam::vec2(PANEL_MARGIN, PANEL_MARGIN), hud).map(|_| ())
+}
+
+/// Draw a translucent text panel with its top-left corner at `pos`, returning its bounds
+pub fn draw_at(ctx: &mut Context, lines: &[String], pos: glam::Vec2, hud: &HudColors) -> GameResult<graphics::Rect> {
+    if lines.is_empty() {
+        return Ok(graphics::Rect::new(pos.x, pos.y, 0.0, 0.0));
+    }
+    let text = graphics::Text::new(graphics::TextFragment {
+        text: lines.join("\n"),
+        color: Some(hud.text.into()),
+        font: Some(graphics::Font::default()),
+        scale: Some(graphics::PxScale::from(FONT_SIZE)),
+    });
+    let bounds = graphics::Rect::new(
+        pos.x,
+        pos.y,
+        text.width(ctx) + 2.0 * PANEL_MARGIN,
+        text.height(ctx) + 2.0 * PANEL_MARGIN,
+    );
+    let background = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        bounds,
+        hud.panel.into(),
+    )?;
+    graphics::draw(ctx, &background, graphics::DrawParam::new())?;
+    graphics::draw(
+        ctx,
+        &text,
+        graphics::DrawParam::default().dest(pos + glam::vec2(PANEL_MARGIN, PANEL_MARGIN)),
+    )?;
+    Ok(bounds)
+}
@@ -0,0 +1,36 @@
+use crate::real::{RVec2, Real};
+
+/// Scheme used to turn a boid's steered velocity into a position change.
+///
+/// Steering forces are applied to velocity during the tick, so each scheme
+/// sees the velocity before (`v0`) and after (`v1`) steering and treats the
+/// difference as a constant acceleration over the step.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Integrator {
+    /// Move with the velocity the tick started with
+    ExplicitEuler,
+    /// Move with the velocity after steering (what the simulation always did)
+    #[default]
+    SemiImplicitEuler,
+    /// `x += v0 * dt + a * dt^2 / 2`, i.e. the average of both velocities
+    VelocityVerlet,
+}
+
+impl Integrator {
+    pub fn parse(name: &str) -> Result<Integrator, String> {
+        match name {
+            "euler" => Ok(Integrator::ExplicitEuler),
+            "semi-implicit" => Ok(Integrator::SemiImplicitEuler),
+            "verlet" => Ok(Integrator::VelocityVerlet),
+            _ => Err(format!("unknown integrator '{}' (euler, semi-implicit, verlet)", name)),
+        }
+    }
+    /// Position after `dt` seconds
+    pub fn advance(self, pos: RVec2, v0: RVec2, v1: RVec2, dt: Real) -> RVec2 {
+        match self {
+            Integrator::ExplicitEuler => pos + v0 * dt,
+            Integrator::SemiImplicitEuler => pos + v1 * dt,
+            Integrator::VelocityVerlet => pos + (v0 + v1) * 0.5 * dt,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use ggez::winit::event::VirtualKeyCode;
+
+/// Everything the keyboard can do
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Play,
+    Pause,
+    Reset,
+    ToggleColors,
+    ToggleFormation,
+    ToggleMates,
+    ToggleChains,
+    ToggleGrowth,
+    ToggleEpidemic,
+    ToggleOpinions,
+    ToggleMilling,
+    NextPreset,
+    SavePreset,
+    RenamePreset,
+    DeletePreset,
+    Spawn,
+    ScrubBack,
+    ScrubForward,
+    Save,
+    Load,
+    Record,
+    ShowDensity,
+    ShowVelocity,
+    ShowGraphs,
+    ShowHistogram,
+    ShowBanking,
+    ShowMotionTrails,
+    ShowTrails,
+    ShowGlow,
+    ShowMemory,
+    ToggleLowRes,
+    CycleFrameCap,
+    CycleCamera,
+    ShowInset,
+    ToggleFrozen,
+    PropagateTraits,
+    ShowRuleColors,
+    ShowForces,
+    ShowPanel,
+    DumpTrees,
+    ExportDot,
+    NextTheme,
+    PanelUp,
+    PanelDown,
+    PanelIncrease,
+    PanelDecrease,
+    PanelNarrow,
+    PanelWiden,
+    Help,
+}
+
+pub struct Binding {
+    pub key: VirtualKeyCode,
+    pub command: Command,
+    pub description: &'static str,
+}
+
+/// The keymap. Input handling and the help overlay both read this table.
+pub const BINDINGS: &[Binding] = &[
+    Binding { key: VirtualKeyCode::Space, command: Command::Play, description: "start / resume" },
+    Binding { key: VirtualKeyCode::P, command: Command::Pause, description: "pause" },
+    Binding { key: VirtualKeyCode::R, command: Command::Reset, description: "reset to setup" },
+    Binding { key: VirtualKeyCode::C, command: Command::ToggleColors, description: "toggle color convergence" },
+    Binding { key: VirtualKeyCode::O, command: Command::ToggleFormation, description: "toggle formation flight" },
+    Binding { key: VirtualKeyCode::J, command: Command::ToggleMates, description: "toggle mates held together by springs" },
+    Binding { key: VirtualKeyCode::Q, command: Command::ToggleChains, description: "toggle snake mode (chains of boids)" },
+    Binding { key: VirtualKeyCode::U, command: Command::ToggleGrowth, description: "toggle growth, splitting well-fed boids in two" },
+    Binding { key: VirtualKeyCode::E, command: Command::ToggleEpidemic, description: "start / stop an epidemic" },
+    Binding { key: VirtualKeyCode::Y, command: Command::ToggleOpinions, description: "toggle opinion dynamics" },
+    Binding { key: VirtualKeyCode::K, command: Command::ToggleMilling, description: "toggle milling mode" },
+    Binding { key: VirtualKeyCode::F6, command: Command::NextPreset, description: "next parameter preset (setup / paused)" },
+    Binding { key: VirtualKeyCode::F7, command: Command::SavePreset, description: "save the tuning as a preset" },
+    Binding { key: VirtualKeyCode::F10, command: Command::RenamePreset, description: "rename the preset (setup / paused)" },
+    Binding { key: VirtualKeyCode::Delete, command: Command::DeletePreset, description: "delete the preset (setup / paused)" },
+    Binding { key: VirtualKeyCode::N, command: Command::Spawn, description: "spawn boids" },
+    Binding { key: VirtualKeyCode::Comma, command: Command::ScrubBack, description: "step back (paused)" },
+    Binding { key: VirtualKeyCode::Period, command: Command::ScrubForward, description: "step forward (paused)" },
+    Binding { key: VirtualKeyCode::F5, command: Command::Save, description: "save state" },
+    Binding { key: VirtualKeyCode::F9, command: Command::Load, description: "load state" },
+    Binding { key: VirtualKeyCode::F8, command: Command::Record, description: "start / stop recording" },
+    Binding { key: VirtualKeyCode::D, command: Command::ShowDensity, description: "density heatmap" },
+    Binding { key: VirtualKeyCode::V, command: Command::ShowVelocity, description: "velocity field" },
+    Binding { key: VirtualKeyCode::G, command: Command::ShowGraphs, description: "metric graphs" },
+    Binding { key: VirtualKeyCode::S, command: Command::ShowHistogram, description: "speed histogram" },
+    Binding { key: VirtualKeyCode::B, command: Command::ShowBanking, description: "bank boids into turns" },
+    Binding { key: VirtualKeyCode::L, command: Command::ShowMotionTrails, description: "motion trails" },
+    Binding { key: VirtualKeyCode::F1, command: Command::ShowTrails, description: "pheromone trails" },
+    Binding { key: VirtualKeyCode::W, command: Command::ShowGlow, description: "glow" },
+    Binding { key: VirtualKeyCode::F4, command: Command::ShowMemory, description: "memory use" },
+    Binding { key: VirtualKeyCode::F12, command: Command::ToggleLowRes, description: "low resolution rendering" },
+    Binding { key: VirtualKeyCode::F11, command: Command::CycleFrameCap, description: "frame rate cap (vsync is set at launch, --no-vsync)" },
+    Binding { key: VirtualKeyCode::Z, command: Command::CycleCamera, description: "camera mode" },
+    Binding { key: VirtualKeyCode::I, command: Command::ShowInset, description: "boid's-eye inset (one boid selected)" },
+    Binding { key: VirtualKeyCode::X, command: Command::ToggleFrozen, description: "freeze / unfreeze the selection" },
+    Binding { key: VirtualKeyCode::A, command: Command::PropagateTraits, description: "give every boid the selected boid's traits" },
+    Binding { key: VirtualKeyCode::M, command: Command::ShowRuleColors, description: "color by strongest rule" },
+    Binding { key: VirtualKeyCode::F, command: Command::ShowForces, description: "steering force arrows (one boid selected)" },
+    Binding { key: VirtualKeyCode::Tab, command: Command::ShowPanel, description: "parameter panel" },
+    Binding { key: VirtualKeyCode::F2, command: Command::DumpTrees, description: "print behavior trees to the console" },
+    Binding { key: VirtualKeyCode::F3, command: Command::ExportDot, description: "export behavior trees to boids.dot" },
+    Binding { key: VirtualKeyCode::T, command: Command::NextTheme, description: "next color theme" },
+    Binding { key: VirtualKeyCode::Up, command: Command::PanelUp, description: "panel: previous entry" },
+    Binding { key: VirtualKeyCode::Down, command: Command::PanelDown, description: "panel: next entry" },
+    Binding { key: VirtualKeyCode::Right, command: Command::PanelIncrease, description: "panel: increase / toggle" },
+    Binding { key: VirtualKeyCode::Left, command: Command::PanelDecrease, description: "panel: decrease / toggle" },
+    Binding { key: VirtualKeyCode::Minus, command: Command::PanelNarrow, description: "panel: narrower" },
+    Binding { key: VirtualKeyCode::Equals, command: Command::PanelWiden, description: "panel: wider" },
+    Binding { key: VirtualKeyCode::H, command: Command::Help, description: "this help" },
+];
+
+pub fn binding(command: Command) -> &'static Binding {
+    BINDINGS
+        .iter()
+        .find(|b| b.command == command)
+        .expect("every command has a binding")
+}
+
+/// Whether the key bound to `command` is in `keys`
+pub fn pressed(keys: &HashSet<VirtualKeyCode>, command: Command) -> bool {
+    keys.contains(&binding(command).key)
+}
+
+/// Display name of the key bound to `command`
+pub fn key_name(command: Command) -> String {
+    format!("{:?}", binding(command).key).to_lowercase()
+}
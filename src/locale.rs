@@ -0,0 +1,78 @@
+//! Translations of user-facing text. UI strings are written in English and
+//! looked up in a table for the language, an object mapping each English
+//! string to its translation. The tables in `locale/` are built into the
+//! binary; one for another language can be dropped into a `locale` folder
+//! next to the executable. Anything missing stays English.
+//! Placeholders are `{}` and are filled in order by `fill`.
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+const LOCALE_DIR: &str = "locale";
+/// Tables built into the binary, so translations work whatever the working directory
+const BUILT_IN: &[(&str, &str)] = &[("es", include_str!("../locale/es.json"))];
+
+static TABLE: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Language from `$LANG` (`de_DE.UTF-8` -> `de`)
+pub fn system_language() -> Option<String> {
+    let lang = std::env::var("LANG").ok()?;
+    let code: String = lang.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+    if code.is_empty() || code == "C" {
+        None
+    } else {
+        Some(code.to_lowercase())
+    }
+}
+
+/// Load the table for `lang`; English needs no file
+pub fn init(lang: &str) -> Result<(), String> {
+    if lang == "en" {
+        return Ok(());
+    }
+    let (path, text) = match BUILT_IN.iter().find(|(code, _)| *code == lang) {
+        Some((_, text)) => (format!("{}/{}.json", LOCALE_DIR, lang), text.to_string()),
+        None => {
+            let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+            let path = exe.with_file_name(LOCALE_DIR).join(format!("{}.json", lang)).display().to_string();
+            let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+            (path, text)
+        }
+    };
+    let table: HashMap<String, String> = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+    // only the first call takes effect
+    let _ = TABLE.set(table);
+    Ok(())
+}
+
+/// `text` in the current language
+pub fn tr(text: &str) -> &str {
+    TABLE.get().and_then(|t| t.get(text)).map_or(text, String::as_str)
+}
+
+/// Replace each `{}` in `template` with the next of `args`
+pub fn fill(template: &str, args: &[String]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(i) = rest.find("{}") {
+        out.push_str(&rest[..i]);
+        out.push_str(args.next().map_or("", String::as_str));
+        rest = &rest[i + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_tables_parse() {
+        for (lang, text) in BUILT_IN {
+            let table: Result<HashMap<String, String>, _> = serde_json::from_str(text);
+            assert!(table.is_ok(), "{}: {:?}", lang, table.err());
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Two-peer lockstep over TCP. Peers only exchange their input each tick and
+//! both simulate every tick locally with a fixed step, so bandwidth stays at a
+//! few bytes per tick whatever the flock size. Build both peers with the same
+//! features (ideally `deterministic`) so they stay in sync.
+//!
+//! All values are little-endian:
+//!
+//! ```text
+//! handshake := host -> client: seed:u64 config_hash:u64
+//!              client -> host: config_hash:u64
+//! input     := tick:u32 flags:u8 x:f32 y:f32 spawn:u8    flags bit 0: cursor present
+//! ```
+//!
+//! Each peer sends its input `INPUT_DELAY` ticks ahead of the tick it applies
+//! to, which hides that much round-trip latency.
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+pub const LOCKSTEP_DT: f64 = 1.0 / 60.0; // Seconds
+const INPUT_DELAY: usize = 3; // Ticks
+const INPUT_SIZE: usize = 14; // Bytes
+
+/// What one player did during one tick
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlayerInput {
+    pub cursor: Option<glam::Vec2>,
+    /// Boids to hatch this tick
+    pub spawn: u8,
+}
+
+impl PlayerInput {
+    fn encode(&self, tick: u32) -> [u8; INPUT_SIZE] {
+        let mut bytes = [0u8; INPUT_SIZE];
+        bytes[0..4].copy_from_slice(&tick.to_le_bytes());
+        let cursor = self.cursor.unwrap_or(glam::Vec2::ZERO);
+        bytes[4] = self.cursor.is_some() as u8;
+        bytes[5..9].copy_from_slice(&cursor.x.to_le_bytes());
+        bytes[9..13].copy_from_slice(&cursor.y.to_le_bytes());
+        bytes[13] = self.spawn;
+        bytes
+    }
+    fn decode(bytes: &[u8]) -> (u32, PlayerInput) {
+        let word = |i: usize| [bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]];
+        let tick = u32::from_le_bytes(word(0));
+        let cursor = glam::vec2(f32::from_le_bytes(word(5)), f32::from_le_bytes(word(9)));
+        let input = PlayerInput {
+            cursor: if bytes[4] & 1 != 0 { Some(cursor) } else { None },
+            spawn: bytes[13],
+        };
+        (tick, input)
+    }
+}
+
+pub struct Lockstep {
+    stream: TcpStream,
+    pub host: bool,
+    /// Seed both peers start the flock from
+    pub seed: u64,
+    /// Next tick to simulate
+    pub tick: u32,
+    /// Inputs sent for ticks `tick..`, oldest first
+    local: VecDeque<PlayerInput>,
+    /// Inputs received for ticks `tick..`, oldest first
+    remote: VecDeque<PlayerInput>,
+    /// Bytes of a partially received input
+    pending: Vec<u8>,
+}
+
+fn read_u64(stream: &mut TcpStream) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    stream.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn config_mismatch() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "peers have different world parameters")
+}
+
+impl Lockstep {
+    /// Wait for one peer to connect on `port`
+    pub fn host(port: u16, seed: u64, config_hash: u64) -> io::Result<Lockstep> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let (mut stream, _) = listener.accept()?;
+        stream.write_all(&seed.to_le_bytes())?;
+        stream.write_all(&config_hash.to_le_bytes())?;
+        if read_u64(&mut stream)? != config_hash {
+            return Err(config_mismatch());
+        }
+        Lockstep::start(stream, true, seed)
+    }
+    pub fn join<A: ToSocketAddrs>(addr: A, config_hash: u64) -> io::Result<Lockstep> {
+        let mut stream = TcpStream::connect(addr)?;
+        let seed = read_u64(&mut stream)?;
+        let host_hash = read_u64(&mut stream)?;
+        stream.write_all(&config_hash.to_le_bytes())?;
+        if host_hash != config_hash {
+            return Err(config_mismatch());
+        }
+        Lockstep::start(stream, false, seed)
+    }
+    fn start(stream: TcpStream, host: bool, seed: u64) -> io::Result<Lockstep> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+        let mut session = Lockstep {
+            stream,
+            host,
+            seed,
+            tick: 0,
+            local: VecDeque::new(),
+            remote: VecDeque::new(),
+            pending: vec![],
+        };
+        // the first ticks run on empty input while real input is in flight
+        for _ in 0..INPUT_DELAY {
+            session.send(PlayerInput::default())?;
+        }
+        Ok(session)
+    }
+    /// Whether there is room to send input for another tick
+    pub fn wants_input(&self) -> bool {
+        self.local.len() <= INPUT_DELAY
+    }
+    pub fn send(&mut self, input: PlayerInput) -> io::Result<()> {
+        let tick = self.tick + self.local.len() as u32;
+        // a blocking write keeps messages whole; each is only a few bytes
+        self.stream.set_nonblocking(false)?;
+        let result = self.stream.write_all(&input.encode(tick));
+        self.stream.set_nonblocking(true)?;
+        result?;
+        self.local.push_back(input);
+        Ok(())
+    }
+    fn receive(&mut self) -> io::Result<()> {
+        let mut bytes = [0u8; 256];
+        loop {
+            match self.stream.read(&mut bytes) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "peer disconnected")),
+                Ok(n) => self.pending.extend_from_slice(&bytes[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let whole = self.pending.len() / INPUT_SIZE * INPUT_SIZE;
+        for message in self.pending[..whole].chunks(INPUT_SIZE) {
+            let (tick, input) = PlayerInput::decode(message);
+            if tick != self.tick + self.remote.len() as u32 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "input out of order"));
+            }
+            self.remote.push_back(input);
+        }
+        self.pending.drain(..whole);
+        Ok(())
+    }
+    /// Both players' inputs for the next tick (host first) once the peer's has
+    /// arrived, advancing the session to the following tick
+    pub fn next_tick(&mut self) -> io::Result<Option<[PlayerInput; 2]>> {
+        self.receive()?;
+        if self.local.is_empty() || self.remote.is_empty() {
+            return Ok(None);
+        }
+        let local = self.local.pop_front().unwrap();
+        let remote = self.remote.pop_front().unwrap();
+        self.tick += 1;
+        Ok(Some(if self.host { [local, remote] } else { [remote, local] }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_round_trips() {
+        for input in [
+            PlayerInput { cursor: Some(glam::vec2(12.5, -3.25)), spawn: 10 },
+            PlayerInput { cursor: None, spawn: 0 },
+            PlayerInput { cursor: Some(glam::Vec2::ZERO), spawn: u8::MAX },
+        ] {
+            let bytes = input.encode(42);
+            assert_eq!(bytes.len(), INPUT_SIZE);
+            assert_eq!(PlayerInput::decode(&bytes), (42, input));
+        }
+    }
+
+    #[test]
+    fn missing_cursor_ignores_its_coordinates() {
+        let mut bytes = PlayerInput { cursor: Some(glam::vec2(1.0, 2.0)), spawn: 3 }.encode(0);
+        bytes[4] = 0;
+        assert_eq!(PlayerInput::decode(&bytes).1, PlayerInput { cursor: None, spawn: 3 });
+    }
+}
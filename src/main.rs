@@ -1,235 +1,2211 @@
 use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufWriter;
+use std::thread;
+use std::time::Instant;
 
-use bonsai_bt::{ActionArgs, BT, Event, State, UpdateArgs, Success, Action, Failure, Sequence, Behavior};
+use bonsai_bt::{ActionArgs, Behavior, BT, Event, State, Status, UpdateArgs, Success, Action, Failure, Sequence};
 use ggez::{conf, Context, ContextBuilder, event, GameResult, graphics, input, timer};
 use ggez::mint::Point2;
 use ggez::winit::event::VirtualKeyCode;
+use ggez::input::mouse::MouseButton;
 
-use crate::boid::{Boid, BoidAction};
+use crate::batch::BatchConfig;
+use crate::bench::{Bench, Soak, SoakStep};
+#[cfg(feature = "irc")]
+use crate::chat::ChatCommand;
+use crate::blackboard::{BBValue, get_bool, get_f32};
+use crate::boid::{Boid, BoidBT};
+use crate::camera::{Camera, CameraMode};
+use crate::control_panel::ControlPanel;
+use crate::downscale::Downscale;
+use crate::frame_cap::FrameCap;
+use crate::frame_time::FrameTime;
+use crate::geometry::Geometry;
+use crate::glow::Glow;
+use crate::heatmap::DensityMap;
+use crate::integrator::Integrator;
+use crate::keymap::Command;
+use crate::locale::{fill, tr};
+use crate::lockstep::{LOCKSTEP_DT, Lockstep, PlayerInput};
+use crate::memory::Usage;
+use crate::metrics::{MetricHistory, Metrics};
+use crate::inset::Inset;
+use crate::motion_trails::MotionTrails;
+use crate::presets::Preset;
+use crate::puff::Puffs;
+use crate::render::{GgezRenderer, Renderer};
+use crate::render_cache::RenderCache;
+use crate::replay::{ReplayFrame, ReplayHeader, ReplayKind, ReplayWriter};
+use crate::rewind::{BoidState, RewindBuffer};
+use crate::scenario::Scenario;
+use crate::selection::Selection;
+use crate::shake::Shake;
+use crate::simulation::Simulation;
+use crate::sweep::SweepConfig;
+use crate::theme::Theme;
+use crate::transcript::TranscriptConfig;
+use crate::tui::TuiConfig;
+use crate::velocity_field::VelocityField;
+use crate::window_state::WindowState;
 
+mod batch;
+mod bench;
+mod blackboard;
+#[cfg(feature = "bevy")]
+mod bevy_plugin;
 mod boid;
+mod camera;
+mod chains;
+mod bt_view;
+#[cfg(feature = "irc")]
+mod chat;
+mod control_panel;
+mod controller;
+mod detmath;
+mod diff;
+mod downscale;
+mod epidemic;
+mod events;
+mod food;
+mod formation;
+mod frame_cap;
+mod frame_time;
+mod geometry;
+mod glow;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod graphs;
+mod heatmap;
+mod help;
+mod inset;
+mod inspector;
+mod integrator;
+mod locale;
+mod keymap;
+mod lockstep;
+mod mates;
+mod memory;
+mod metrics;
+mod migration;
+mod motion_trails;
+mod navgrid;
+mod opinion;
+mod pheromone;
+#[cfg(feature = "rapier")]
+mod physics;
+mod presets;
+mod puff;
+mod real;
+mod render;
+mod render_cache;
+mod replay;
+mod rewind;
+mod roost;
+mod scenario;
+mod selection;
+mod shake;
+mod simulation;
+mod steering_view;
+mod subtrees;
+mod sweep;
+mod theme;
+mod transcript;
+mod tui;
+mod velocity_field;
+mod window_state;
 
-const WINDOW_HEIGHT: f32 = 720.0;
-const WINDOW_WIDTH: f32 = WINDOW_HEIGHT * (16.0 / 9.0);
-const OBJECT_COUNT: usize = 100;
-pub const OBJECT_SIZE: f32 = 32.0; // Pixels
+/// Size of the world the boids fly in, in world units. Everything in the
+/// simulation is measured in these, whatever the screen.
+pub const WORLD_HEIGHT: f32 = 720.0;
+pub const WORLD_WIDTH: f32 = WORLD_HEIGHT * (16.0 / 9.0);
+/// Screen pixels per world unit when the whole world is in view, unless
+/// --pixels-per-unit says otherwise. Only the rendering uses it.
+pub const PIXELS_PER_UNIT: f32 = 1.0;
+pub const OBJECT_COUNT: usize = 100;
+const DENSITY_CELL_SIZE: f32 = 20.0; // World units
+const VELOCITY_CELL_SIZE: f32 = 40.0; // World units
+/// Fraction of the motion trails kept from one frame to the next
+const TRAIL_FADE: f32 = 0.94;
+/// How much of each frame's cursor velocity goes into a dragged boid's fling
+const FLING_SMOOTHING: f32 = 0.5;
+const METRIC_WINDOW: f32 = 30.0; // Seconds
+const REWIND_WINDOW: f32 = 10.0; // Seconds
+const RESUME_COUNTDOWN: f32 = 3.0; // Seconds
+const STATE_FILE: &str = "boids.state";
+const REPLAY_FILE: &str = "boids.rpl";
+const DOT_FILE: &str = "boids.dot";
+/// Boids hatched per press of the spawn key
+const SPAWN_BATCH: u8 = 10;
+const ATTRACT_AFTER: f32 = 60.0; // Seconds
+/// How long attract mode shows each preset
+const ATTRACT_SHOT: f32 = 20.0; // Seconds
+pub const OBJECT_SIZE: f32 = 32.0; // World units
 
 #[derive(Clone, PartialEq)]
 enum MenuState {
     Play,
     Setup,
     Pause,
+    /// Counting down from pause back to play
+    Resume,
+}
+
+/// Demo played on its own while nobody is at the controls
+struct Attract {
+    /// Seconds until the next preset and camera mode
+    shot: f32,
+    /// Preset and camera mode in use before it started
+    restore: (Option<usize>, CameraMode),
 }
 
 #[derive(Clone, Debug)]
 enum OperationState {
     InputKey,
+    /// Holds off ticking until the resume countdown has run out
+    Countdown,
+    InputMouse,
     UpdateGameData,
 }
 
+/// Mouse buttons held down this frame
+#[derive(Clone, Copy, Default)]
+struct MouseButtons {
+    left: bool,
+    right: bool,
+    /// Shift held, turning a left click into removal
+    shift: bool,
+    /// Control held, turning a left drag into moving a boid
+    ctrl: bool,
+}
+
+/// A boid being dragged with the mouse
+struct Grab {
+    id: usize,
+    /// Cursor position last frame
+    last: glam::Vec2,
+    /// Smoothed cursor velocity, given to the boid when it is let go
+    velocity: glam::Vec2,
+}
+
 struct GameWorld {
     menu_state: MenuState,
-    boids: Vec<Boid>,
+    sim: Simulation,
+    /// Second simulation shown in the right half for A/B comparison
+    compare: Option<Simulation>,
+    /// Parameter overrides of each side, shown as labels
+    compare_labels: [String; 2],
+    /// Step the next tick on a worker thread while the current one is drawn
+    pipelined: bool,
+    /// Tick queued by update for draw to run alongside rendering
+    pending_step: Option<(std::time::Duration, Point2<f32>)>,
+    /// Front buffer drawn while the tick steps, kept across frames so bringing
+    /// it up to date copies only what is drawn
+    shown: Option<(Simulation, Option<Simulation>)>,
+    /// Networked session; only inputs are exchanged and each peer simulates locally
+    lockstep: Option<Lockstep>,
+    /// Boids requested with the spawn key, hatched on the next tick
+    spawn_request: u8,
+    #[cfg(feature = "irc")]
+    chat: Option<std::sync::mpsc::Receiver<ChatCommand>>,
     points: Vec<glam::Vec2>,
-    boid_bt: BT<BoidAction, String, f32>,
+    density: DensityMap,
+    show_density: bool,
+    show_velocity: bool,
+    show_banking: bool,
+    show_motion_trails: bool,
+    /// Pheromone heat layer
+    show_trails: bool,
+    motion_trails: MotionTrails,
+    show_glow: bool,
+    /// Screen shake and flash after the cursor catches boids
+    shake: Shake,
+    /// Particles left where boids were removed
+    puffs: Puffs,
+    grab: Option<Grab>,
+    camera: Camera,
+    /// Close up of the selected boid's neighborhood in a corner
+    show_inset: bool,
+    inset: Inset,
+    /// Post-process making bright boids glow, made the first time it is shown
+    glow: Option<Glow>,
+    /// Tint boids red, green or blue by their strongest flocking rule
+    show_rule_colors: bool,
+    /// Arrows for each rule's force on the inspected boid
+    show_forces: bool,
+    show_help: bool,
+    themes: Vec<Theme>,
+    /// Index into `themes` of the one in use
+    theme: usize,
+    metrics: MetricHistory,
+    rewind: RewindBuffer,
+    recorder: Option<ReplayWriter<BufWriter<File>>>,
+    /// Recorded frames being played back instead of simulating, and the next one to show
+    playback: Option<(Vec<ReplayFrame>, usize)>,
+    show_graphs: bool,
+    show_histogram: bool,
+    panel: ControlPanel,
+    /// Values the milling preset replaced, put back when milling mode is turned off
+    milling_restore: Vec<(String, f32)>,
+    presets: Vec<Preset>,
+    /// Index into `presets` of the one in use, if any
+    preset: Option<usize>,
+    /// Values the preset in use replaced, one list per simulation
+    preset_restore: Vec<Vec<(String, f32)>>,
+    /// Seconds without input on the setup screen before attract mode starts, 0 for never
+    attract_after: f32,
+    /// Seconds since the last key or click
+    idle: f32,
+    attract: Option<Attract>,
+    /// Ignore the keyboard and mouse until they are let go, after they ended attract mode
+    swallow_input: bool,
+    /// Pause when the window loses focus, unless --no-focus-pause
+    pause_on_focus_loss: bool,
+    /// Paused because the window lost focus; a click resumes
+    focus_paused: bool,
+    /// Drawing the world at a fraction of the window's resolution
+    downscale: Downscale,
+    /// Frame rate the loop sleeps down to, from --fps-cap and F11
+    frame_cap: FrameCap,
+    /// Clamps and smooths each frame's delta into `dt`
+    frame_time: FrameTime,
+    /// Meshes and text reused across frames
+    render_cache: RenderCache,
+    /// Timings of the benchmark scene, while --bench-scene runs
+    bench: Option<Bench>,
+    show_memory: bool,
+    /// Bytes the process may use before a warning, from --memory-budget
+    memory_budget: Option<usize>,
+    /// Whether the budget was exceeded when last checked, so the warning is printed once per overrun
+    over_budget: bool,
+    /// Flock size search, while --soak runs
+    soak: Option<Soak>,
+    /// New name being typed for the preset in use; keys type instead of acting meanwhile
+    renaming: Option<String>,
+    /// Panel width the window was last resized for
+    docked_width: f32,
+    /// Screen pixels per world unit when the whole world is in view; the
+    /// playfield is the world at this size
+    pixels_per_unit: f32,
+    /// Physical pixels per logical pixel. Everything is laid out and hit-tested
+    /// in logical pixels, so geometry keeps its size on high-DPI displays.
+    scale: f32,
+    /// Pick radius and boid marks for the current scale
+    geometry: Geometry,
+    /// Window geometry last seen out of fullscreen, saved on exit
+    windowed: Option<WindowState>,
     game_op_bt: State<OperationState>,
+    /// Status each game-loop action returned last frame
+    op_trace: HashMap<String, Status>,
+    /// Seconds left before a resume starts ticking
+    countdown: f32,
     dt: std::time::Duration,
+    prev_keys: HashSet<VirtualKeyCode>,
+    prev_buttons: MouseButtons,
+    selection: Selection,
 }
 
 impl GameWorld {
     pub fn new(_ctx: &mut Context,
-               bt: BT<BoidAction, String, f32>,
+               sim: Simulation,
+               compare: Option<Simulation>,
+               compare_labels: [String; 2],
     ) -> GameWorld {
         GameWorld {
             menu_state: MenuState::Setup,
             dt: Default::default(),
-            boids: std::default::Default::default(),
+            sim,
+            compare,
+            compare_labels,
+            pipelined: false,
+            pending_step: None,
+            shown: None,
+            lockstep: None,
+            spawn_request: 0,
+            #[cfg(feature = "irc")]
+            chat: None,
             points: vec![
                 glam::vec2(0.0, -OBJECT_SIZE / 2.0),
                 glam::vec2(OBJECT_SIZE / 4.0, OBJECT_SIZE / 2.0),
                 glam::vec2(0.0, OBJECT_SIZE / 3.0),
                 glam::vec2(-OBJECT_SIZE / 4.0, OBJECT_SIZE / 2.0),
             ],
-            boid_bt: bt,
+            density: DensityMap::new(WORLD_WIDTH, WORLD_HEIGHT, DENSITY_CELL_SIZE),
+            show_density: false,
+            show_velocity: false,
+            show_banking: false,
+            show_motion_trails: false,
+            show_trails: true,
+            motion_trails: MotionTrails::default(),
+            show_glow: false,
+            shake: Shake::default(),
+            puffs: Puffs::default(),
+            grab: None,
+            camera: Camera::new(graphics::Rect::new(0.0, 0.0, WORLD_WIDTH * PIXELS_PER_UNIT, WORLD_HEIGHT * PIXELS_PER_UNIT),
+                                glam::vec2(WORLD_WIDTH, WORLD_HEIGHT)),
+            show_inset: false,
+            inset: Inset::default(),
+            glow: None,
+            show_rule_colors: false,
+            show_forces: false,
+            show_help: false,
+            themes: theme::builtin(),
+            theme: 0,
+            metrics: MetricHistory::new(METRIC_WINDOW),
+            rewind: RewindBuffer::new(REWIND_WINDOW),
+            recorder: None,
+            playback: None,
+            show_graphs: false,
+            show_histogram: false,
+            panel: ControlPanel::default(),
+            milling_restore: vec![],
+            presets: presets::builtin(),
+            preset: None,
+            preset_restore: vec![],
+            attract_after: ATTRACT_AFTER,
+            idle: 0.0,
+            attract: None,
+            swallow_input: false,
+            pause_on_focus_loss: true,
+            focus_paused: false,
+            render_cache: RenderCache::default(),
+            downscale: Downscale::default(),
+            frame_cap: FrameCap::default(),
+            frame_time: FrameTime::default(),
+            bench: None,
+            soak: None,
+            show_memory: false,
+            memory_budget: None,
+            over_budget: false,
+            renaming: None,
+            docked_width: 0.0,
+            pixels_per_unit: PIXELS_PER_UNIT,
+            scale: 1.0,
+            geometry: Geometry::default(),
+            windowed: None,
             game_op_bt: Self::create_bt(),
+            op_trace: HashMap::new(),
+            countdown: 0.0,
+            prev_keys: HashSet::new(),
+            prev_buttons: MouseButtons::default(),
+            selection: Selection::default(),
         }
     }
-    fn create_bt() -> State<OperationState> {
-        let state = Sequence(vec![
+    fn create_behavior() -> Behavior<OperationState> {
+        Sequence(vec![
             Action(OperationState::InputKey),
+            Action(OperationState::Countdown),
+            Action(OperationState::InputMouse),
             Action(OperationState::UpdateGameData)
-        ]);
-        State::new(state)
+        ])
+    }
+    fn create_bt() -> State<OperationState> {
+        State::new(Self::create_behavior())
+    }
+    fn file_header(&self, kind: ReplayKind) -> ReplayHeader {
+        ReplayHeader {
+            version: (replay::VERSION_MAJOR, replay::VERSION_MINOR),
+            kind,
+            seed: self.sim.seed,
+            config_hash: replay::config_hash(&self.sim.world_bb.read().unwrap()),
+            boid_count: self.sim.boids.len() as u32,
+        }
+    }
+    fn save_state(&self) -> std::io::Result<()> {
+        let file = BufWriter::new(File::create(STATE_FILE)?);
+        let mut writer = ReplayWriter::new(file, &self.file_header(ReplayKind::State))?;
+        writer.write_frame(0.0, &BoidState::capture(&self.sim.boids))?;
+        writer.finish()
+    }
+    /// Write both behavior trees as Graphviz, with the statuses of the game
+    /// loop and of the selected boid from the last tick
+    fn export_dot(&self) -> std::io::Result<()> {
+        let boid_trace = self
+            .selection
+            .single()
+            .and_then(|id| self.sim.boids.iter().find(|b| b.id == id))
+            .map(|b| b.trace.clone())
+            .unwrap_or_default();
+        std::fs::write(DOT_FILE, trees_dot(&boid_trace, &self.op_trace))
+    }
+    fn load_state(&mut self) -> Result<(), replay::ReplayError> {
+        let (header, frames) = replay::read(File::open(STATE_FILE)?)?;
+        if header.config_hash != replay::config_hash(&self.sim.world_bb.read().unwrap()) {
+            eprintln!("{}: saved with different parameters", STATE_FILE);
+        }
+        let frame = frames.last().ok_or(replay::ReplayError::Corrupt("no frames"))?;
+        self.sim.apply_states(&frame.boids);
+        self.rewind.clear();
+        Ok(())
+    }
+    fn toggle_recording(&mut self) -> std::io::Result<()> {
+        match self.recorder.take() {
+            Some(recorder) => {
+                recorder.finish()?;
+                eprintln!("recording saved to {}", REPLAY_FILE);
+            }
+            None => {
+                let file = BufWriter::new(File::create(REPLAY_FILE)?);
+                self.recorder = Some(ReplayWriter::new(file, &self.file_header(ReplayKind::Replay))?);
+            }
+        }
+        Ok(())
+    }
+    /// Size of the playfield, the whole world at `pixels_per_unit`, in logical pixels
+    fn playfield(&self) -> glam::Vec2 {
+        glam::vec2(WORLD_WIDTH, WORLD_HEIGHT) * self.pixels_per_unit
+    }
+    /// Draw the world at `pixels_per_unit` screen pixels per world unit,
+    /// resizing the window to match
+    fn set_pixels_per_unit(&mut self, ctx: &mut Context, pixels_per_unit: f32) -> GameResult {
+        self.pixels_per_unit = pixels_per_unit;
+        let playfield = self.playfield();
+        // keep the view where it is, just drawn bigger or smaller
+        self.camera.zoom *= playfield.y / self.camera.viewport.h;
+        self.camera.viewport = graphics::Rect::new(0.0, 0.0, playfield.x, playfield.y);
+        self.fit_window(ctx)
+    }
+    /// Size the window for the simulation and docked panel at the current scale
+    fn fit_window(&mut self, ctx: &mut Context) -> GameResult {
+        self.docked_width = self.panel.docked_width();
+        let playfield = self.playfield();
+        let width = playfield.x + self.docked_width;
+        graphics::set_drawable_size(ctx, width * self.scale, playfield.y * self.scale)?;
+        self.fit_layout(ctx, width * self.scale, playfield.y * self.scale)
+    }
+    /// Scale the layout up or down to the largest that fits a window of `width`
+    /// by `height` physical pixels, centered with the background either side
+    fn fit_layout(&mut self, ctx: &mut Context, width: f32, height: f32) -> GameResult {
+        let layout = self.playfield() + glam::vec2(self.docked_width, 0.0);
+        self.scale = (width / layout.x).min(height / layout.y);
+        self.geometry = Geometry::new(self.scale, self.pixels_per_unit);
+        let shown = glam::vec2(width, height) / self.scale;
+        let margin = (shown - layout) / 2.0;
+        graphics::set_screen_coordinates(ctx, graphics::Rect::new(-margin.x, -margin.y, shown.x, shown.y))
+    }
+    /// Remember the window's geometry for the next run
+    fn save_window(&self, ctx: &Context) {
+        if let Err(e) = window_state::save(&WindowState::capture(ctx, self.windowed)) {
+            eprintln!("window: {}", e);
+        }
+    }
+    /// Mouse position in logical pixels
+    fn cursor(&self, ctx: &Context) -> Point2<f32> {
+        let p = input::mouse::position(ctx);
+        let screen = graphics::screen_coordinates(ctx);
+        Point2 { x: screen.x + p.x / self.scale, y: screen.y + p.y / self.scale }
+    }
+    /// Mouse position in the world, through the camera
+    fn world_cursor(&self, ctx: &Context) -> Point2<f32> {
+        let at = self.camera.to_world(self.cursor(ctx).into());
+        Point2 { x: at.x, y: at.y }
+    }
+    /// Act on chat commands received since the last tick
+    #[cfg(feature = "irc")]
+    fn apply_chat(&mut self) {
+        let Some(chat) = &self.chat else { return };
+        for command in chat.try_iter() {
+            match command {
+                ChatCommand::Spawn(count) => {
+                    self.spawn_request = self.spawn_request.saturating_add(count.min(u8::MAX as u32) as u8);
+                }
+                // spawns go through the session, the rest would change only this peer's world
+                _ if self.lockstep.is_some() => {}
+                ChatCommand::Scatter => {
+                    chat::scatter(&mut self.sim);
+                    if let Some(b) = &mut self.compare {
+                        chat::scatter(b);
+                    }
+                }
+                ChatCommand::Wind(wind) => {
+                    chat::set_wind(&self.sim, wind);
+                    if let Some(b) = &self.compare {
+                        chat::set_wind(b, wind);
+                    }
+                }
+            }
+        }
+    }
+    /// Switch to theme `index`, recoloring the boids from its palette
+    fn set_theme(&mut self, index: usize) {
+        self.theme = index;
+        let palette = self.themes[index].palette;
+        // colors don't affect motion, so this can stay off the simulation's rng
+        let mut rng = rand::thread_rng();
+        for sim in std::iter::once(&mut self.sim).chain(self.compare.as_mut()) {
+            palette.store(&mut sim.world_bb.write().unwrap());
+            for boid in &mut sim.boids {
+                boid.color = palette.sample(&mut rng);
+            }
+        }
+    }
+    /// Bytes held by each subsystem, both sides of a comparison together
+    fn memory_usage(&self) -> Usage {
+        let sims: Vec<&Simulation> = std::iter::once(&self.sim).chain(self.compare.as_ref()).collect();
+        Usage {
+            subsystems: vec![
+                ("boids", sims.iter().map(|s| s.boid_bytes()).sum()),
+                ("trails", sims.iter().map(|s| s.trails.values.capacity() * std::mem::size_of::<f32>()).sum()),
+                ("particles", self.puffs.bytes()),
+                ("rewind buffer", self.rewind.bytes()),
+                ("density map", self.density.counts.capacity() * std::mem::size_of::<u32>()),
+                ("metric history", self.metrics.samples.capacity() * std::mem::size_of::<(f32, Metrics)>()),
+            ],
+        }
+    }
+    /// Spawn a fresh flock and start playing
+    fn play(&mut self) {
+        // both sides start from the same flock
+        let seed = match &self.lockstep {
+            Some(session) => session.seed,
+            None => rand::random(),
+        };
+        self.sim.reseed(seed);
+        self.sim.spawn(OBJECT_COUNT);
+        if let Some(b) = &mut self.compare {
+            b.reseed(seed);
+            b.spawn(OBJECT_COUNT);
+        }
+        self.menu_state = MenuState::Play;
+    }
+    /// Start the countdown from pause back to play
+    fn resume(&mut self) {
+        self.rewind.resume();
+        self.countdown = RESUME_COUNTDOWN;
+        self.menu_state = MenuState::Resume;
+    }
+    /// Clear the flock and go back to the setup screen
+    fn reset(&mut self) {
+        self.menu_state = MenuState::Setup;
+        self.sim.reset();
+        if let Some(b) = &mut self.compare {
+            b.reset();
+        }
+        self.selection.clear();
+        self.density.clear();
+        self.metrics.clear();
+        self.rewind.clear();
+        if self.recorder.is_some() {
+            if let Err(e) = self.toggle_recording() {
+                eprintln!("{}: {}", REPLAY_FILE, e);
+            }
+        }
+        if let Some((_, next)) = &mut self.playback {
+            *next = 0;
+        }
+    }
+    /// Replace every flock with `count` boids from the benchmark seed and play
+    fn spawn_all(&mut self, count: usize) {
+        for sim in std::iter::once(&mut self.sim).chain(self.compare.as_mut()) {
+            sim.reseed(bench::BENCH_SEED);
+            sim.spawn(count);
+        }
+        self.menu_state = MenuState::Play;
+        self.attract_after = 0.0;
+    }
+    /// Start the benchmark scene: a big flock from a fixed seed, timed until it has run its time
+    fn start_bench(&mut self) {
+        self.spawn_all(bench::BENCH_BOIDS);
+        self.bench = Some(Bench::default());
+    }
+    /// Start the soak test, growing the flock until frames take over `frame_ms`
+    fn start_soak(&mut self, frame_ms: f32) {
+        self.spawn_all(bench::SOAK_START);
+        self.soak = Some(Soak::new(frame_ms));
+    }
+    /// `draw_frame`, timed while benchmarking
+    fn draw_timed(&mut self, ctx: &mut Context) -> GameResult {
+        let start = Instant::now();
+        // held apart from self while drawing, which reads the rest of it
+        let mut cache = std::mem::take(&mut self.render_cache);
+        let result = self.draw_frame(ctx, &mut cache);
+        self.render_cache = cache;
+        if let Some(bench) = &mut self.bench {
+            bench.record_draw(start.elapsed());
+        }
+        result
+    }
+    /// Start attract mode: play a flock on the first preset
+    fn start_attract(&mut self) {
+        self.attract = Some(Attract { shot: ATTRACT_SHOT, restore: (self.preset, self.camera.mode) });
+        self.play();
+        self.set_preset(Some(0));
+        self.follow_random_boid();
+    }
+    /// Move attract mode on to the next preset and camera mode
+    fn next_attract_shot(&mut self) {
+        let next = self.preset.map_or(0, |i| (i + 1) % self.presets.len());
+        self.set_preset(Some(next));
+        // side by side runs share the window, so the camera stays put
+        if self.compare.is_none() {
+            self.camera.mode = self.camera.mode.next();
+        }
+        self.follow_random_boid();
+    }
+    /// Select a boid at random for the follow camera modes to track
+    fn follow_random_boid(&mut self) {
+        self.selection.clear();
+        if !self.sim.boids.is_empty() {
+            self.selection.ids.insert(self.sim.boids[rand::random::<usize>() % self.sim.boids.len()].id);
+        }
+    }
+    /// Leave attract mode for the setup screen, as it was before
+    fn stop_attract(&mut self) {
+        let Some(attract) = self.attract.take() else {
+            return;
+        };
+        let (preset, camera) = attract.restore;
+        self.reset();
+        self.set_preset(preset);
+        self.camera.mode = camera;
+    }
+    /// Switch to preset `index`, or back to the parameters as they were without one
+    fn set_preset(&mut self, index: Option<usize>) {
+        let sims: Vec<&mut Simulation> = std::iter::once(&mut self.sim).chain(self.compare.as_mut()).collect();
+        let mut restore = std::mem::take(&mut self.preset_restore).into_iter();
+        for sim in sims {
+            let mut world = sim.world_bb.write().unwrap();
+            for (key, value) in restore.next().unwrap_or_default() {
+                world.insert(key, BBValue::F32(value));
+            }
+            if let Some(preset) = index.map(|i| &self.presets[i]) {
+                self.preset_restore.push(simulation::apply_preset(&mut world, &preset.entries()));
+            }
+        }
+        self.preset = index;
+    }
+    /// Save the current tuning as a new preset and switch to it
+    fn save_preset(&mut self) {
+        let name = presets::unused_name(&self.presets);
+        let mut preset = Preset::capture(&name, &self.sim.world_bb.read().unwrap(), self.sim.width, self.sim.height);
+        match presets::save(&mut preset) {
+            Ok(()) => {
+                println!("saved preset '{}'", name);
+                // the values it holds are already in place
+                self.preset_restore.clear();
+                self.presets.push(preset);
+                self.preset = Some(self.presets.len() - 1);
+            }
+            Err(e) => eprintln!("preset: {}", e),
+        }
+    }
+    /// Give the preset in use the name typed in
+    fn finish_rename(&mut self) {
+        let (Some(name), Some(index)) = (self.renaming.take(), self.preset) else {
+            return;
+        };
+        match presets::rename(&mut self.presets, index, &name) {
+            Ok(()) => println!("renamed preset to '{}'", self.presets[index].name),
+            Err(e) => eprintln!("preset: {}", e),
+        }
+    }
+    /// Delete the user preset in use, going back to the parameters from before it
+    fn delete_preset(&mut self) {
+        let Some(index) = self.preset else {
+            return;
+        };
+        if let Err(e) = presets::delete(&self.presets[index]) {
+            eprintln!("preset: {}", e);
+            return;
+        }
+        println!("deleted preset '{}'", self.presets[index].name);
+        self.set_preset(None);
+        // brings back a built-in preset the deleted one replaced
+        match presets::load() {
+            Ok(presets) => self.presets = presets,
+            Err(e) => {
+                eprintln!("presets: {}", e);
+                self.presets.remove(index);
+            }
+        }
+    }
+    /// The presets, marking the one in use, for the setup and pause screens
+    fn preset_lines(&self) -> Vec<String> {
+        let mut lines = vec![tr("presets:").to_string()];
+        let names = std::iter::once(tr("none").to_string()).chain(self.presets.iter().map(|p| match p.file {
+            Some(_) => p.name.clone(),
+            None => fill(tr("{} (built in)"), std::slice::from_ref(&p.name)),
+        }));
+        let current = self.preset.map_or(0, |i| i + 1);
+        for (i, name) in names.enumerate() {
+            lines.push(match &self.renaming {
+                Some(typed) if i == current => format!("> {}_", typed),
+                _ if i == current => format!("> {}", name),
+                _ => format!("  {}", name),
+            });
+        }
+        lines
+    }
+    fn draw_frame(&mut self, ctx: &mut Context, cache: &mut RenderCache) -> GameResult {
+        // glow stays off where its shaders don't compile
+        if self.show_glow && self.glow.is_none() {
+            match Glow::new(ctx) {
+                Ok(glow) => self.glow = Some(glow),
+                Err(e) => {
+                    eprintln!("glow: {}", e);
+                    self.show_glow = false;
+                }
+            }
+        }
+        let inset = match self.selection.single() {
+            Some(id) if self.show_inset => self.sim.boids.iter_mut().find(|b| b.id == id).map(|b| (b.pos(), b.visual_range())),
+            _ => None,
+        };
+        let cursor = self.world_cursor(ctx);
+        let screen = graphics::screen_coordinates(ctx);
+        // gathered up front, since it reads boid blackboards through `self`
+        let hud_text = match self.menu_state {
+            MenuState::Setup => Hud::default(),
+            _ => self.hud(cursor, self.cursor(ctx)),
+        };
+        let playfield = self.playfield();
+        let theme = &self.themes[self.theme];
+        let hud = &theme.hud;
+        let mut renderer = GgezRenderer::new(ctx, &self.points, cache, &self.selection, theme);
+        renderer.banking = self.show_banking;
+        renderer.rule_colors = self.show_rule_colors;
+        renderer.geometry = self.geometry;
+        renderer.trails = self.show_trails;
+        renderer.view = self.camera.matrix();
+        renderer.playfield = self.camera.viewport;
+        renderer.begin_frame()?;
+        // MENU: display controls
+        match self.menu_state {
+            MenuState::Setup if self.show_help => renderer.pass(|ctx| help::draw(ctx, hud))?,
+            MenuState::Setup => {
+                let menu: Vec<String> = [Command::Play, Command::Pause, Command::Reset, Command::ToggleColors,
+                    Command::ShowDensity, Command::NextPreset, Command::SavePreset, Command::RenamePreset,
+                    Command::DeletePreset, Command::Save, Command::Load, Command::Record, Command::Help]
+                    .iter()
+                    .map(|&c| format!("{} : <{}>", tr(keymap::binding(c).description), keymap::key_name(c)))
+                    .collect();
+                let menu = format!("{}\n\n{}", self.preset_lines().join("\n"), menu.join("\n"));
+                renderer.draw_banner(&menu, 56.0)?;
+            }
+            _ => {
+                let view = renderer.view;
+                renderer.pass(|ctx| {
+                    // glow has canvases of its own, at full resolution
+                    if let Some(glow) = self.glow.as_mut().filter(|_| self.show_glow) {
+                        glow.begin(ctx, theme.background.into())?;
+                    } else {
+                        self.downscale.begin(ctx, theme.background.into())?;
+                    }
+                    /*Density heatmap..*/
+                    if self.show_density {
+                        let mut image = self.density.to_image(ctx)?;
+                        image.set_filter(graphics::FilterMode::Linear);
+                        graphics::draw(
+                            ctx,
+                            &image,
+                            graphics::DrawParam::default().transform(
+                                view * glam::Mat4::from_scale(glam::vec3(self.density.cell_size, self.density.cell_size, 1.0))),
+                        )?;
+                    }
+                    /*Motion trails..*/
+                    if self.show_motion_trails {
+                        let compare = self.compare.as_ref().map(|b| (b, glam::vec2(self.sim.width, 0.0)));
+                        let sims = std::iter::once((&self.sim, glam::Vec2::ZERO)).chain(compare);
+                        self.motion_trails.update(ctx, sims, view, TRAIL_FADE)?;
+                        self.motion_trails.draw(ctx)?;
+                    }
+                    Ok(())
+                })?;
+                renderer.draw_boids(&self.sim, glam::Vec2::ZERO)?;
+                if let Some(b) = &self.compare {
+                    renderer.draw_boids(b, glam::vec2(self.sim.width, 0.0))?;
+                }
+                renderer.draw_shapes(|mb| {
+                    if self.compare.is_some() {
+                        mb.line(
+                            &[glam::vec2(self.sim.width, 0.0), glam::vec2(self.sim.width, WORLD_HEIGHT)],
+                            2.0,
+                            [1.0, 1.0, 1.0, 0.6].into(),
+                        )?;
+                    }
+                    if self.shake.flash > 0.0 {
+                        let world = graphics::Rect::new(0.0, 0.0, WORLD_WIDTH, WORLD_HEIGHT);
+                        mb.rectangle(graphics::DrawMode::fill(), world, [1.0, 1.0, 1.0, 0.2 * self.shake.flash].into())?;
+                    }
+                    self.puffs.draw(mb)?;
+                    /*Selection rectangle..*/
+                    if let Some(rect) = self.selection.drag_rect(glam::vec2(cursor.x, cursor.y)) {
+                        if rect.w > 0.0 && rect.h > 0.0 {
+                            mb.rectangle(graphics::DrawMode::stroke(1.0), rect, [1.0, 1.0, 1.0, 0.8].into())?;
+                        }
+                    }
+                    /*Velocity field..*/
+                    if self.show_velocity {
+                        VelocityField::sample(&self.sim.boids, WORLD_WIDTH, WORLD_HEIGHT, VELOCITY_CELL_SIZE)
+                            .draw_arrows(mb, boid::SPEED_LIMIT)?;
+                    }
+                    /*Steering forces of the inspected boid..*/
+                    if self.show_forces {
+                        if let Some(boid) = self.selection.single().and_then(|id| self.sim.boids.iter().find(|b| b.id == id)) {
+                            let max_force = get_f32(&self.sim.world_bb.read().unwrap(), "max_force");
+                            steering_view::draw_arrows(mb, boid, max_force)?;
+                        }
+                    }
+                    Ok(())
+                })?;
+                /*Highlight cursor..*/
+                renderer.draw_cursor(glam::vec2(cursor.x, cursor.y))?;
+                // the rest goes over the screen, not the world
+                renderer.flush()?;
+                renderer.view = glam::Mat4::IDENTITY;
+                /*Dim while paused..*/
+                if matches!(self.menu_state, MenuState::Pause | MenuState::Resume) {
+                    renderer.draw_shapes(|mb| {
+                        mb.rectangle(
+                            graphics::DrawMode::fill(),
+                            graphics::Rect::new(0.0, 0.0, playfield.x, playfield.y),
+                            [0.0, 0.0, 0.0, 0.5].into(),
+                        )?;
+                        Ok(())
+                    })?;
+                }
+                renderer.pass(|ctx| {
+                    if let Some(glow) = &mut self.glow {
+                        glow.finish(ctx)?;
+                    }
+                    self.downscale.finish(ctx)
+                })?;
+                /*Boid's-eye inset..*/
+                if let Some((center, range)) = inset {
+                    renderer.pass(|ctx| self.inset.begin(ctx, theme.background.into()))?;
+                    let camera = Inset::camera(center, range, screen);
+                    renderer.view = camera.matrix();
+                    renderer.draw_boids(&self.sim, glam::Vec2::ZERO)?;
+                    // a few screen pixels wide, so still visible once shrunk into the corner
+                    renderer.draw_shapes(|mb| {
+                        mb.circle(graphics::DrawMode::stroke(camera.world_length(4.0)), center, range, 0.5, [1.0, 1.0, 1.0, 0.4].into())?;
+                        Ok(())
+                    })?;
+                    renderer.view = glam::Mat4::IDENTITY;
+                    renderer.pass(|ctx| self.inset.finish(ctx))?;
+                }
+                if self.show_help {
+                    renderer.pass(|ctx| help::draw(ctx, hud))?;
+                }
+                if self.compare.is_some() {
+                    renderer.pass(|ctx| {
+                        for (i, label) in self.compare_labels.iter().enumerate() {
+                            let text = graphics::Text::new(graphics::TextFragment {
+                                text: label.clone(),
+                                color: Some(hud.text.into()),
+                                font: Some(graphics::Font::default()),
+                                scale: Some(graphics::PxScale::from(18.0)),
+                            });
+                            let middle = self.camera.to_screen(glam::vec2((i as f32 + 0.5) * self.sim.width, 0.0));
+                            let x = middle.x - text.width(ctx) / 2.0;
+                            graphics::draw(ctx, &text, graphics::DrawParam::default().dest(glam::vec2(x, playfield.y - 28.0)))?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                hud_text.draw(&mut renderer)?;
+                renderer.pass(|ctx| {
+                    if self.show_graphs && !self.panel.open {
+                        graphs::draw(ctx, &self.metrics, playfield.x, 0.0, hud)?;
+                    }
+                    if self.show_histogram {
+                        graphs::draw_speed_histogram(ctx, &self.sim.boids, playfield.x, playfield.y, boid::SPEED_LIMIT, hud)?;
+                    }
+                    Ok(())
+                })?;
+            }
+        };
+        if self.panel.open {
+            let world = self.sim.world_bb.read().unwrap();
+            // which boid actions fired last tick, to spot branches that never do
+            let mut stats = vec![String::new(), tr("boid actions (success / failure / running)").to_string()];
+            for (label, [success, failure, running]) in
+                bt_view::status_counts(&Boid::create_bt(), self.sim.boids.iter().map(|b| &b.trace)) {
+                stats.push(format!("  {:<16} {:>4} {:>4} {:>4}", label, success, failure, running));
+            }
+            let inspected = self.selection.single().and_then(|id| self.sim.boids.iter_mut().find(|b| b.id == id));
+            let boid = inspected.map(|b| (b.id, &*b.bt.get_blackboard().get_db()));
+            renderer.pass(|ctx| {
+                let graphs_top = self.panel.draw(ctx, &world, boid, &stats, playfield.x, playfield.y)?;
+                if self.show_graphs {
+                    graphs::draw(ctx, &self.metrics, playfield.x + self.panel.width, graphs_top, hud)?;
+                }
+                Ok(())
+            })?;
+        }
+        renderer.end_frame()
+    }
+    /// HUD text over a running or paused world: status lines, the pause and
+    /// countdown banners and the tooltip under the cursor. `cursor` is in
+    /// world units and `screen_cursor` in screen units.
+    fn hud(&mut self, cursor: Point2<f32>, screen_cursor: Point2<f32>) -> Hud {
+        let mut hud = Hud::default();
+        let lines = &mut hud.lines;
+        if self.recorder.is_some() {
+            lines.push(fill(tr("recording ({} to stop)"), &[keymap::key_name(Command::Record)]));
+        }
+        if let Some((frames, next)) = &self.playback {
+            lines.push(fill(tr("replay: frame {}/{}"), &[next.to_string(), frames.len().to_string()]));
+        }
+        if let Some(offset) = self.rewind.offset() {
+            lines.push(fill(tr("rewind: -{}s ({}/{} scrub)"), &[
+                format!("{:.2}", offset),
+                keymap::key_name(Command::ScrubBack),
+                keymap::key_name(Command::ScrubForward),
+            ]));
+        }
+        if let Some((_, m)) = self.metrics.samples.back() {
+            lines.push(fill(tr("polarization {}  angular momentum {}"), &[
+                format!("{:.2}", m.polarization),
+                format!("{:.2}", m.milling),
+            ]));
+            if m.is_milling() {
+                lines.push(tr("the flock is milling").to_string());
+            }
+        }
+        if let Some(migration) = &self.sim.migration {
+            lines.push(fill(tr("migration: {} crossings, {}% at the far edge"), &[
+                migration.crossings.to_string(),
+                format!("{:.0}", migration.arrived(&self.sim.boids, self.sim.width) * 100.0),
+            ]));
+        }
+        {
+            let world = self.sim.world_bb.read().unwrap();
+            let holds = [
+                ("polarization", "hold_polarization", &self.sim.polarization_hold),
+                ("spacing", "hold_spacing", &self.sim.spacing_hold),
+            ];
+            for (name, target, hold) in holds {
+                if let Some(hold) = hold {
+                    lines.push(fill(tr("holding {} at {}: {} {}"), &[
+                        tr(name).to_string(),
+                        format!("{:.2}", get_f32(&world, target)),
+                        hold.factor.to_string(),
+                        format!("{:.2}", get_f32(&world, hold.factor)),
+                    ]));
+                }
+            }
+        }
+        if self.downscale.enabled && self.downscale.scale < 1.0 && !self.show_glow {
+            lines.push(fill(tr("rendering at {}% resolution"), &[format!("{:.0}", self.downscale.scale * 100.0)]));
+        }
+        if let Some(fps) = self.frame_cap.fps {
+            lines.push(fill(tr("frame rate capped at {} fps"), &[fps.to_string()]));
+        }
+        if self.camera.mode != CameraMode::Fixed {
+            lines.push(fill(tr("camera: {}"), &[tr(self.camera.mode.name()).to_string()]));
+        }
+        if self.sim.catches > 0 {
+            lines.push(fill(tr("caught: {}"), &[self.sim.catches.to_string()]));
+        }
+        if self.sim.outbreak {
+            let [s, i, r] = epidemic::counts(&self.sim.boids);
+            lines.push(fill(tr("susceptible {}  infected {}  recovered {}"), &[s.to_string(), i.to_string(), r.to_string()]));
+        }
+        if self.sim.opinions_since.is_some() {
+            let count = opinion::count(&self.sim.world_bb.read().unwrap());
+            let counts: Vec<String> = opinion::counts(&self.sim.boids, count).iter().map(|c| c.to_string()).collect();
+            lines.push(fill(tr("opinions: {}"), &[counts.join(" / ")]));
+            if let Some(after) = self.sim.consensus_after {
+                lines.push(fill(tr("consensus after {}s"), &[format!("{:.1}", after)]));
+            }
+        }
+        if self.sim.scenario.nest.is_some() {
+            lines.push(fill(tr("food brought to the nest: {}"), &[self.sim.delivered.to_string()]));
+        }
+        if self.show_memory {
+            lines.extend(self.memory_usage().lines(self.memory_budget));
+        } else if self.over_budget {
+            lines.push(tr("over the memory budget").to_string());
+        }
+        if self.sim.splits > 0 {
+            lines.push(fill(tr("splits: {}"), &[self.sim.splits.to_string()]));
+        }
+        if !self.sim.scenario.lanes.is_empty() {
+            let per_minute = if self.sim.clock > 0.0 { self.sim.passages as f32 / self.sim.clock * 60.0 } else { 0.0 };
+            lines.push(fill(tr("lane passages: {} ({} per minute)"), &[
+                self.sim.passages.to_string(),
+                format!("{:.0}", per_minute),
+            ]));
+        }
+        if let Some(stats) = self.selection.stats(&self.sim.boids) {
+            lines.push(fill(tr("selected: {}"), &[stats.count.to_string()]));
+            lines.push(fill(tr("centroid: ({}, {})"), &[format!("{:.0}", stats.centroid.x), format!("{:.0}", stats.centroid.y)]));
+            lines.push(fill(tr("mean speed: {}"), &[format!("{:.1}", stats.mean_speed)]));
+        }
+        if let Some(id) = self.selection.single() {
+            if let Some(boid) = self.sim.boids.iter_mut().find(|b| b.id == id) {
+                lines.push(fill(tr("boid #{} tree: {}"), &[boid.id.to_string(), format!("{:?}", boid.status)]));
+                if boid.frozen {
+                    lines.push(fill(tr("frozen (<{}> to thaw)"), &[keymap::key_name(Command::ToggleFrozen)]));
+                }
+                lines.extend(bt_view::tree_lines(&Boid::create_bt(), &boid.trace));
+                lines.push(tr("blackboard:").to_string());
+                lines.extend(bt_view::blackboard_lines(boid.bt.get_blackboard().get_db()));
+                if self.show_forces {
+                    lines.push(tr("steering forces:").to_string());
+                    lines.extend(steering_view::force_lines(&boid.steering));
+                }
+            }
+        }
+        if self.show_help {
+            lines.clear();
+        }
+        if self.menu_state == MenuState::Pause {
+            let mut text = tr("PAUSED").to_string();
+            if let Some((_, m)) = self.metrics.samples.back() {
+                text.push('\n');
+                text.push_str(&fill(tr("{}s  {} boids\npolarization {}  clusters {}\n<{}> to resume"), &[
+                    format!("{:.1}", self.metrics.now()),
+                    self.sim.boids.len().to_string(),
+                    format!("{:.2}", m.polarization),
+                    m.clusters.to_string(),
+                    keymap::key_name(Command::Play),
+                ]));
+            }
+            if self.focus_paused {
+                text.push('\n');
+                text.push_str(tr("click to resume"));
+            }
+            hud.banner = Some((text, 48.0));
+            hud.panels.push((self.preset_lines(), glam::vec2(16.0, self.playfield().y / 2.0)));
+        } else if self.sim.migration.as_ref().is_some_and(|m| m.finished) {
+            hud.banner = Some((tr("migration complete").to_string(), 48.0));
+        }
+        if self.attract.is_some() {
+            let preset = self.preset.map_or("", |i| self.presets[i].name.as_str());
+            let lines = vec![preset.to_string(), tr("press any key").to_string()];
+            hud.panels.push((lines, glam::vec2(16.0, self.playfield().y - 64.0)));
+        }
+        /*Tooltip for the boid under the cursor, while paused..*/
+        if self.menu_state == MenuState::Pause {
+            if let Some(id) = selection::nearest(&self.sim.boids, cursor.into(), self.geometry.pick_radius) {
+                let boid = self.sim.boids.iter_mut().find(|b| b.id == id).unwrap();
+                let (pos, range) = (boid.pos(), boid.visual_range());
+                let speed = boid.vel().length();
+                let energy = get_f32(boid.bt.get_blackboard().get_db(), "energy");
+                let neighbors = self.sim.boids.iter().filter(|b| b.id != id && b.pos().distance(pos) < range).count();
+                let lines = vec![
+                    fill(tr("boid #{}"), &[id.to_string()]),
+                    fill(tr("speed: {}"), &[format!("{:.1}", speed)]),
+                    fill(tr("neighbors: {}"), &[neighbors.to_string()]),
+                    fill(tr("energy: {}%"), &[format!("{:.0}", energy * 100.0)]),
+                ];
+                hud.panels.push((lines, glam::Vec2::from(screen_cursor) + glam::vec2(16.0, 16.0)));
+            }
+        }
+        if self.menu_state == MenuState::Resume {
+            hud.banner = Some((format!("{}", self.countdown.ceil().max(1.0)), 160.0));
+        }
+        hud
     }
     fn game_op_tick(&mut self,
                     dt: f32,
                     pressed_keys: &HashSet<VirtualKeyCode>,
+                    just_pressed: &HashSet<VirtualKeyCode>,
+                    buttons: MouseButtons,
                     cursor: Point2<f32>) {
         let e: Event = UpdateArgs { dt: dt.into() }.into();
         let mut game_op_bt = self.game_op_bt.clone();
-        game_op_bt.tick(&e, &mut |args: ActionArgs<Event, OperationState>|
+        let mut run = |args: ActionArgs<Event, OperationState>|
             match args.action {
                 OperationState::InputKey => {
+                    // in a lockstep session anything that changes only this peer's world is off
+                    let local = self.lockstep.is_none();
                     if pressed_keys.is_empty() {
                     } else {
+                        // overlays
+                        if keymap::pressed(just_pressed, Command::ShowDensity) {
+                            self.show_density = !self.show_density;
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowVelocity) {
+                            self.show_velocity = !self.show_velocity;
+                        }
+                        if keymap::pressed(just_pressed, Command::Help) {
+                            self.show_help = !self.show_help;
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowBanking) {
+                            self.show_banking = !self.show_banking;
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowMotionTrails) {
+                            self.show_motion_trails = !self.show_motion_trails;
+                            self.motion_trails.clear();
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowTrails) {
+                            self.show_trails = !self.show_trails;
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowMemory) {
+                            self.show_memory = !self.show_memory;
+                        }
+                        if keymap::pressed(just_pressed, Command::ToggleLowRes) {
+                            self.downscale.enabled = !self.downscale.enabled;
+                        }
+                        if keymap::pressed(just_pressed, Command::CycleFrameCap) {
+                            self.frame_cap.cycle();
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowGlow) {
+                            self.show_glow = !self.show_glow;
+                        }
+                        // side by side runs share the window, so the camera stays put
+                        if keymap::pressed(just_pressed, Command::CycleCamera) && self.compare.is_none() {
+                            self.camera.mode = self.camera.mode.next();
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowInset) {
+                            self.show_inset = !self.show_inset;
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowRuleColors) {
+                            self.show_rule_colors = !self.show_rule_colors;
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowForces) {
+                            self.show_forces = !self.show_forces;
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowGraphs) {
+                            self.show_graphs = !self.show_graphs;
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowHistogram) {
+                            self.show_histogram = !self.show_histogram;
+                        }
+                        if keymap::pressed(just_pressed, Command::DumpTrees) {
+                            dump_trees();
+                        }
+                        if keymap::pressed(just_pressed, Command::ExportDot) {
+                            match self.export_dot() {
+                                Ok(()) => println!("wrote {}", DOT_FILE),
+                                Err(e) => eprintln!("{}: {}", DOT_FILE, e),
+                            }
+                        }
+                        if keymap::pressed(just_pressed, Command::ShowPanel) {
+                            self.panel.open = !self.panel.open;
+                        }
+                        if keymap::pressed(just_pressed, Command::NextTheme) {
+                            self.set_theme((self.theme + 1) % self.themes.len());
+                        }
+                        if keymap::pressed(just_pressed, Command::SavePreset) {
+                            self.save_preset();
+                        }
+                        if matches!(self.menu_state, MenuState::Setup | MenuState::Pause) && local {
+                            // none, then each preset in turn
+                            if keymap::pressed(just_pressed, Command::NextPreset) {
+                                let next = self.preset.map_or(0, |i| i + 1);
+                                self.set_preset((next < self.presets.len()).then_some(next));
+                            }
+                            if keymap::pressed(just_pressed, Command::RenamePreset) {
+                                if let Some(preset) = self.preset.map(|i| &self.presets[i]).filter(|p| p.file.is_some()) {
+                                    self.renaming = Some(preset.name.clone());
+                                }
+                            }
+                            if keymap::pressed(just_pressed, Command::DeletePreset) {
+                                self.delete_preset();
+                            }
+                        }
+                        if keymap::pressed(just_pressed, Command::ToggleFrozen) && local {
+                            self.sim.toggle_frozen(&self.selection.ids);
+                        }
+                        if keymap::pressed(just_pressed, Command::PropagateTraits) && local {
+                            if let Some(id) = self.selection.single() {
+                                let count = self.sim.propagate_traits(id);
+                                println!("boid #{}'s traits copied to {} boids", id, count);
+                            }
+                        }
+                        if local {
+                            let inspected = self.selection.single().and_then(|id| self.sim.boids.iter_mut().find(|b| b.id == id));
+                            let boid = inspected.map(|b| b.bt.get_blackboard().get_db());
+                            self.panel.handle_keys(just_pressed, &mut self.sim.world_bb.write().unwrap(), boid);
+                        }
+                        // saved states and recordings
+                        if keymap::pressed(just_pressed, Command::Save) && self.menu_state != MenuState::Setup {
+                            if let Err(e) = self.save_state() {
+                                eprintln!("{}: {}", STATE_FILE, e);
+                            }
+                        }
+                        if keymap::pressed(just_pressed, Command::Load) && local {
+                            match self.load_state() {
+                                Ok(()) => self.menu_state = MenuState::Pause,
+                                Err(e) => eprintln!("{}: {}", STATE_FILE, e),
+                            }
+                        }
+                        if keymap::pressed(just_pressed, Command::Record) && self.menu_state == MenuState::Play {
+                            if let Err(e) = self.toggle_recording() {
+                                eprintln!("{}: {}", REPLAY_FILE, e);
+                            }
+                        }
                         // -> setup
-                        if pressed_keys.contains(&event::KeyCode::R) {
-                            self.menu_state = MenuState::Setup;
-                            self.boids.drain(..);
+                        if keymap::pressed(pressed_keys, Command::Reset) && local {
+                            self.reset();
                         } else {
                             match self.menu_state {
                                 MenuState::Setup => {
                                     // -> play
-                                    if pressed_keys.contains(&event::KeyCode::Space) {
-                                        self.boids = Boid::create_boids(
-                                            &self.boid_bt,
-                                            OBJECT_COUNT,
-                                            WINDOW_WIDTH,
-                                            WINDOW_HEIGHT);
-                                        self.menu_state = MenuState::Play;
+                                    if keymap::pressed(pressed_keys, Command::Play) {
+                                        self.play();
                                     }
                                 }
                                 MenuState::Pause => {
-                                    // -> play
-                                    if pressed_keys.contains(&event::KeyCode::Space) {
-                                        self.menu_state = MenuState::Play;
+                                    // scrub through the rewind buffer
+                                    let steps = if keymap::pressed(pressed_keys, Command::ScrubBack) {
+                                        -1
+                                    } else if keymap::pressed(pressed_keys, Command::ScrubForward) {
+                                        1
+                                    } else {
+                                        0
+                                    };
+                                    if steps != 0 {
+                                        self.rewind.scrub(steps, &mut self.sim, self.compare.as_mut());
+                                    }
+                                    // -> resume countdown
+                                    if keymap::pressed(pressed_keys, Command::Play) {
+                                        self.resume();
                                     }
                                 }
+                                MenuState::Resume => {}
                                 MenuState::Play => {
                                     // -> pause
-                                    if pressed_keys.contains(&event::KeyCode::P) {
+                                    if keymap::pressed(pressed_keys, Command::Pause) && local {
                                         self.menu_state = MenuState::Pause;
                                     }
+                                    if keymap::pressed(just_pressed, Command::Spawn) {
+                                        self.spawn_request = self.spawn_request.saturating_add(SPAWN_BATCH);
+                                    }
+                                    // toggle color convergence
+                                    if keymap::pressed(just_pressed, Command::ToggleColors) && local {
+                                        let mut world = self.sim.world_bb.write().unwrap();
+                                        let blend = get_bool(&world, "color_blend");
+                                        world.insert("color_blend".to_string(), BBValue::Bool(!blend));
+                                    }
+                                    // free flocking <-> formation flight
+                                    if keymap::pressed(just_pressed, Command::ToggleFormation) && local {
+                                        let mut world = self.sim.world_bb.write().unwrap();
+                                        let formation = get_bool(&world, "formation");
+                                        world.insert("formation".to_string(), BBValue::Bool(!formation));
+                                    }
+                                    if keymap::pressed(just_pressed, Command::ToggleMates) && local {
+                                        let mut world = self.sim.world_bb.write().unwrap();
+                                        let mates = get_bool(&world, "mates");
+                                        world.insert("mates".to_string(), BBValue::Bool(!mates));
+                                    }
+                                    if keymap::pressed(just_pressed, Command::ToggleChains) && local {
+                                        let mut world = self.sim.world_bb.write().unwrap();
+                                        let chains = get_bool(&world, "chains");
+                                        world.insert("chains".to_string(), BBValue::Bool(!chains));
+                                    }
+                                    if keymap::pressed(just_pressed, Command::ToggleGrowth) && local {
+                                        let mut world = self.sim.world_bb.write().unwrap();
+                                        let growth = get_bool(&world, "growth");
+                                        world.insert("growth".to_string(), BBValue::Bool(!growth));
+                                    }
+                                    if keymap::pressed(just_pressed, Command::ToggleEpidemic) && local {
+                                        let mut world = self.sim.world_bb.write().unwrap();
+                                        let epidemic = get_bool(&world, "epidemic");
+                                        world.insert("epidemic".to_string(), BBValue::Bool(!epidemic));
+                                    }
+                                    if keymap::pressed(just_pressed, Command::ToggleOpinions) && local {
+                                        let mut world = self.sim.world_bb.write().unwrap();
+                                        let opinions = get_bool(&world, "opinions");
+                                        world.insert("opinions".to_string(), BBValue::Bool(!opinions));
+                                    }
+                                    if keymap::pressed(just_pressed, Command::ToggleMilling) && local {
+                                        let mut world = self.sim.world_bb.write().unwrap();
+                                        let milling = !get_bool(&world, "milling");
+                                        world.insert("milling".to_string(), BBValue::Bool(milling));
+                                        if milling {
+                                            self.milling_restore = simulation::apply_preset(&mut world, simulation::MILLING_PRESET);
+                                        } else {
+                                            for (key, value) in self.milling_restore.drain(..) {
+                                                world.insert(key, BBValue::F32(value));
+                                            }
+                                        }
+                                    }
                                 }
                             };
                         }
                     }
 
-                    if self.menu_state == MenuState::Play {
+                    if matches!(self.menu_state, MenuState::Play | MenuState::Resume) {
                         (Success, args.dt)
                     } else {
                         (Failure, args.dt)
                     }
                 }
+                OperationState::Countdown => {
+                    if self.menu_state == MenuState::Resume {
+                        self.countdown -= dt;
+                        if self.countdown > 0.0 {
+                            return (Failure, args.dt);
+                        }
+                        self.menu_state = MenuState::Play;
+                    }
+                    (Success, args.dt)
+                }
+                OperationState::InputMouse => {
+                    let at = glam::vec2(cursor.x, cursor.y);
+                    // remove the boid under the cursor
+                    if buttons.left && !self.prev_buttons.left && buttons.shift {
+                        if let Some(id) = selection::nearest(&self.sim.boids, at, self.geometry.pick_radius).filter(|_| self.lockstep.is_none()) {
+                            let boid = self.sim.boids.iter().find(|b| b.id == id).unwrap();
+                            self.puffs.burst(boid.pos(), boid.color);
+                            self.sim.despawn(id);
+                            self.selection.ids.remove(&id);
+                        }
+                    } else if buttons.left && !self.prev_buttons.left && buttons.ctrl {
+                        if let Some(id) = selection::nearest(&self.sim.boids, at, self.geometry.pick_radius).filter(|_| self.lockstep.is_none()) {
+                            self.grab = Some(Grab { id, last: at, velocity: glam::Vec2::ZERO });
+                            self.sim.held = Some(id);
+                        }
+                    // rectangle selection
+                    } else if buttons.left && !self.prev_buttons.left {
+                        self.selection.begin_drag(at);
+                    } else if !buttons.left && self.selection.is_dragging() {
+                        self.selection.end_drag(at, &self.sim.boids, self.geometry.pick_radius);
+                    }
+                    // a dragged boid stays on the cursor and is flung at the cursor's speed when let go
+                    if self.sim.held.is_none() {
+                        self.grab = None;
+                    }
+                    if let Some(grab) = &mut self.grab {
+                        if buttons.left {
+                            if dt > 0.0 {
+                                grab.velocity = grab.velocity.lerp((at - grab.last) / dt, FLING_SMOOTHING);
+                            }
+                            grab.last = at;
+                            self.sim.place(grab.id, at, glam::Vec2::ZERO);
+                        } else {
+                            self.sim.place(grab.id, at, grab.velocity);
+                            self.sim.held = None;
+                            self.grab = None;
+                        }
+                    }
+                    // send the selection to the clicked point
+                    if buttons.right && !self.prev_buttons.right && self.lockstep.is_none() {
+                        for i in 0..self.sim.boids.len() {
+                            if self.selection.contains(self.sim.boids[i].id) {
+                                self.sim.send_to(i, at);
+                            }
+                        }
+                    }
+                    self.prev_buttons = buttons;
+
+                    (Success, args.dt)
+                }
                 OperationState::UpdateGameData => {
-                    let tick = (self.dt.subsec_millis() as f32) / 1000.0;
-                    for i in 0..(self.boids).len() {
-                        let boids_vec = self.boids.to_vec();
-                        let boid = &mut self.boids[i];
-                        Boid::game_tick(
-                            self.dt.as_secs_f32(),
-                            cursor,
-                            boid,
-                            boids_vec,
-                        );
-
-                        //Convert new velocity to postion change
-                        boid.x += (boid.dx * tick);
-                        boid.y += (boid.dy * tick);
-
-                        self.boids[i] = boid.clone();
+                    let mut dt = self.dt.as_secs_f32();
+                    #[cfg(feature = "irc")]
+                    self.apply_chat();
+                    if let Some((frames, next)) = &mut self.playback {
+                        match frames.get(*next) {
+                            Some(frame) => {
+                                // metrics follow recorded time, not this frame's
+                                self.sim.apply_states(&frame.boids);
+                                dt = frame.dt;
+                                *next += 1;
+                            }
+                            None => self.menu_state = MenuState::Pause,
+                        }
+                    } else if let Some(session) = &mut self.lockstep {
+                        match lockstep_tick(session, &mut self.spawn_request, cursor) {
+                            Ok(Some(inputs)) => {
+                                dt = LOCKSTEP_DT as f32;
+                                apply_inputs(&mut self.sim, self.compare.as_mut(), &inputs);
+                            }
+                            // waiting on the peer, nothing new to record
+                            Ok(None) => return (Success, args.dt),
+                            Err(e) => {
+                                eprintln!("lockstep: {}", e);
+                                self.lockstep = None;
+                                self.menu_state = MenuState::Pause;
+                                return (Success, args.dt);
+                            }
+                        }
+                    } else if self.pipelined {
+                        hatch(&mut self.sim, self.compare.as_mut(), std::mem::take(&mut self.spawn_request));
+                        // stepped during draw; what follows sees the tick finished last frame
+                        self.pending_step = Some((self.dt, cursor));
+                    } else {
+                        hatch(&mut self.sim, self.compare.as_mut(), std::mem::take(&mut self.spawn_request));
+                        let start = Instant::now();
+                        step_sims(&mut self.sim, self.compare.as_mut(), self.dt, cursor);
+                        if let Some(bench) = &mut self.bench {
+                            bench.record_tick(start.elapsed());
+                        }
+                    }
+                    let world = self.sim.world_bb.read().unwrap();
+                    self.rewind.record(dt, &self.sim.boids, self.compare.as_ref().map(|b| b.boids.as_slice()));
+                    if let Some(recorder) = &mut self.recorder {
+                        if let Err(e) = recorder.write_frame(dt, &BoidState::capture(&self.sim.boids)) {
+                            eprintln!("{}: {}", REPLAY_FILE, e);
+                            self.recorder = None;
+                        }
                     }
+                    self.density.accumulate(&self.sim.boids);
+                    self.metrics.push(dt, Metrics::measure(&self.sim.boids, get_f32(&world, "cluster_distance")));
                     (Success, args.dt)
                 }
-            },
-        );
+            };
+        let mut trace = HashMap::new();
+        game_op_bt.tick(&e, &mut |args: ActionArgs<Event, OperationState>| {
+            let label = format!("{:?}", args.action);
+            let result = run(args);
+            trace.insert(label, result.0);
+            result
+        });
+        self.op_trace = trace;
     }
 }
 
 impl event::EventHandler for GameWorld {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        self.dt = timer::delta(ctx);
+        self.frame_cap.background = self.focus_paused;
+        self.frame_cap.wait();
+        // the benchmarks time real frames, everything else steps by the smoothed delta
+        let delta = timer::delta(ctx);
+        self.dt = self.frame_time.smooth(delta);
         let pressed_keys =
             input::keyboard::pressed_keys(ctx);
-        let cursor: Point2<f32> =
-            input::mouse::position(ctx);
+        let just_pressed: HashSet<VirtualKeyCode> =
+            pressed_keys.difference(&self.prev_keys).cloned().collect();
+        self.prev_keys = pressed_keys.clone();
+        let mut buttons = MouseButtons {
+            left: input::mouse::button_pressed(ctx, MouseButton::Left),
+            right: input::mouse::button_pressed(ctx, MouseButton::Right),
+            shift: input::keyboard::is_mod_active(ctx, input::keyboard::KeyMods::SHIFT),
+            ctrl: input::keyboard::is_mod_active(ctx, input::keyboard::KeyMods::CTRL),
+        };
+        let mut cursor = self.world_cursor(ctx);
+        // a click back in the window picks up where losing focus paused, and goes no further
+        self.focus_paused &= self.menu_state == MenuState::Pause;
+        if self.focus_paused && buttons.left {
+            self.focus_paused = false;
+            self.swallow_input = true;
+            self.resume();
+        }
+        // attract mode runs after ATTRACT_AFTER idle seconds on the setup screen, until any key or click
+        let touched = !pressed_keys.is_empty() || buttons.left || buttons.right;
+        self.idle = if touched { 0.0 } else { self.idle + self.dt.as_secs_f32() };
+        self.swallow_input &= touched;
+        if let Some(attract) = &mut self.attract {
+            attract.shot -= self.dt.as_secs_f32();
+            if touched {
+                self.stop_attract();
+                self.swallow_input = true;
+            } else if attract.shot <= 0.0 {
+                attract.shot = ATTRACT_SHOT;
+                self.next_attract_shot();
+            }
+        } else if self.menu_state == MenuState::Setup && !self.show_help && self.lockstep.is_none() && self.playback.is_none()
+            && self.attract_after > 0.0 && self.idle >= self.attract_after && !self.presets.is_empty() {
+            self.start_attract();
+        }
+        if self.attract.is_some() || self.swallow_input {
+            buttons = MouseButtons::default();
+            cursor = simulation::NO_CURSOR;
+        }
+        // typing a preset name
+        let typing = HashSet::new();
+        let (pressed_keys, just_pressed) = match self.renaming {
+            Some(_) => (&typing, HashSet::new()),
+            None if self.swallow_input => (&typing, HashSet::new()),
+            None => (pressed_keys, just_pressed),
+        };
         self.game_op_tick(
             self.dt.as_secs_f32(),
             pressed_keys,
+            &just_pressed,
+            buttons,
             cursor);
+        let followed = self.selection.single().and_then(|id| self.sim.boids.iter().find(|b| b.id == id));
+        self.camera.update(self.dt.as_secs_f32(), &self.sim.boids, followed, glam::vec2(WORLD_WIDTH, WORLD_HEIGHT));
+        let catches = self.sim.catches + self.compare.as_ref().map_or(0, |b| b.catches);
+        self.shake.update(self.dt.as_secs_f32(), catches);
+        // boids that ran into obstacles puff where they hit, the compared flock's off to the right
+        #[cfg(feature = "rapier")]
+        {
+            for impact in self.sim.physics.impacts.drain(..) {
+                self.puffs.burst(impact.pos, impact.color);
+            }
+            let offset = glam::vec2(self.sim.width, 0.0);
+            for impact in self.compare.iter_mut().flat_map(|b| b.physics.impacts.drain(..)) {
+                self.puffs.burst(impact.pos + offset, impact.color);
+            }
+        }
+        self.camera.shake = self.shake.offset();
+        self.puffs.update(self.dt.as_secs_f32());
+
+        // grow the window to dock the control panel beside the simulation
+        if self.panel.docked_width() != self.docked_width {
+            self.fit_window(ctx)?;
+        }
+        if self.bench.as_mut().is_some_and(|b| b.frame(delta.as_secs_f32())) {
+            let report = self.bench.take().unwrap().report(self.sim.boids.len(), self.pipelined);
+            if let Err(e) = bench::write(&report) {
+                eprintln!("bench: {}", e);
+            }
+            event::quit(ctx);
+        }
+        if let Some(budget) = self.memory_budget {
+            let total = self.memory_usage().total();
+            if total > budget && !self.over_budget {
+                eprintln!("memory: {} is over the budget of {}", memory::format_bytes(total), memory::format_bytes(budget));
+            }
+            self.over_budget = total > budget;
+        }
+        match self.soak.as_mut().map(|soak| soak.frame(delta.as_secs_f32())) {
+            Some(SoakStep::Grow(boids)) => self.spawn_all(boids),
+            Some(SoakStep::Done(sustained)) => {
+                let frame_ms = self.soak.take().unwrap().frame_ms;
+                match sustained {
+                    Some(boids) => println!("soak: {} boids keep frames under {:.1} ms", boids, frame_ms),
+                    None => println!("soak: even {} boids take over {:.1} ms a frame", bench::SOAK_START, frame_ms),
+                }
+                event::quit(ctx);
+            }
+            Some(SoakStep::Hold) | None => {}
+        }
         Ok(())
     }
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        graphics::clear(ctx, [0.15, 0.2, 0.22, 1.0].into());
-        // MENU: display controls
-        match self.menu_state {
-            MenuState::Setup => {
-                let menu_text = graphics::Text::new(graphics::TextFragment {
-                    text: "play : <space>\npause : <p>\nreset : <r>".to_string(),
-                    color: Some(graphics::Color::WHITE),
-                    font: Some(graphics::Font::default()),
-                    scale: Some(graphics::PxScale::from(100.0)),
-                });
-
-                let text_pos = glam::vec2(
-                    (WINDOW_WIDTH - menu_text.width(ctx) as f32) / 2.0,
-                    (WINDOW_HEIGHT - menu_text.height(ctx) as f32) / 2.0,
-                );
-
-                graphics::draw(
-                    ctx,
-                    &menu_text,
-                    graphics::DrawParam::default().dest(text_pos),
-                )?;
+        let (dt, cursor) = match self.pending_step.take() {
+            Some(step) => step,
+            None => return self.draw_timed(ctx),
+        };
+        // bring the front buffer up to this tick, then step the simulation itself
+        // (the back buffer) on a worker while the front one is drawn
+        let start = Instant::now();
+        let (mut front, mut front_compare) = self.shown.take().unwrap_or_else(|| (self.sim.clone(), self.compare.clone()));
+        let inspected = self.selection.single();
+        self.sim.copy_view(&mut front, inspected);
+        if let (Some(b), Some(front_b)) = (&mut self.compare, &mut front_compare) {
+            b.copy_view(front_b, inspected);
+        }
+        if let Some(bench) = &mut self.bench {
+            bench.record_draw(start.elapsed());
+        }
+        let mut back = std::mem::replace(&mut self.sim, front);
+        let mut back_compare = std::mem::replace(&mut self.compare, front_compare);
+        let (result, tick_time) = thread::scope(|s| {
+            let worker = s.spawn(|| {
+                let start = Instant::now();
+                step_sims(&mut back, back_compare.as_mut(), dt, cursor);
+                start.elapsed()
+            });
+            (self.draw_timed(ctx), worker.join().expect("simulation worker panicked"))
+        });
+        let front = std::mem::replace(&mut self.sim, back);
+        let front_compare = std::mem::replace(&mut self.compare, back_compare);
+        self.shown = Some((front, front_compare));
+        if let Some(bench) = &mut self.bench {
+            bench.record_tick(tick_time);
+        }
+        result
+    }
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: VirtualKeyCode, _keymods: input::keyboard::KeyMods, _repeat: bool) {
+        match (&mut self.renaming, keycode) {
+            (Some(_), VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter) => self.finish_rename(),
+            (Some(_), VirtualKeyCode::Escape) => self.renaming = None,
+            (Some(typed), VirtualKeyCode::Back) => {
+                typed.pop();
             }
-            _ => {
-                let mb = &mut graphics::MeshBuilder::new();
-                for boid in &self.boids {
-                    let rot = glam::Mat2::from_angle(boid.dx.atan2(-boid.dy));
-                    let pos = glam::vec2(boid.x, boid.y);
-                    mb.polygon(
-                        graphics::DrawMode::fill(),
-                        &[
-                            (rot * self.points[0]) + pos,
-                            (rot * self.points[1]) + pos,
-                            (rot * self.points[2]) + pos,
-                            (rot * self.points[3]) + pos,
-                        ],
-                        boid.color.into(),
-                    )?;
-                }
-                /*Highlight cursor..*/
-                mb.circle(
-                    graphics::DrawMode::fill(),
-                    input::mouse::position(ctx),
-                    10.0,
-                    0.1,
-                    [1.0, 1.0, 1.0, 0.5].into(),
-                )?;
-                let line = &[
-                    glam::vec2(0.0, 0.0),
-                    glam::vec2(50.0, 5.0),
-                    glam::vec2(42.0, 10.0),
-                    glam::vec2(150.0, 100.0),
-                ];
-                mb.polyline(
-                    graphics::DrawMode::stroke(2.0),
-                    line,
-                    [1.0, 1.0, 1.0, 1.0].into(),
-                )?;
-                let m = mb.build(ctx)?;
-                graphics::draw(ctx, &m, graphics::DrawParam::new())?;
+            (Some(_), _) => {}
+            (None, VirtualKeyCode::Escape) => {
+                self.save_window(ctx);
+                event::quit(ctx);
             }
-        };
-        graphics::present(ctx)
+            (None, _) => {}
+        }
+    }
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        self.windowed = Some(WindowState::capture(ctx, self.windowed));
+        if let Err(e) = self.fit_layout(ctx, width, height) {
+            eprintln!("window: {}", e);
+        }
+    }
+    fn quit_event(&mut self, ctx: &mut Context) -> bool {
+        self.save_window(ctx);
+        false
     }
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) {
+        // a networked session can't stop for one peer, and the demos run unattended
+        let local = self.lockstep.is_none() && self.attract.is_none() && self.bench.is_none() && self.soak.is_none();
+        if !gained && self.pause_on_focus_loss && self.menu_state == MenuState::Play && local {
+            self.menu_state = MenuState::Pause;
+            self.focus_paused = true;
+        }
+    }
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        if let Some(typed) = &mut self.renaming {
+            if !character.is_control() {
+                typed.push(character);
+            }
+        }
+    }
+}
+
+/// Print the boid and game-loop behavior trees to stdout
+fn dump_trees() {
+    for (name, lines) in [
+        ("boid", bt_view::structure_lines(&Boid::create_bt())),
+        ("game loop", bt_view::structure_lines(&GameWorld::create_behavior())),
+    ] {
+        println!("{} tree:", name);
+        for line in lines {
+            println!("  {}", line);
+        }
+    }
+}
+
+/// Graphviz file of the boid and game-loop trees, colored by the given statuses
+fn trees_dot(boid_trace: &HashMap<String, Status>, op_trace: &HashMap<String, Status>) -> String {
+    bt_view::dot(&[
+        bt_view::dot_cluster(0, "boid", &Boid::create_bt(), boid_trace),
+        bt_view::dot_cluster(1, "game loop", &GameWorld::create_behavior(), op_trace),
+    ])
+}
+
+/// Text drawn over a running or paused world
+#[derive(Default)]
+struct Hud {
+    /// Status lines at the top left
+    lines: Vec<String>,
+    /// Text panels and their top-left corners, in screen units
+    panels: Vec<(Vec<String>, glam::Vec2)>,
+    /// Message across the middle of the screen, and its size
+    banner: Option<(String, f32)>,
+}
+
+impl Hud {
+    fn draw<R: Renderer>(&self, renderer: &mut R) -> Result<(), R::Error> {
+        renderer.draw_overlay(&self.lines)?;
+        if let Some((text, size)) = &self.banner {
+            renderer.draw_banner(text, *size)?;
+        }
+        for (lines, at) in &self.panels {
+            renderer.draw_overlay_at(lines, *at)?;
+        }
+        Ok(())
+    }
+}
+
+/// Advance the main simulation and, in compare mode, the B side
+fn step_sims(sim: &mut Simulation, compare: Option<&mut Simulation>, dt: std::time::Duration, cursor: Point2<f32>) {
+    match compare {
+        Some(b) => {
+            // shared cursor, mapped into each half's local coordinates
+            let local = Point2 { x: cursor.x % b.width, y: cursor.y };
+            sim.step(dt, local);
+            b.step(dt, local);
+        }
+        None => sim.step(dt, cursor),
+    }
+}
+
+/// Hatch `count` boids into each simulation
+fn hatch(sim: &mut Simulation, mut compare: Option<&mut Simulation>, count: u8) {
+    for _ in 0..count {
+        sim.spawn_boid();
+        if let Some(b) = &mut compare {
+            b.spawn_boid();
+        }
+    }
+}
+
+/// Send this peer's input if the session has room for it, then return both
+/// players' inputs for the next tick if they have arrived. A cursor outside
+/// the world goes as none, leaving the other player's to steer.
+fn lockstep_tick(session: &mut Lockstep, spawn_request: &mut u8, cursor: Point2<f32>) -> std::io::Result<Option<[PlayerInput; 2]>> {
+    if session.wants_input() {
+        let inside = (0.0..WORLD_WIDTH).contains(&cursor.x) && (0.0..WORLD_HEIGHT).contains(&cursor.y);
+        session.send(PlayerInput {
+            cursor: inside.then(|| glam::vec2(cursor.x, cursor.y)),
+            spawn: std::mem::take(spawn_request),
+        })?;
+    }
+    session.next_tick()
+}
+
+/// Step one lockstep tick from both players' inputs, identically on every peer
+fn apply_inputs(sim: &mut Simulation, mut compare: Option<&mut Simulation>, inputs: &[PlayerInput; 2]) {
+    for input in inputs {
+        hatch(sim, compare.as_deref_mut(), input.spawn);
+    }
+    // the host's cursor wins while both are over the world, else whichever is
+    let cursor = inputs
+        .iter()
+        .find_map(|input| input.cursor)
+        .map_or(simulation::NO_CURSOR, |c| Point2 { x: c.x, y: c.y });
+    step_sims(sim, compare, std::time::Duration::from_secs_f64(LOCKSTEP_DT), cursor);
+}
+
+/// Arguments following the flag at `i`, up to the next flag
+fn flag_values(args: &[String], i: usize) -> &[String] {
+    let rest = &args[i + 1..];
+    let end = rest.iter().position(|a| a.starts_with("--")).unwrap_or(rest.len());
+    &rest[..end]
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // --locale xx, else $LANG; English when neither has a table
+    match args.iter().position(|a| a == "--locale") {
+        Some(i) => {
+            let result = args
+                .get(i + 1)
+                .ok_or_else(|| "expected a language code".to_string())
+                .and_then(|lang| locale::init(lang));
+            if let Err(e) = result {
+                eprintln!("--locale: {}", e);
+            }
+        }
+        // most languages have no table yet, so a missing one isn't worth a warning
+        None => {
+            if let Some(lang) = locale::system_language() {
+                let _ = locale::init(&lang);
+            }
+        }
+    }
+    if args.get(1).map(String::as_str) == Some("diff") {
+        let code = match (args.get(2), args.get(3)) {
+            (Some(a), Some(b)) => match diff::run(a, b) {
+                Ok(true) => 0,
+                Ok(false) => 1,
+                Err(e) => {
+                    eprintln!("diff: {}", e);
+                    2
+                }
+            },
+            _ => {
+                eprintln!("usage: diff <a> <b>");
+                2
+            }
+        };
+        std::process::exit(code);
+    }
+    if let Some(i) = args.iter().position(|a| a == "--batch") {
+        match BatchConfig::parse(flag_values(&args, i)) {
+            Ok(config) => batch::run(&config),
+            Err(e) => {
+                eprintln!("--batch: {}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+    if let Some(i) = args.iter().position(|a| a == "--sweep") {
+        let result = args
+            .get(i + 1)
+            .ok_or_else(|| "expected a sweep file".to_string())
+            .and_then(|path| SweepConfig::load(path))
+            .and_then(|config| sweep::run(&config).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("--sweep: {}", e);
+            std::process::exit(2);
+        }
+        return;
+    }
+    if let Some(i) = args.iter().position(|a| a == "--transcript") {
+        let result = TranscriptConfig::parse(flag_values(&args, i))
+            .and_then(|config| transcript::run(&config).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("--transcript: {}", e);
+            std::process::exit(2);
+        }
+        return;
+    }
+    #[cfg(feature = "bevy")]
+    if let Some(i) = args.iter().position(|a| a == "--bevy") {
+        match bevy_plugin::BevyConfig::parse(flag_values(&args, i)) {
+            Ok(config) => bevy_plugin::run(&config),
+            Err(e) => {
+                eprintln!("--bevy: {}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+    #[cfg(feature = "gpu")]
+    if let Some(i) = args.iter().position(|a| a == "--gpu") {
+        let result = gpu::GpuConfig::parse(flag_values(&args, i)).and_then(|config| gpu::run(&config));
+        if let Err(e) = result {
+            eprintln!("--gpu: {}", e);
+            std::process::exit(2);
+        }
+        return;
+    }
+    if let Some(i) = args.iter().position(|a| a == "--tui") {
+        let result = TuiConfig::parse(flag_values(&args, i))
+            .and_then(|config| tui::run(&config).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("--tui: {}", e);
+            std::process::exit(2);
+        }
+        return;
+    }
+
+    if args.iter().any(|a| a == "--dump-bt") {
+        dump_trees();
+    }
+    // --export-dot FILE writes the trees without statuses, for docs
+    if let Some(i) = args.iter().position(|a| a == "--export-dot") {
+        let result = args
+            .get(i + 1)
+            .ok_or_else(|| "expected an output file".to_string())
+            .and_then(|path| std::fs::write(path, trees_dot(&HashMap::new(), &HashMap::new())).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("--export-dot: {}", e);
+            std::process::exit(2);
+        }
+        return;
+    }
+
+    // --no-vsync lets frames run as fast as the cap, or the machine, allows. It
+    // can't be a key: ggez 0.7 fixes vsync when it builds the GL context.
+    let vsync = !args.iter().any(|a| a == "--no-vsync");
+    // --pixels-per-unit F draws the world bigger or smaller without changing it
+    let pixels_per_unit = match args.iter().position(|a| a == "--pixels-per-unit") {
+        Some(i) => match args.get(i + 1).and_then(|f| f.parse::<f32>().ok()) {
+            Some(ppu) if ppu > 0.0 && ppu.is_finite() => ppu,
+            _ => {
+                eprintln!("--pixels-per-unit: expected a positive number");
+                std::process::exit(2);
+            }
+        },
+        None => PIXELS_PER_UNIT,
+    };
+    let playfield = glam::vec2(WORLD_WIDTH, WORLD_HEIGHT) * pixels_per_unit;
     let (mut ctx, events_loop) = ContextBuilder::new("Boids", "Daniel Eisen")
-        .window_mode(conf::WindowMode::default().dimensions(WINDOW_WIDTH, WINDOW_HEIGHT))
-        .window_setup(conf::WindowSetup::default().samples(conf::NumSamples::Eight))
+        .window_mode(conf::WindowMode::default().dimensions(playfield.x, playfield.y).resizable(true))
+        .window_setup(conf::WindowSetup::default().samples(conf::NumSamples::Eight).vsync(vsync))
         .build()
         .expect("Failed to create context");
 
     let boid_bt = Boid::create_bt();
-    let mut blackboard: HashMap<String, f32> = HashMap::new();
-    blackboard.insert("win_width".to_string(), WINDOW_WIDTH);
-    blackboard.insert("win_height".to_string(), WINDOW_HEIGHT);
-    let boid_bt: BT<BoidAction, String, f32> = BT::new(boid_bt, blackboard);
+    let boid_bt: BoidBT = BT::new(boid_bt, Boid::create_blackboard());
+    // --compare key=value.. runs defaults (A) beside the overridden set (B)
+    let compare_overrides = match args.iter().position(|a| a == "--compare") {
+        Some(i) => match simulation::parse_overrides(flag_values(&args, i)) {
+            Ok(overrides) => Some(overrides),
+            Err(e) => {
+                eprintln!("--compare: {}", e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let seed = rand::random();
+    let (sim, compare, compare_labels) = match compare_overrides {
+        Some(overrides) => {
+            let width = WORLD_WIDTH / 2.0;
+            let world = simulation::create_world_blackboard(width, WORLD_HEIGHT);
+            let sim = Simulation::new(boid_bt.clone(), world.clone(), width, WORLD_HEIGHT, seed);
+            let mut world_b = world;
+            let mut label_b = vec![];
+            for (key, value) in &overrides {
+                if !matches!(world_b.get(key), Some(BBValue::F32(_))) {
+                    eprintln!("--compare: unknown numeric parameter '{}'", key);
+                    std::process::exit(2);
+                }
+                world_b.insert(key.clone(), BBValue::F32(*value));
+                label_b.push(format!("{}={}", key, value));
+            }
+            let b = Simulation::new(boid_bt, world_b, width, WORLD_HEIGHT, seed);
+            (sim, Some(b), [tr("A: defaults").to_string(), fill(tr("B: {}"), &[label_b.join(" ")])])
+        }
+        None => {
+            let world = simulation::create_world_blackboard(WORLD_WIDTH, WORLD_HEIGHT);
+            let sim = Simulation::new(boid_bt, world, WORLD_WIDTH, WORLD_HEIGHT, seed);
+            (sim, None, Default::default())
+        }
+    };
 
-    let game_state =
-        GameWorld::new(&mut ctx, boid_bt);
+    let mut game_state =
+        GameWorld::new(&mut ctx, sim, compare, compare_labels);
+    game_state.pipelined = args.iter().any(|a| a == "--pipelined");
+    game_state.show_glow = args.iter().any(|a| a == "--glow");
+    game_state.pause_on_focus_loss = !args.iter().any(|a| a == "--no-focus-pause");
+    // --scale F overrides the display's own scale factor
+    game_state.scale = match args.iter().position(|a| a == "--scale") {
+        Some(i) => match args.get(i + 1).and_then(|f| f.parse().ok()) {
+            Some(scale) if scale > 0.0 => scale,
+            _ => {
+                eprintln!("--scale: expected a positive factor");
+                std::process::exit(2);
+            }
+        },
+        None => graphics::window(&ctx).scale_factor() as f32,
+    };
+    if let Err(e) = game_state.set_pixels_per_unit(&mut ctx, pixels_per_unit) {
+        eprintln!("--scale: {}", e);
+    }
+    // the window comes back as the last run left it, unless --scale or
+    // --pixels-per-unit asks for a size
+    match window_state::load() {
+        Ok(Some(state)) if !args.iter().any(|a| a == "--scale" || a == "--pixels-per-unit") => {
+            game_state.windowed = Some(state);
+            if let Err(e) = state.apply(&mut ctx) {
+                eprintln!("window: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("window: {}", e),
+    }
+    match theme::load() {
+        Ok(themes) => game_state.themes = themes,
+        Err(e) => eprintln!("themes: {}", e),
+    }
+    // --theme NAME picks the starting theme, T cycles through them. The first
+    // is applied too when none is given, as themes.json may have changed it.
+    let start_theme = match args.iter().position(|a| a == "--theme") {
+        Some(i) => match args.get(i + 1).and_then(|name| game_state.themes.iter().position(|t| &t.name == name)) {
+            Some(index) => index,
+            None => {
+                let names: Vec<&str> = game_state.themes.iter().map(|t| t.name.as_str()).collect();
+                eprintln!("--theme: expected one of {}", names.join(", "));
+                std::process::exit(2);
+            }
+        },
+        None => 0,
+    };
+    game_state.set_theme(start_theme);
+    if let Some(i) = args.iter().position(|a| a == "--capacity") {
+        match args.get(i + 1).and_then(|n| n.parse().ok()) {
+            Some(capacity) => {
+                game_state.sim.capacity = capacity;
+                if let Some(b) = &mut game_state.compare {
+                    b.capacity = capacity;
+                }
+            }
+            None => {
+                eprintln!("--capacity: expected a boid count");
+                std::process::exit(2);
+            }
+        }
+    }
+    if let Some(i) = args.iter().position(|a| a == "--integrator") {
+        match args.get(i + 1).map(|name| Integrator::parse(name)) {
+            Some(Ok(integrator)) => {
+                game_state.sim.integrator = integrator;
+                if let Some(b) = &mut game_state.compare {
+                    b.integrator = integrator;
+                }
+            }
+            Some(Err(e)) => {
+                eprintln!("--integrator: {}", e);
+                std::process::exit(2);
+            }
+            None => {
+                eprintln!("--integrator: expected euler, semi-implicit or verlet");
+                std::process::exit(2);
+            }
+        }
+    }
+    match presets::load() {
+        Ok(presets) => game_state.presets = presets,
+        Err(e) => eprintln!("presets: {}", e),
+    }
+    // --attract SECONDS sets how long the setup screen waits before attract mode, 0 for never
+    if let Some(i) = args.iter().position(|a| a == "--attract") {
+        match args.get(i + 1).and_then(|s| s.parse::<f32>().ok()) {
+            Some(seconds) if seconds >= 0.0 => game_state.attract_after = seconds,
+            _ => {
+                eprintln!("--attract: expected a number of seconds");
+                std::process::exit(2);
+            }
+        }
+    }
+    // --preset NAME starts from a named parameter set, F6 on the setup screen cycles through them
+    if let Some(i) = args.iter().position(|a| a == "--preset") {
+        match args.get(i + 1).and_then(|name| game_state.presets.iter().position(|p| &p.name == name)) {
+            Some(index) => game_state.set_preset(Some(index)),
+            None => {
+                let names: Vec<&str> = game_state.presets.iter().map(|p| p.name.as_str()).collect();
+                eprintln!("--preset: expected one of {}", names.join(", "));
+                std::process::exit(2);
+            }
+        }
+    }
+    // --scenario corridor|maze|ants|FILE sets up walls, lanes, a nest and parameter overrides
+    if let Some(i) = args.iter().position(|a| a == "--scenario") {
+        let Some(name) = args.get(i + 1) else {
+            eprintln!("--scenario: expected corridor, maze, ants or a scenario file");
+            std::process::exit(2);
+        };
+        let sims = std::iter::once(&mut game_state.sim).chain(game_state.compare.as_mut());
+        for sim in sims {
+            match Scenario::load(name, sim.width, sim.height) {
+                Ok(scenario) => sim.set_scenario(scenario),
+                Err(e) => {
+                    eprintln!("--scenario: {}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+    }
+    let config_hash = replay::config_hash(&game_state.sim.world_bb.read().unwrap());
+    let session = if let Some(i) = args.iter().position(|a| a == "--host") {
+        match args.get(i + 1).and_then(|port| port.parse().ok()) {
+            Some(port) => {
+                eprintln!("waiting for a peer on port {}", port);
+                Some(Lockstep::host(port, rand::random(), config_hash))
+            }
+            None => {
+                eprintln!("--host: expected a port");
+                std::process::exit(2);
+            }
+        }
+    } else if let Some(i) = args.iter().position(|a| a == "--join") {
+        match args.get(i + 1) {
+            Some(addr) => Some(Lockstep::join(addr.as_str(), config_hash)),
+            None => {
+                eprintln!("--join: expected host:port");
+                std::process::exit(2);
+            }
+        }
+    } else {
+        None
+    };
+    match session {
+        Some(Ok(session)) => game_state.lockstep = Some(session),
+        Some(Err(e)) => {
+            eprintln!("lockstep: {}", e);
+            std::process::exit(2);
+        }
+        None => {}
+    }
+    #[cfg(feature = "irc")]
+    if let Some(i) = args.iter().position(|a| a == "--irc") {
+        let (server, channel, nick) = match flag_values(&args, i) {
+            [server, channel] => (server, channel, format!("justinfan{}", rand::random::<u16>())),
+            [server, channel, nick] => (server, channel, nick.clone()),
+            _ => {
+                eprintln!("--irc: expected <host:port> <#channel> [nick]");
+                std::process::exit(2);
+            }
+        };
+        match chat::connect(server, channel, &nick) {
+            Ok(commands) => game_state.chat = Some(commands),
+            Err(e) => {
+                eprintln!("--irc: {}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+    // --bench-scene times a 10k boid flock for 30 seconds, then reports and quits
+    if args.iter().any(|a| a == "--bench-scene") {
+        game_state.start_bench();
+    }
+    // --render-scale F draws the world at F of the window's resolution, F12 toggles it
+    if let Some(i) = args.iter().position(|a| a == "--render-scale") {
+        match args.get(i + 1).and_then(|f| f.parse::<f32>().ok()) {
+            Some(scale) if (downscale::MIN_SCALE..=1.0).contains(&scale) => {
+                game_state.downscale.scale = scale;
+                game_state.downscale.enabled = true;
+            }
+            _ => {
+                eprintln!("--render-scale: expected a share of the resolution from {} to 1", downscale::MIN_SCALE);
+                std::process::exit(2);
+            }
+        }
+    }
+    // --fps-cap N holds the frame rate to N, F11 steps through other caps
+    if let Some(i) = args.iter().position(|a| a == "--fps-cap") {
+        match args.get(i + 1).and_then(|n| n.parse::<u32>().ok()) {
+            Some(fps) if fps > 0 => game_state.frame_cap.fps = Some(fps),
+            _ => {
+                eprintln!("--fps-cap: expected a number of frames per second");
+                std::process::exit(2);
+            }
+        }
+    }
+    // --memory-budget MB warns once the subsystems (or the heap, with alloc-stats) hold more
+    if let Some(i) = args.iter().position(|a| a == "--memory-budget") {
+        match args.get(i + 1).and_then(|mb| mb.parse::<f32>().ok()) {
+            Some(mb) if mb > 0.0 => game_state.memory_budget = Some((mb * (1 << 20) as f32) as usize),
+            _ => {
+                eprintln!("--memory-budget: expected a size in MiB");
+                std::process::exit(2);
+            }
+        }
+    }
+    // --soak [MS] grows the flock until frames take over MS (30 fps by default), then reports the largest that kept up
+    if let Some(i) = args.iter().position(|a| a == "--soak") {
+        match flag_values(&args, i) {
+            [] => game_state.start_soak(bench::SOAK_FRAME_MS),
+            [ms] => match ms.parse::<f32>() {
+                Ok(ms) if ms > 0.0 => game_state.start_soak(ms),
+                _ => {
+                    eprintln!("--soak: expected a frame time in milliseconds");
+                    std::process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("--soak: expected at most a frame time in milliseconds");
+                std::process::exit(2);
+            }
+        }
+    }
+    if let Some(i) = args.iter().position(|a| a == "--replay") {
+        let loaded = args
+            .get(i + 1)
+            .ok_or_else(|| "expected a replay file".to_string())
+            .and_then(|path| File::open(path).map_err(|e| e.to_string()))
+            .and_then(|file| replay::read(file).map_err(|e| e.to_string()));
+        match loaded {
+            Ok((header, frames)) => {
+                eprintln!("replay v{}.{}: {} boids, {} frames, seed {}",
+                          header.version.0, header.version.1, header.boid_count, frames.len(), header.seed);
+                game_state.playback = Some((frames, 0));
+            }
+            Err(e) => {
+                eprintln!("--replay: {}", e);
+                std::process::exit(2);
+            }
+        }
+    }
     event::run(ctx, events_loop, game_state);
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::NullRenderer;
+
+    fn peer(seed: u64) -> Simulation {
+        let boid_bt: BoidBT = BT::new(Boid::create_bt(), Boid::create_blackboard());
+        let world = simulation::create_world_blackboard(WORLD_WIDTH, WORLD_HEIGHT);
+        let mut sim = Simulation::new(boid_bt, world, WORLD_WIDTH, WORLD_HEIGHT, seed);
+        sim.spawn(OBJECT_COUNT);
+        sim
+    }
+
+    #[test]
+    fn apply_inputs_is_the_same_on_both_peers() {
+        let (mut host, mut client) = (peer(7), peer(7));
+        let ticks = [
+            [PlayerInput { cursor: Some(glam::vec2(100.0, 200.0)), spawn: 0 }, PlayerInput { cursor: None, spawn: 2 }],
+            [PlayerInput { cursor: None, spawn: 0 }, PlayerInput { cursor: Some(glam::vec2(600.0, 300.0)), spawn: 0 }],
+            [PlayerInput::default(), PlayerInput::default()],
+        ];
+        for _ in 0..5 {
+            for inputs in &ticks {
+                apply_inputs(&mut host, None, inputs);
+                apply_inputs(&mut client, None, inputs);
+            }
+        }
+        assert_eq!(host.boids.len(), OBJECT_COUNT + 10);
+        assert_eq!(BoidState::capture(&host.boids), BoidState::capture(&client.boids));
+    }
+
+    #[test]
+    fn hud_draws_without_a_window() {
+        let hud = Hud {
+            lines: vec!["caught: 3".to_string()],
+            panels: vec![(vec!["boid #1".to_string()], glam::vec2(16.0, 16.0))],
+            banner: Some(("PAUSED".to_string(), 48.0)),
+        };
+        let mut renderer = NullRenderer;
+        renderer.begin_frame().unwrap();
+        hud.draw(&mut renderer).unwrap();
+        renderer.end_frame().unwrap();
+    }
+}
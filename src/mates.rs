@@ -0,0 +1,71 @@
+//! Pair bonds: with mates on, boids pair off with a nearby single boid and
+//! each pair is held together by a soft spring, so partners stay close while
+//! the flock moves around them
+use std::collections::HashMap;
+
+use crate::boid::Boid;
+use crate::real::real;
+
+/// Free boids whose mate is gone, then pair each single boid with the nearest
+/// other single one within `range`. Boids with no single boid in range wait.
+pub fn pair(boids: &mut [Boid], range: f32) {
+    let alive: HashMap<usize, usize> = boids.iter().enumerate().map(|(i, b)| (b.id, i)).collect();
+    for boid in boids.iter_mut() {
+        if boid.mate.is_some_and(|mate| !alive.contains_key(&mate)) {
+            boid.mate = None;
+        }
+    }
+    let mut single: Vec<usize> = (0..boids.len()).filter(|&i| boids[i].mate.is_none()).collect();
+    while let Some(i) = single.pop() {
+        let pos = boids[i].pos();
+        let nearest = single
+            .iter()
+            .enumerate()
+            .map(|(k, &j)| (k, boids[j].pos().distance(pos)))
+            .filter(|&(_, d)| d < range)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((k, _)) = nearest {
+            let j = single.swap_remove(k);
+            boids[i].mate = Some(boids[j].id);
+            boids[j].mate = Some(boids[i].id);
+        }
+    }
+}
+
+/// Pairs of indices into `boids` of every mated pair, each pair once
+pub fn pairs(boids: &[Boid]) -> Vec<(usize, usize)> {
+    let index: HashMap<usize, usize> = boids.iter().enumerate().map(|(i, b)| (b.id, i)).collect();
+    boids
+        .iter()
+        .enumerate()
+        .filter_map(|(i, b)| b.mate.filter(|&mate| b.id < mate).and_then(|mate| index.get(&mate)).map(|&j| (i, j)))
+        .collect()
+}
+
+/// Spring every pair towards `length` apart for `dt` seconds. Each unit of
+/// stretch pulls with `stiffness` per second squared, or pushes when the pair
+/// is squeezed, and `damping` per second takes out the speed they part or
+/// close at. Frozen boids stay put; their mates take the whole pull.
+pub fn pull(boids: &mut [Boid], dt: f32, length: f32, stiffness: f32, damping: f32) {
+    for (i, j) in pairs(boids) {
+        let offset = boids[j].pos() - boids[i].pos();
+        let distance = offset.length();
+        if distance == 0.0 {
+            continue;
+        }
+        let along = offset / distance;
+        let parting = (boids[j].vel() - boids[i].vel()).dot(along);
+        let accel = stiffness * (distance - length) + damping * parting;
+        let (a, b) = (boids[i].frozen, boids[j].frozen);
+        let share = if a || b { 1.0 } else { 0.5 };
+        let dv = along * accel * share * dt;
+        if !a {
+            boids[i].dx += real(dv.x);
+            boids[i].dy += real(dv.y);
+        }
+        if !b {
+            boids[j].dx -= real(dv.x);
+            boids[j].dy -= real(dv.y);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Memory accounting: what each subsystem holds, estimated from the capacity
+//! of its buffers, checked against an optional budget. With the alloc-stats
+//! feature a counting allocator adds the true heap total.
+use crate::locale::{fill, tr};
+
+#[cfg(feature = "alloc-stats")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+    /// The system allocator, keeping count of the bytes it has out
+    pub struct Counting;
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+            }
+            ptr
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: Counting = Counting;
+}
+
+/// Heap bytes allocated right now, when the counting allocator is in
+pub fn allocated() -> Option<usize> {
+    #[cfg(feature = "alloc-stats")]
+    return Some(counting::ALLOCATED.load(std::sync::atomic::Ordering::Relaxed));
+    #[cfg(not(feature = "alloc-stats"))]
+    None
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MiB", b as f32 / (1 << 20) as f32),
+        b if b >= 1 << 10 => format!("{:.1} KiB", b as f32 / (1 << 10) as f32),
+        b => format!("{} B", b),
+    }
+}
+
+/// Bytes held per subsystem
+pub struct Usage {
+    pub subsystems: Vec<(&'static str, usize)>,
+}
+
+impl Usage {
+    /// What the subsystems hold between them, or the heap total if it is counted
+    pub fn total(&self) -> usize {
+        allocated().unwrap_or_else(|| self.subsystems.iter().map(|&(_, bytes)| bytes).sum())
+    }
+    /// HUD lines, one per subsystem and then the total against `budget`
+    pub fn lines(&self, budget: Option<usize>) -> Vec<String> {
+        let mut lines = vec![tr("memory:").to_string()];
+        for &(name, bytes) in &self.subsystems {
+            lines.push(format!("  {} {}", tr(name), format_bytes(bytes)));
+        }
+        let total = match allocated() {
+            Some(heap) => fill(tr("  heap {}"), &[format_bytes(heap)]),
+            None => fill(tr("  total ~{}"), &[format_bytes(self.total())]),
+        };
+        lines.push(match budget {
+            Some(budget) => format!("{} / {}", total, format_bytes(budget)),
+            None => total,
+        });
+        lines
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+
+use crate::boid::Boid;
+use crate::epidemic;
+
+/// Flock-level measurements taken once per tick
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Metrics {
+    /// Length of the mean heading unit vector, 0 (disordered) to 1 (aligned)
+    pub polarization: f32,
+    pub mean_speed: f32,
+    /// Groups of boids connected within the cluster distance
+    pub clusters: usize,
+    /// Normalized angular momentum about the centroid, 0 (none) to 1 (all circling the same way)
+    pub milling: f32,
+    pub spacing: Spacing,
+    /// Susceptible, infected and recovered boids
+    pub health: [usize; 3],
+}
+
+/// Distribution of the distance from each boid to its nearest neighbor, all
+/// zero with fewer than two boids
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Spacing {
+    pub min: f32,
+    pub mean: f32,
+    pub median: f32,
+    /// 90th percentile
+    pub p90: f32,
+}
+
+impl Spacing {
+    pub fn measure(boids: &[Boid]) -> Spacing {
+        let mut distances = nearest_neighbor_distances(boids);
+        if distances.is_empty() {
+            return Spacing::default();
+        }
+        distances.sort_by(f32::total_cmp);
+        // nearest rank
+        let percentile = |p: f32| distances[((p * distances.len() as f32).ceil() as usize).clamp(1, distances.len()) - 1];
+        Spacing {
+            min: distances[0],
+            mean: distances.iter().sum::<f32>() / distances.len() as f32,
+            median: percentile(0.5),
+            p90: percentile(0.9),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn measure(boids: &[Boid], cluster_distance: f32) -> Metrics {
+        Metrics {
+            polarization: polarization(boids),
+            mean_speed: mean_speed(boids),
+            clusters: cluster_count(boids, cluster_distance),
+            milling: milling(boids),
+            spacing: Spacing::measure(boids),
+            health: epidemic::counts(boids),
+        }
+    }
+    /// Circling the centroid together rather than flying off in one direction
+    pub fn is_milling(&self) -> bool {
+        self.milling > 0.5 && self.polarization < 0.35
+    }
+}
+
+pub fn speed(boid: &Boid) -> f32 {
+    boid.vel().length()
+}
+
+pub fn polarization(boids: &[Boid]) -> f32 {
+    if boids.is_empty() {
+        return 0.0;
+    }
+    let sum = boids
+        .iter()
+        .map(|b| b.vel().normalize_or_zero())
+        .fold(glam::Vec2::ZERO, |acc, v| acc + v);
+    sum.length() / boids.len() as f32
+}
+
+pub fn milling(boids: &[Boid]) -> f32 {
+    if boids.is_empty() {
+        return 0.0;
+    }
+    let centroid = boids.iter().fold(glam::Vec2::ZERO, |acc, b| acc + b.pos()) / boids.len() as f32;
+    let sum: f32 = boids
+        .iter()
+        .map(|b| (b.pos() - centroid).normalize_or_zero().perp_dot(b.vel().normalize_or_zero()))
+        .sum();
+    sum.abs() / boids.len() as f32
+}
+
+pub fn mean_speed(boids: &[Boid]) -> f32 {
+    if boids.is_empty() {
+        return 0.0;
+    }
+    boids.iter().map(speed).sum::<f32>() / boids.len() as f32
+}
+
+/// Distance from each boid to the closest other one; empty with fewer than two boids
+pub fn nearest_neighbor_distances(boids: &[Boid]) -> Vec<f32> {
+    if boids.len() < 2 {
+        return vec![];
+    }
+    boids
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            boids
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, b)| a.pos().distance(b.pos()))
+                .fold(f32::INFINITY, f32::min)
+        })
+        .collect()
+}
+
+/// Number of connected components where boids closer than `distance` are linked
+pub fn cluster_count(boids: &[Boid], distance: f32) -> usize {
+    let mut visited = vec![false; boids.len()];
+    let mut clusters = 0;
+    let mut stack = vec![];
+    for start in 0..boids.len() {
+        if visited[start] {
+            continue;
+        }
+        clusters += 1;
+        visited[start] = true;
+        stack.push(start);
+        while let Some(i) = stack.pop() {
+            for j in 0..boids.len() {
+                if !visited[j] && boids[i].pos().distance(boids[j].pos()) < distance {
+                    visited[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+    }
+    clusters
+}
+
+/// Rolling window of timestamped samples
+pub struct MetricHistory {
+    pub window: f32,
+    pub samples: VecDeque<(f32, Metrics)>,
+    time: f32,
+}
+
+impl MetricHistory {
+    pub fn new(window: f32) -> MetricHistory {
+        MetricHistory {
+            window,
+            samples: VecDeque::new(),
+            time: 0.0,
+        }
+    }
+    pub fn push(&mut self, dt: f32, metrics: Metrics) {
+        self.time += dt;
+        self.samples.push_back((self.time, metrics));
+        while let Some(&(t, _)) = self.samples.front() {
+            if self.time - t > self.window {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.time = 0.0;
+    }
+    pub fn now(&self) -> f32 {
+        self.time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bonsai_bt::BT;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::boid::BoidBT;
+    use crate::real::real;
+    use crate::simulation;
+    use crate::theme::Palette;
+
+    /// Boids at `pos`, flying at `vel`
+    fn boids(states: &[(glam::Vec2, glam::Vec2)]) -> Vec<Boid> {
+        let bt: BoidBT = BT::new(Boid::create_bt(), Boid::create_blackboard());
+        let palette = Palette::from_world(&simulation::create_world_blackboard(400.0, 300.0));
+        let mut rng = StdRng::seed_from_u64(1);
+        states
+            .iter()
+            .enumerate()
+            .map(|(id, &(pos, vel))| {
+                let mut boid = Boid::new(id, 400.0, 300.0, bt.clone(), &palette, &mut rng);
+                (boid.x, boid.y) = (real(pos.x), real(pos.y));
+                (boid.dx, boid.dy) = (real(vel.x), real(vel.y));
+                boid
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spacing_takes_nearest_rank_percentiles() {
+        // gaps of 1, 2, ... 9 along a line, so the nearest neighbor distances
+        // sort to 1, 1, 2, 3, 4, 5, 6, 7, 8, 9
+        let line: Vec<_> = [0.0, 1.0, 3.0, 6.0, 10.0, 15.0, 21.0, 28.0, 36.0, 45.0]
+            .iter()
+            .map(|&x| (glam::vec2(x, 0.0), glam::Vec2::X))
+            .collect();
+        let flock = boids(&line);
+        assert_eq!(nearest_neighbor_distances(&flock), [1.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        let spacing = Spacing::measure(&flock);
+        assert_eq!(spacing.min, 1.0);
+        assert!((spacing.mean - 4.6).abs() < 1e-5, "mean {}", spacing.mean);
+        // ranks ceil(0.5 * 10) = 5 and ceil(0.9 * 10) = 9
+        assert_eq!(spacing.median, 4.0);
+        assert_eq!(spacing.p90, 8.0);
+    }
+
+    #[test]
+    fn spacing_is_zero_with_fewer_than_two_boids() {
+        for count in 0..2 {
+            let flock = boids(&vec![(glam::vec2(10.0, 10.0), glam::Vec2::X); count]);
+            assert!(nearest_neighbor_distances(&flock).is_empty());
+            let spacing = Spacing::measure(&flock);
+            assert_eq!([spacing.min, spacing.mean, spacing.median, spacing.p90], [0.0; 4]);
+        }
+    }
+
+    #[test]
+    fn aligned_boids_are_fully_polarized() {
+        // same heading, different speeds and places
+        let flock = boids(&[
+            (glam::vec2(10.0, 20.0), glam::vec2(3.0, 4.0)),
+            (glam::vec2(200.0, 50.0), glam::vec2(6.0, 8.0)),
+            (glam::vec2(90.0, 250.0), glam::vec2(0.3, 0.4)),
+        ]);
+        assert!((polarization(&flock) - 1.0).abs() < 1e-6);
+        assert!(milling(&flock) < 1.0);
+    }
+
+    #[test]
+    fn a_circling_ring_is_fully_milling() {
+        let center = glam::vec2(200.0, 150.0);
+        let ring: Vec<_> = (0..12)
+            .map(|i| {
+                let angle = i as f32 / 12.0 * std::f32::consts::TAU;
+                let out = glam::vec2(angle.cos(), angle.sin());
+                (center + out * 100.0, out.perp() * 50.0)
+            })
+            .collect();
+        let flock = boids(&ring);
+        assert!((milling(&flock) - 1.0).abs() < 1e-5, "milling {}", milling(&flock));
+        // headings all round the circle cancel out
+        assert!(polarization(&flock) < 1e-5);
+    }
+}
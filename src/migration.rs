@@ -0,0 +1,52 @@
+//! Migration mode: a target crosses the world slowly and draws the flock along
+use crate::boid::Boid;
+
+/// Strip along the far edge, as a fraction of the width, the flock has to reach
+const FAR_EDGE: f32 = 0.1;
+
+#[derive(Clone, Debug)]
+pub struct Migration {
+    pub target: glam::Vec2,
+    /// 1 while crossing to the right, -1 on the way back
+    pub heading: f32,
+    /// Crossings completed
+    pub crossings: usize,
+    /// Set once the flock has crossed and the run doesn't loop
+    pub finished: bool,
+}
+
+impl Migration {
+    /// Set off rightwards from the middle of `boids`
+    pub fn start(boids: &[Boid], height: f32) -> Migration {
+        let x = boids.iter().map(|b| b.pos().x).sum::<f32>() / boids.len().max(1) as f32;
+        Migration { target: glam::vec2(x, height / 2.0), heading: 1.0, crossings: 0, finished: false }
+    }
+    /// Fraction of `boids` inside the strip along the edge being crossed to
+    pub fn arrived(&self, boids: &[Boid], width: f32) -> f32 {
+        let reached = boids
+            .iter()
+            .filter(|b| {
+                let x = b.pos().x / width;
+                if self.heading > 0.0 { x > 1.0 - FAR_EDGE } else { x < FAR_EDGE }
+            })
+            .count();
+        reached as f32 / boids.len().max(1) as f32
+    }
+    /// Move the target on by `speed` world units per second, stopping at the edge.
+    /// Once `goal` of the flock has reached the far edge the crossing counts;
+    /// the migration then turns back if `looping`, and otherwise finishes.
+    pub fn advance(&mut self, dt: f32, boids: &[Boid], width: f32, speed: f32, goal: f32, looping: bool) {
+        if self.finished {
+            return;
+        }
+        self.target.x = (self.target.x + self.heading * speed * dt).clamp(0.0, width);
+        if self.arrived(boids, width) >= goal {
+            self.crossings += 1;
+            if looping {
+                self.heading = -self.heading;
+            } else {
+                self.finished = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Long motion trails from an offscreen canvas that keeps every frame's
+//! picture: each frame it is dimmed by a fade factor, the boids are stamped
+//! on top, and the result is drawn under the live boids. One full-screen
+//! pass costs the same however many boids leave trails, or however long.
+use ggez::{Context, GameResult, graphics};
+use ggez::graphics::Drawable;
+
+use crate::simulation::Simulation;
+
+/// Radius of the dot each boid leaves behind, in world units
+const DOT_RADIUS: f32 = 1.5;
+
+#[derive(Default)]
+pub struct MotionTrails {
+    /// Created on first use and again whenever the window changes size
+    canvas: Option<graphics::Canvas>,
+}
+
+impl MotionTrails {
+    /// Forget the trails drawn so far
+    pub fn clear(&mut self) {
+        self.canvas = None;
+    }
+    /// Multiply what the canvas holds by `fade` and stamp the boids of each
+    /// simulation, shifted by its offset, on top as seen through `view`
+    pub fn update<'s>(&mut self, ctx: &mut Context, sims: impl Iterator<Item = (&'s Simulation, glam::Vec2)>,
+                      view: glam::Mat4, fade: f32) -> GameResult {
+        let (width, height) = graphics::drawable_size(ctx);
+        let stale = self.canvas.as_ref().is_none_or(|c| {
+            (c.width(), c.height()) != (width as u16, height as u16)
+        });
+        if stale {
+            let canvas = graphics::Canvas::with_window_size(ctx)?;
+            graphics::set_canvas(ctx, Some(&canvas));
+            graphics::clear(ctx, graphics::Color::new(0.0, 0.0, 0.0, 0.0));
+            graphics::set_canvas(ctx, None);
+            self.canvas = Some(canvas);
+        }
+        let canvas = self.canvas.as_ref().unwrap();
+        graphics::set_canvas(ctx, Some(canvas));
+
+        let screen = graphics::screen_coordinates(ctx);
+        let mut dim = graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), screen, [fade; 4].into())?;
+        dim.set_blend_mode(Some(graphics::BlendMode::Multiply));
+        graphics::draw(ctx, &dim, graphics::DrawParam::new())?;
+
+        let mut mb = graphics::MeshBuilder::new();
+        let mut any = false;
+        for (sim, offset) in sims {
+            for boid in &sim.boids {
+                mb.circle(graphics::DrawMode::fill(), boid.pos() + offset, DOT_RADIUS, 0.5, boid.color.into())?;
+                any = true;
+            }
+        }
+        if any {
+            let dots = mb.build(ctx)?;
+            graphics::draw(ctx, &dots, graphics::DrawParam::new().transform(view))?;
+        }
+        graphics::set_canvas(ctx, None);
+        Ok(())
+    }
+    /// Draw the trails over the whole screen
+    pub fn draw(&self, ctx: &mut Context) -> GameResult {
+        let Some(canvas) = &self.canvas else { return Ok(()) };
+        let screen = graphics::screen_coordinates(ctx);
+        let scale = glam::vec2(screen.w / canvas.width() as f32, screen.h / canvas.height() as f32);
+        graphics::draw(ctx, canvas, graphics::DrawParam::default().dest(glam::vec2(screen.x, screen.y)).scale(scale))
+    }
+}
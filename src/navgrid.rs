@@ -0,0 +1,307 @@
+//! Coarse occupancy grid over a scenario's obstacles, with an A* planner that
+//! routes boids around walls they would otherwise get stuck behind, and flow
+//! fields that steer any number of boids to one goal for the cost of a single search
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::scenario::Obstacle;
+
+/// Neighbor offsets, straight ones first
+const NEIGHBORS: [(isize, isize); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+#[derive(Clone, Debug, Default)]
+pub struct NavGrid {
+    pub cell_size: f32,
+    pub cols: usize,
+    pub rows: usize,
+    /// Cells whose center is within clearance of an obstacle, row by row
+    pub blocked: Vec<bool>,
+}
+
+/// Path length to a goal from every cell of a grid
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    pub goal: glam::Vec2,
+    /// Infinite for cells that are blocked or cut off from the goal
+    pub distance: Vec<f32>,
+}
+
+/// Open list entry; the heap pops the lowest estimated total cost first
+#[derive(PartialEq)]
+struct Open {
+    estimate: f32,
+    cell: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then(other.cell.cmp(&self.cell))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl NavGrid {
+    /// Grid of `cell_size` cells over the world, blocking those that come within `clearance` of an obstacle
+    pub fn build(obstacles: &[Obstacle], width: f32, height: f32, cell_size: f32, clearance: f32) -> NavGrid {
+        let cols = (width / cell_size).ceil().max(1.0) as usize;
+        let rows = (height / cell_size).ceil().max(1.0) as usize;
+        let mut grid = NavGrid { cell_size, cols, rows, blocked: vec![false; cols * rows] };
+        for cell in 0..cols * rows {
+            let center = grid.center(cell);
+            grid.blocked[cell] = obstacles.iter().any(|o| o.closest_point(center).distance(center) < clearance);
+        }
+        grid
+    }
+    pub fn has_obstacles(&self) -> bool {
+        self.blocked.contains(&true)
+    }
+    fn cell(&self, pos: glam::Vec2) -> usize {
+        let col = ((pos.x / self.cell_size).max(0.0) as usize).min(self.cols - 1);
+        let row = ((pos.y / self.cell_size).max(0.0) as usize).min(self.rows - 1);
+        row * self.cols + col
+    }
+    fn center(&self, cell: usize) -> glam::Vec2 {
+        glam::vec2((cell % self.cols) as f32 + 0.5, (cell / self.cols) as f32 + 0.5) * self.cell_size
+    }
+    /// Unblocked cells next to `cell` and the distance to each. Diagonal
+    /// steps that would squeeze between two blocked cells are left out.
+    fn neighbors(&self, cell: usize) -> impl Iterator<Item = (usize, f32)> + '_ {
+        let (col, row) = ((cell % self.cols) as isize, (cell / self.cols) as isize);
+        NEIGHBORS.into_iter().filter_map(move |(dc, dr)| {
+            let (c, r) = (col + dc, row + dr);
+            if c < 0 || r < 0 || c >= self.cols as isize || r >= self.rows as isize {
+                return None;
+            }
+            let next = r as usize * self.cols + c as usize;
+            let corner_cut = dc != 0 && dr != 0
+                && (self.blocked[row as usize * self.cols + c as usize] || self.blocked[r as usize * self.cols + col as usize]);
+            if self.blocked[next] || corner_cut {
+                return None;
+            }
+            let step = if dc != 0 && dr != 0 { std::f32::consts::SQRT_2 } else { 1.0 };
+            Some((next, step * self.cell_size))
+        })
+    }
+    /// Whether the straight line from `a` to `b` stays out of blocked cells,
+    /// checked every half cell
+    pub fn clear_line(&self, a: glam::Vec2, b: glam::Vec2) -> bool {
+        let steps = (a.distance(b) / (self.cell_size * 0.5)).ceil() as usize;
+        (0..=steps).all(|i| !self.blocked[self.cell(a.lerp(b, i as f32 / steps.max(1) as f32))])
+    }
+    /// Waypoints from `from` to `to` around the blocked cells, ending at `to`.
+    /// Only the corners of the grid path are kept, each as far along as the
+    /// one before can see. None if `to` is blocked or cut off.
+    pub fn find_path(&self, from: glam::Vec2, to: glam::Vec2) -> Option<Vec<glam::Vec2>> {
+        let (start, goal) = (self.cell(from), self.cell(to));
+        if self.blocked[goal] {
+            return None;
+        }
+        let heuristic = |cell: usize| self.center(cell).distance(self.center(goal));
+        let mut cost = vec![f32::INFINITY; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[start] = 0.0;
+        open.push(Open { estimate: heuristic(start), cell: start });
+        while let Some(Open { estimate, cell }) = open.pop() {
+            if cell == goal {
+                break;
+            }
+            if estimate > cost[cell] + heuristic(cell) {
+                continue;
+            }
+            for (next, step) in self.neighbors(cell) {
+                let through = cost[cell] + step;
+                if through < cost[next] {
+                    cost[next] = through;
+                    came_from[next] = cell;
+                    open.push(Open { estimate: through + heuristic(next), cell: next });
+                }
+            }
+        }
+        if start != goal && came_from[goal] == usize::MAX {
+            return None;
+        }
+
+        let mut cells = vec![goal];
+        while let Some(&cell) = cells.last().filter(|&&c| c != start) {
+            cells.push(came_from[cell]);
+        }
+        let mut points: Vec<glam::Vec2> = cells.iter().rev().map(|&c| self.center(c)).collect();
+        points[0] = from;
+        *points.last_mut().unwrap() = to;
+        // pull the path tight: from each waypoint skip ahead to the furthest one in sight
+        let mut path = vec![];
+        let (mut anchor, mut i) = (from, 1);
+        while i < points.len() {
+            while i + 1 < points.len() && self.clear_line(anchor, points[i + 1]) {
+                i += 1;
+            }
+            path.push(points[i]);
+            anchor = points[i];
+            i += 1;
+        }
+        if path.is_empty() {
+            path.push(to);
+        }
+        Some(path)
+    }
+    /// Flow field towards `goal`, by Dijkstra outwards from the goal's cell
+    pub fn flow_field(&self, goal: glam::Vec2) -> FlowField {
+        let mut distance = vec![f32::INFINITY; self.blocked.len()];
+        let start = self.cell(goal);
+        let mut open = BinaryHeap::new();
+        if !self.blocked[start] {
+            distance[start] = 0.0;
+            open.push(Open { estimate: 0.0, cell: start });
+        }
+        while let Some(Open { estimate, cell }) = open.pop() {
+            if estimate > distance[cell] {
+                continue;
+            }
+            for (next, step) in self.neighbors(cell) {
+                if estimate + step < distance[next] {
+                    distance[next] = estimate + step;
+                    open.push(Open { estimate: estimate + step, cell: next });
+                }
+            }
+        }
+        FlowField { goal, distance }
+    }
+    /// Which way to fly from `pos` to follow `field` downhill: straight at the
+    /// goal from its own cell, otherwise towards the neighboring cell closest to
+    /// it, which also leads boids out of blocked cells. None where the field
+    /// doesn't reach.
+    pub fn flow_direction(&self, field: &FlowField, pos: glam::Vec2) -> Option<glam::Vec2> {
+        let cell = self.cell(pos);
+        if field.distance[cell] == 0.0 {
+            return Some((field.goal - pos).normalize_or_zero());
+        }
+        self.neighbors(cell)
+            .map(|(next, step)| (next, field.distance[next] + step))
+            .filter(|&(_, distance)| distance.is_finite())
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(next, _)| (self.center(next) - pos).normalize_or_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CELL: f32 = 10.0;
+
+    /// Grid from rows of `.` open and `#` blocked cells
+    fn grid(rows: &[&str]) -> NavGrid {
+        let blocked: Vec<bool> = rows.iter().flat_map(|row| row.chars().map(|c| c == '#')).collect();
+        NavGrid { cell_size: CELL, cols: rows[0].len(), rows: rows.len(), blocked }
+    }
+
+    fn at(col: usize, row: usize) -> glam::Vec2 {
+        glam::vec2(col as f32 + 0.5, row as f32 + 0.5) * CELL
+    }
+
+    const WALL: &[&str] = &[
+        "..........",
+        "....#.....",
+        "....#.....",
+        "....#.....",
+        "....#.....",
+        "..........",
+    ];
+
+    #[test]
+    fn path_goes_around_a_wall() {
+        let grid = grid(WALL);
+        let (from, to) = (at(1, 2), at(8, 2));
+        assert!(!grid.clear_line(from, to));
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(*path.last().unwrap(), to);
+        let mut legs = std::iter::once(from).chain(path.iter().copied()).collect::<Vec<_>>();
+        legs.dedup();
+        for leg in legs.windows(2) {
+            assert!(grid.clear_line(leg[0], leg[1]), "{:?} cuts through the wall", leg);
+        }
+        assert!(path.iter().any(|p| p.y < CELL || p.y > 5.0 * CELL), "{:?} doesn't go round an end", path);
+    }
+
+    #[test]
+    fn no_path_to_a_blocked_or_unreachable_goal() {
+        let grid = grid(&[
+            "......",
+            ".####.",
+            ".#..#.",
+            ".####.",
+            "......",
+        ]);
+        assert_eq!(grid.find_path(at(0, 0), at(1, 1)), None);
+        assert_eq!(grid.find_path(at(0, 0), at(2, 2)), None);
+    }
+
+    #[test]
+    fn path_to_the_same_cell_is_the_goal() {
+        let grid = grid(WALL);
+        let (from, to) = (at(1, 1), at(1, 1) + glam::vec2(2.0, -3.0));
+        assert_eq!(grid.find_path(from, to), Some(vec![to]));
+    }
+
+    #[test]
+    fn no_squeezing_between_diagonal_blocked_cells() {
+        let grid = grid(&[
+            ".#",
+            "#.",
+        ]);
+        assert_eq!(grid.neighbors(0).count(), 0);
+        assert_eq!(grid.find_path(at(0, 0), at(1, 1)), None);
+        assert!(grid.flow_field(at(1, 1)).distance[0].is_infinite());
+    }
+
+    #[test]
+    fn flow_distances_fall_towards_the_goal() {
+        let grid = grid(WALL);
+        let goal = at(8, 2);
+        let field = grid.flow_field(goal);
+        assert_eq!(field.distance[grid.cell(goal)], 0.0);
+        for start in 0..grid.blocked.len() {
+            if grid.blocked[start] {
+                continue;
+            }
+            // walking downhill from any open cell gets to the goal
+            let mut cell = start;
+            while field.distance[cell] > 0.0 {
+                let (next, _) = grid
+                    .neighbors(cell)
+                    .min_by(|a, b| (field.distance[a.0] + a.1).total_cmp(&(field.distance[b.0] + b.1)))
+                    .unwrap();
+                assert!(field.distance[next] < field.distance[cell]);
+                cell = next;
+            }
+            assert_eq!(cell, grid.cell(goal));
+        }
+        let direction = grid.flow_direction(&field, at(3, 2)).unwrap();
+        assert!(direction.x < 0.5 && direction.y != 0.0, "{} heads into the wall", direction);
+    }
+
+    #[test]
+    fn cut_off_cells_stay_infinite() {
+        let grid = grid(&[
+            "......",
+            ".####.",
+            ".#..#.",
+            ".####.",
+            "......",
+        ]);
+        let field = grid.flow_field(at(0, 0));
+        for (cell, &distance) in field.distance.iter().enumerate() {
+            let enclosed = cell == grid.cell(at(2, 2)) || cell == grid.cell(at(3, 2));
+            assert_eq!(distance.is_infinite(), grid.blocked[cell] || enclosed, "cell {}", cell);
+        }
+        assert_eq!(grid.flow_direction(&field, at(2, 2)), None);
+    }
+}
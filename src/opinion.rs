@@ -0,0 +1,76 @@
+//! Opinion dynamics: every boid holds one of a few opinions and now and then
+//! takes up whichever its neighbors mostly hold, so agreement spreads only
+//! as fast as the flock's structure lets it
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::blackboard::{Blackboard, get_f32};
+use crate::boid::Boid;
+
+pub const COLORS: [[f32; 4]; 6] = [
+    [0.95, 0.35, 0.35, 1.0],
+    [0.35, 0.6, 1.0, 1.0],
+    [0.4, 0.9, 0.45, 1.0],
+    [1.0, 0.85, 0.3, 1.0],
+    [0.8, 0.45, 1.0, 1.0],
+    [0.3, 0.9, 0.9, 1.0],
+];
+
+/// How many opinions the world's opinion_count asks for, as many as there are colors at most
+pub fn count(world: &Blackboard) -> usize {
+    (get_f32(world, "opinion_count").max(0.0) as usize).clamp(2, COLORS.len())
+}
+
+/// Give every boid without an opinion, or with one past `count`, one of
+/// `count` at random
+pub fn assign(boids: &mut [Boid], count: usize, rng: &mut StdRng) {
+    for boid in boids.iter_mut() {
+        if boid.opinion.is_none_or(|o| o >= count) {
+            boid.opinion = Some(rng.gen_range(0..count));
+        }
+    }
+}
+
+/// Boids holding each of `count` opinions
+pub fn counts(boids: &[Boid], count: usize) -> Vec<usize> {
+    let mut counts = vec![0; count];
+    for opinion in boids.iter().filter_map(|b| b.opinion) {
+        if let Some(c) = counts.get_mut(opinion) {
+            *c += 1;
+        }
+    }
+    counts
+}
+
+/// The opinion every boid holds, if they all agree
+pub fn consensus(boids: &[Boid]) -> Option<usize> {
+    let first = boids.first()?.opinion?;
+    boids.iter().all(|b| b.opinion == Some(first)).then_some(first)
+}
+
+/// Let each boid, at `rate` per second, take up the opinion most held among
+/// the boids within its visual range, itself included. A boid whose own
+/// opinion ties for most held keeps it; other ties are settled at random.
+/// Every boid decides on the opinions as they were at the start of the tick.
+pub fn update(boids: &mut [Boid], dt: f32, count: usize, rate: f32, rng: &mut StdRng) {
+    let seen: Vec<(glam::Vec2, f32, Option<usize>)> = boids.iter_mut().map(|b| (b.pos(), b.visual_range(), b.opinion)).collect();
+    let chance = (rate * dt).min(1.0);
+    for (i, boid) in boids.iter_mut().enumerate() {
+        if rng.gen::<f32>() >= chance {
+            continue;
+        }
+        let (pos, range, own) = seen[i];
+        let mut tally = vec![0; count];
+        for opinion in seen.iter().filter(|(at, ..)| at.distance(pos) < range).filter_map(|&(.., o)| o) {
+            if let Some(t) = tally.get_mut(opinion) {
+                *t += 1;
+            }
+        }
+        let most = tally.iter().copied().max().unwrap_or(0);
+        if most == 0 || own.is_some_and(|o| tally.get(o) == Some(&most)) {
+            continue;
+        }
+        let tied: Vec<usize> = (0..count).filter(|&o| tally[o] == most).collect();
+        boid.opinion = Some(tied[rng.gen_range(0..tied.len())]);
+    }
+}
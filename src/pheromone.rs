@@ -0,0 +1,73 @@
+/// Decaying scalar grid boids deposit into as they fly
+#[derive(Clone, Debug)]
+pub struct PheromoneGrid {
+    pub cell_size: f32,
+    pub cols: usize,
+    pub rows: usize,
+    pub values: Vec<f32>,
+}
+
+impl PheromoneGrid {
+    pub fn new(width: f32, height: f32, cell_size: f32) -> PheromoneGrid {
+        let cols = (width / cell_size).ceil() as usize;
+        let rows = (height / cell_size).ceil() as usize;
+        PheromoneGrid {
+            cell_size,
+            cols,
+            rows,
+            values: vec![0.0; cols * rows],
+        }
+    }
+    fn cell(&self, pos: glam::Vec2) -> Option<(usize, usize)> {
+        if pos.x < 0.0 || pos.y < 0.0 {
+            return None;
+        }
+        let col = (pos.x / self.cell_size) as usize;
+        let row = (pos.y / self.cell_size) as usize;
+        if col < self.cols && row < self.rows {
+            Some((col, row))
+        } else {
+            None
+        }
+    }
+    fn value_at(&self, col: isize, row: isize) -> f32 {
+        if col < 0 || row < 0 || col as usize >= self.cols || row as usize >= self.rows {
+            0.0
+        } else {
+            self.values[row as usize * self.cols + col as usize]
+        }
+    }
+    pub fn deposit(&mut self, pos: glam::Vec2, amount: f32) {
+        if let Some((col, row)) = self.cell(pos) {
+            self.values[row * self.cols + col] += amount;
+        }
+    }
+    /// Fade every cell by `rate` per second
+    pub fn decay(&mut self, dt: f32, rate: f32) {
+        let keep = (1.0 - rate * dt).max(0.0);
+        for v in &mut self.values {
+            *v *= keep;
+        }
+    }
+    /// Direction of increasing concentration around `pos` (central differences)
+    pub fn gradient(&self, pos: glam::Vec2) -> glam::Vec2 {
+        match self.cell(pos) {
+            Some((col, row)) => {
+                let (col, row) = (col as isize, row as isize);
+                glam::vec2(
+                    self.value_at(col + 1, row) - self.value_at(col - 1, row),
+                    self.value_at(col, row + 1) - self.value_at(col, row - 1),
+                ) / 2.0
+            }
+            None => glam::Vec2::ZERO,
+        }
+    }
+    pub fn clear(&mut self) {
+        for v in &mut self.values {
+            *v = 0.0;
+        }
+    }
+    pub fn max_value(&self) -> f32 {
+        self.values.iter().cloned().fold(0.0, f32::max)
+    }
+}
@@ -0,0 +1,268 @@
+//! Rapier collisions (`--features rapier`). Every scenario obstacle is a fixed
+//! collider and every boid a kinematic body. Each tick a boid's move is swept
+//! against the obstacles, so it stops at a wall and slides along it instead of
+//! being pushed back out afterwards. Rapier's contact events become impacts:
+//! the boid loses `impact_damage` energy and the window puffs where it hit.
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use rapier2d::parry::query::ShapeCastOptions;
+use rapier2d::prelude::*;
+
+use crate::boid::Boid;
+use crate::scenario::Obstacle;
+
+/// Radius boids are swept with, in world units: their beak, not their drawn shape
+const BODY_RADIUS: f32 = 2.0;
+/// Reach of a boid's contact sensor past its body, so a boid stopped against
+/// a wall is still touching it
+const CONTACT_REACH: f32 = 1.0;
+/// Gap a sweep leaves between a boid and the wall it hits, in world units
+const SKIN: f32 = 0.1;
+/// Times a move may slide along a wall before the rest of it is dropped;
+/// two get a boid round the inside of a corner
+const SLIDES: usize = 2;
+
+/// A boid that ran into an obstacle
+#[derive(Clone, Copy, Debug)]
+pub struct Impact {
+    pub id: usize,
+    pub pos: glam::Vec2,
+    pub color: [f32; 4],
+}
+
+/// Collects the contact events of a pipeline step
+#[derive(Default)]
+struct Contacts(Mutex<Vec<CollisionEvent>>);
+
+impl EventHandler for Contacts {
+    fn handle_collision_event(&self, _: &RigidBodySet, _: &ColliderSet, event: CollisionEvent, _: Option<&ContactPair>) {
+        self.0.lock().unwrap().push(event);
+    }
+    fn handle_contact_force_event(&self, _: Real, _: &RigidBodySet, _: &ColliderSet, _: &ContactPair, _: Real) {}
+}
+
+pub struct Physics {
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    /// Colliders of the scenario's obstacles
+    walls: Vec<ColliderHandle>,
+    /// Body of each boid, by boid id
+    handles: HashMap<usize, RigidBodyHandle>,
+    islands: IslandManager,
+    broad_phase: DefaultBroadPhase,
+    narrow_phase: NarrowPhase,
+    impulse_joints: ImpulseJointSet,
+    multibody_joints: MultibodyJointSet,
+    ccd: CCDSolver,
+    queries: QueryPipeline,
+    pipeline: PhysicsPipeline,
+    /// Boids that ran into an obstacle on the last tick, until the window takes them
+    pub impacts: Vec<Impact>,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Physics {
+            bodies: RigidBodySet::new(),
+            colliders: ColliderSet::new(),
+            walls: vec![],
+            handles: HashMap::new(),
+            islands: IslandManager::new(),
+            broad_phase: DefaultBroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            impulse_joints: ImpulseJointSet::new(),
+            multibody_joints: MultibodyJointSet::new(),
+            ccd: CCDSolver::new(),
+            queries: QueryPipeline::new(),
+            pipeline: PhysicsPipeline::new(),
+            impacts: vec![],
+        }
+    }
+}
+
+// the pipeline only holds scratch space between steps, so a copy gets its own
+impl Clone for Physics {
+    fn clone(&self) -> Self {
+        Physics {
+            bodies: self.bodies.clone(),
+            colliders: self.colliders.clone(),
+            walls: self.walls.clone(),
+            handles: self.handles.clone(),
+            islands: self.islands.clone(),
+            broad_phase: self.broad_phase.clone(),
+            narrow_phase: self.narrow_phase.clone(),
+            impulse_joints: self.impulse_joints.clone(),
+            multibody_joints: self.multibody_joints.clone(),
+            ccd: self.ccd.clone(),
+            queries: self.queries.clone(),
+            pipeline: PhysicsPipeline::new(),
+            impacts: self.impacts.clone(),
+        }
+    }
+}
+
+impl Physics {
+    /// Replace the obstacle colliders with `obstacles`
+    pub fn set_obstacles(&mut self, obstacles: &[Obstacle]) {
+        for wall in self.walls.drain(..) {
+            self.colliders.remove(wall, &mut self.islands, &mut self.bodies, false);
+        }
+        for obstacle in obstacles {
+            let collider = ColliderBuilder::cuboid(obstacle.w / 2.0, obstacle.h / 2.0)
+                .translation(vector![obstacle.x + obstacle.w / 2.0, obstacle.y + obstacle.h / 2.0])
+                .build();
+            self.walls.push(self.colliders.insert(collider));
+        }
+        self.queries.update(&self.colliders);
+    }
+    /// Where a boid moving by `motion` from `from` ends up, sliding along any
+    /// obstacle in the way, and the outward normal of the last one it hit
+    pub fn sweep(&self, from: glam::Vec2, mut motion: glam::Vec2) -> (glam::Vec2, Option<glam::Vec2>) {
+        let ball = Ball::new(BODY_RADIUS);
+        let options = ShapeCastOptions {
+            max_time_of_impact: 1.0,
+            target_distance: SKIN,
+            stop_at_penetration: false,
+            compute_impact_geometry_on_penetration: true,
+        };
+        let (mut pos, mut normal) = (from, None);
+        for _ in 0..=SLIDES {
+            let at = Isometry::translation(pos.x, pos.y);
+            let velocity = vector![motion.x, motion.y];
+            let filter = QueryFilter::only_fixed();
+            let Some((_, hit)) = self.queries.cast_shape(&self.bodies, &self.colliders, &at, &velocity, &ball, options, filter) else {
+                return (pos + motion, normal);
+            };
+            let wall = glam::vec2(hit.normal1.x, hit.normal1.y);
+            pos += motion * hit.time_of_impact;
+            // the rest of the move, less the part into the wall
+            motion *= 1.0 - hit.time_of_impact;
+            motion -= wall * motion.dot(wall).min(0.0);
+            normal = Some(wall);
+        }
+        (pos, normal)
+    }
+    /// Move the boids' bodies to where the boids are, giving hatchlings bodies
+    /// and dropping the dead's, and gather the boids that hit an obstacle into
+    /// `impacts`
+    pub fn sync(&mut self, boids: &[Boid], dt: f32) {
+        let alive: HashMap<usize, &Boid> = boids.iter().map(|b| (b.id, b)).collect();
+        self.handles.retain(|id, handle| {
+            let keep = alive.contains_key(id);
+            if !keep {
+                self.bodies.remove(*handle, &mut self.islands, &mut self.colliders,
+                                   &mut self.impulse_joints, &mut self.multibody_joints, true);
+            }
+            keep
+        });
+        for boid in boids {
+            let pos = boid.pos();
+            match self.handles.get(&boid.id) {
+                // moved outright rather than by its next kinematic position, so
+                // this step's contacts are where the boid is now
+                Some(&handle) => self.bodies[handle].set_translation(vector![pos.x, pos.y], true),
+                None => {
+                    let body = RigidBodyBuilder::kinematic_position_based()
+                        .translation(vector![pos.x, pos.y])
+                        .user_data(boid.id as u128)
+                        .build();
+                    let handle = self.bodies.insert(body);
+                    let sensor = ColliderBuilder::ball(BODY_RADIUS + CONTACT_REACH)
+                        .sensor(true)
+                        .active_collision_types(ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_FIXED)
+                        .active_events(ActiveEvents::COLLISION_EVENTS)
+                        .build();
+                    self.colliders.insert_with_parent(sensor, handle, &mut self.bodies);
+                    self.handles.insert(boid.id, handle);
+                }
+            }
+        }
+
+        let params = IntegrationParameters { dt, ..Default::default() };
+        let contacts = Contacts::default();
+        self.pipeline.step(
+            &vector![0.0, 0.0],
+            &params,
+            &mut self.islands,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.bodies,
+            &mut self.colliders,
+            &mut self.impulse_joints,
+            &mut self.multibody_joints,
+            &mut self.ccd,
+            Some(&mut self.queries),
+            &(),
+            &contacts,
+        );
+
+        self.impacts.clear();
+        for event in contacts.0.into_inner().unwrap() {
+            let CollisionEvent::Started(a, b, _) = event else {
+                continue;
+            };
+            let body = [a, b].into_iter().find_map(|h| self.colliders.get(h).and_then(|c| c.parent()));
+            let id = body.map(|h| self.bodies[h].user_data as usize);
+            if let Some(boid) = id.and_then(|id| alive.get(&id)) {
+                self.impacts.push(Impact { id: boid.id, pos: boid.pos(), color: boid.color });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bonsai_bt::BT;
+
+    use super::*;
+    use crate::blackboard::get_f32;
+    use crate::boid::BoidBT;
+    use crate::real::real;
+    use crate::scenario::Scenario;
+    use crate::simulation::{self, NO_CURSOR, Simulation};
+
+    fn wall() -> Obstacle {
+        Obstacle { x: 100.0, y: 0.0, w: 20.0, h: 300.0 }
+    }
+
+    #[test]
+    fn a_move_into_a_wall_stops_short_and_slides_along_it() {
+        let mut physics = Physics::default();
+        physics.set_obstacles(&[wall()]);
+
+        let (to, normal) = physics.sweep(glam::vec2(90.0, 50.0), glam::vec2(20.0, 20.0));
+        assert_eq!(normal, Some(glam::vec2(-1.0, 0.0)));
+        assert!((to.x - (100.0 - BODY_RADIUS - SKIN)).abs() < 0.01, "stopped at x = {}", to.x);
+        assert!(to.y > 65.0, "didn't slide down the wall: y = {}", to.y);
+
+        let (to, normal) = physics.sweep(glam::vec2(50.0, 50.0), glam::vec2(20.0, 20.0));
+        assert_eq!((to, normal), (glam::vec2(70.0, 70.0), None));
+    }
+
+    #[test]
+    fn a_boid_flying_into_a_wall_is_stopped_and_hurt() {
+        let boid_bt: BoidBT = BT::new(Boid::create_bt(), Boid::create_blackboard());
+        let world = simulation::create_world_blackboard(400.0, 300.0);
+        let mut sim = Simulation::new(boid_bt, world, 400.0, 300.0, 3);
+        let params = [("obstacle_factor", 0.0), ("energy_drain", 0.0), ("impact_damage", 0.25)];
+        sim.set_scenario(Scenario {
+            name: "wall".to_string(),
+            obstacles: vec![wall()],
+            params: params.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+            ..Default::default()
+        });
+        sim.spawn(1);
+        let boid = &mut sim.boids[0];
+        (boid.x, boid.y, boid.dx, boid.dy) = (real(96.0), real(150.0), real(200.0), real(0.0));
+
+        sim.step(std::time::Duration::from_secs_f32(0.05), NO_CURSOR);
+        let boid = &mut sim.boids[0];
+        assert!(boid.pos().x < 100.0 - BODY_RADIUS, "went into the wall: x = {}", boid.pos().x);
+        assert!(boid.dx <= real(0.0), "still flying into the wall");
+        let impacts = &sim.physics.impacts;
+        assert_eq!(impacts.iter().map(|i| i.id).collect::<Vec<_>>(), [boid.id]);
+        let energy = get_f32(boid.bt.get_blackboard().get_db(), "energy");
+        assert!((energy - 0.75).abs() < 1e-4, "energy {}", energy);
+    }
+}
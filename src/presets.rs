@@ -0,0 +1,155 @@
+//! Named parameter sets: a few curated flock characters, plus any tuning the
+//! user saved under a name of their own
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::blackboard::{BBValue, Blackboard};
+use crate::simulation::create_world_blackboard;
+
+/// Directory user presets are saved to and loaded from, one file per preset
+pub const PRESET_DIR: &str = "presets";
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    /// Numeric world parameter overrides
+    pub params: BTreeMap<String, f32>,
+    /// File a user preset was loaded from or saved to; built-in presets have none
+    #[serde(skip)]
+    pub file: Option<PathBuf>,
+}
+
+impl Preset {
+    fn new(name: &str, params: &[(&str, f32)]) -> Preset {
+        Preset { name: name.to_string(), params: params.iter().map(|&(k, v)| (k.to_string(), v)).collect(), file: None }
+    }
+    /// Overrides as `apply_preset` takes them
+    pub fn entries(&self) -> Vec<(&str, f32)> {
+        self.params.iter().map(|(k, &v)| (k.as_str(), v)).collect()
+    }
+    /// Every numeric entry of `world` that differs from the defaults for its size
+    pub fn capture(name: &str, world: &Blackboard, width: f32, height: f32) -> Preset {
+        let defaults = create_world_blackboard(width, height);
+        let params = world
+            .iter()
+            .filter_map(|(key, value)| match (value, defaults.get(key)) {
+                (BBValue::F32(v), Some(BBValue::F32(d))) if v != d => Some((key.clone(), *v)),
+                _ => None,
+            })
+            .collect();
+        Preset { name: name.to_string(), params, file: None }
+    }
+}
+
+pub fn builtin() -> Vec<Preset> {
+    vec![
+        // fast, tightly packed and strongly aligned, turning as one
+        Preset::new("murmuration", &[
+            ("centering_factor", 5.0),
+            ("matching_factor", 10.0),
+            ("avoid_factor", 45.0),
+            ("max_turn_rate", 720.0),
+            ("speed_scale", 1.2),
+        ]),
+        // slow, loosely spaced, sweeping wide turns
+        Preset::new("fish school", &[
+            ("centering_factor", 1.5),
+            ("matching_factor", 4.0),
+            ("avoid_factor", 15.0),
+            ("max_turn_rate", 180.0),
+            ("speed_scale", 0.6),
+        ]),
+        // a cloud held together loosely, every gnat darting its own way
+        Preset::new("gnat swarm", &[
+            ("centering_factor", 8.0),
+            ("matching_factor", 0.5),
+            ("avoid_factor", 10.0),
+            ("wander_weight", 1.0),
+            ("branch_interval", 0.5),
+            ("max_turn_rate", 1080.0),
+            ("speed_scale", 0.8),
+        ]),
+    ]
+}
+
+/// Built-in presets plus any saved in `PRESET_DIR`, replacing any with the same name
+pub fn load() -> Result<Vec<Preset>, String> {
+    let mut presets = builtin();
+    let entries = match fs::read_dir(PRESET_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(presets),
+        Err(e) => return Err(format!("{}: {}", PRESET_DIR, e)),
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let preset = read(&path)?;
+        match presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+    }
+    Ok(presets)
+}
+
+fn read(path: &Path) -> Result<Preset, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let preset: Preset = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Preset { file: Some(path.to_path_buf()), ..preset })
+}
+
+/// Write `preset` to its own file in `PRESET_DIR`, named after it
+pub fn save(preset: &mut Preset) -> Result<(), String> {
+    fs::create_dir_all(PRESET_DIR).map_err(|e| format!("{}: {}", PRESET_DIR, e))?;
+    let path = Path::new(PRESET_DIR).join(format!("{}.json", preset.name));
+    let text = serde_json::to_string_pretty(preset).map_err(|e| e.to_string())?;
+    fs::write(&path, text + "\n").map_err(|e| format!("{}: {}", path.display(), e))?;
+    preset.file = Some(path);
+    Ok(())
+}
+
+/// Rename the user preset `index` of `presets`, moving it to a file of the new name
+pub fn rename(presets: &mut [Preset], index: usize, name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(format!("'{}' can't be used as a file name", name));
+    }
+    if presets.iter().enumerate().any(|(i, p)| i != index && p.name == name) {
+        return Err(format!("there is already a preset called '{}'", name));
+    }
+    let preset = &mut presets[index];
+    let Some(old) = preset.file.clone() else {
+        return Err(format!("'{}' is built in", preset.name));
+    };
+    let previous = std::mem::replace(&mut preset.name, name.to_string());
+    if let Err(e) = save(preset) {
+        preset.name = previous;
+        return Err(e);
+    }
+    if preset.file.as_ref() != Some(&old) {
+        fs::remove_file(&old).map_err(|e| format!("{}: {}", old.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Delete the file of a user preset
+pub fn delete(preset: &Preset) -> Result<(), String> {
+    match &preset.file {
+        Some(path) => fs::remove_file(path).map_err(|e| format!("{}: {}", path.display(), e)),
+        None => Err(format!("'{}' is built in", preset.name)),
+    }
+}
+
+/// First `custom-N` not already taken by one of `presets`
+pub fn unused_name(presets: &[Preset]) -> String {
+    (1..)
+        .map(|n| format!("custom-{}", n))
+        .find(|name| presets.iter().all(|p| &p.name != name))
+        .unwrap()
+}
@@ -0,0 +1,84 @@
+//! Particle puffs marking where boids were removed by hand. Purely visual: the
+//! particles live in the front end, not the simulation.
+use ggez::{GameResult, graphics};
+use rand::Rng;
+
+/// Particles per puff
+const COUNT: usize = 12;
+/// Seconds a particle lasts
+const LIFETIME: f32 = 0.6;
+/// Fastest a particle flies out, in world units per second
+const SPEED: f32 = 90.0;
+/// Share of its speed a particle keeps after a second
+const DRAG: f32 = 0.05;
+const RADIUS: f32 = 2.0;
+/// Particles made up front; a burst with none spare is cut short
+const POOL: usize = COUNT * 64;
+
+#[derive(Clone, Copy, Default)]
+struct Particle {
+    pos: glam::Vec2,
+    vel: glam::Vec2,
+    age: f32,
+    color: [f32; 4],
+}
+
+pub struct Puffs {
+    /// Particles in flight
+    particles: Vec<Particle>,
+    /// Faded particles kept for the next burst, like the simulation's spare boids
+    spares: Vec<Particle>,
+}
+
+impl Default for Puffs {
+    fn default() -> Self {
+        Puffs { particles: Vec::with_capacity(POOL), spares: vec![Particle::default(); POOL] }
+    }
+}
+
+impl Puffs {
+    /// Bytes the particle buffers hold
+    pub fn bytes(&self) -> usize {
+        (self.particles.capacity() + self.spares.capacity()) * std::mem::size_of::<Particle>()
+    }
+    /// Scatter a ring of `color` particles out from `pos`
+    pub fn burst(&mut self, pos: glam::Vec2, color: [f32; 4]) {
+        // only for show, so off the simulation's rng
+        let mut rng = rand::thread_rng();
+        for i in 0..COUNT {
+            let angle = (i as f32 + rng.gen::<f32>()) / COUNT as f32 * std::f32::consts::TAU;
+            let speed = SPEED * rng.gen_range(0.4..1.0);
+            let vel = glam::vec2(angle.cos(), angle.sin()) * speed;
+            if self.spares.pop().is_none() {
+                break;
+            }
+            self.particles.push(Particle { pos, vel, age: 0.0, color });
+        }
+    }
+    /// Move the particles on by `dt` seconds and put the ones that have faded
+    /// out back in the pool
+    pub fn update(&mut self, dt: f32) {
+        let keep = DRAG.powf(dt);
+        let mut i = 0;
+        while i < self.particles.len() {
+            let p = &mut self.particles[i];
+            p.pos += p.vel * dt;
+            p.vel *= keep;
+            p.age += dt;
+            if p.age < LIFETIME {
+                i += 1;
+            } else {
+                self.spares.push(self.particles.swap_remove(i));
+            }
+        }
+    }
+    /// Add the particles to the frame's batch, fading as they age
+    pub fn draw(&self, mb: &mut graphics::MeshBuilder) -> GameResult {
+        for p in &self.particles {
+            let [r, g, b, a] = p.color;
+            let alpha = a * (1.0 - p.age / LIFETIME);
+            mb.circle(graphics::DrawMode::fill(), p.pos, RADIUS, 0.5, [r, g, b, alpha].into())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+//! Scalar type of the simulation core. Build with `--features f64` for long
+//! headless runs where f32 rounding drift becomes noticeable; parameters,
+//! rendering and file formats stay f32 either way.
+
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+#[cfg(not(feature = "f64"))]
+pub type RVec2 = glam::Vec2;
+#[cfg(feature = "f64")]
+pub type RVec2 = glam::DVec2;
+
+/// Widen an f32 parameter into the simulation scalar
+#[allow(clippy::useless_conversion)]
+pub fn real(v: f32) -> Real {
+    Real::from(v)
+}
+
+pub fn rvec2(v: glam::Vec2) -> RVec2 {
+    RVec2::new(real(v.x), real(v.y))
+}
+
+/// Narrow a simulation scalar for rendering and output
+#[allow(clippy::unnecessary_cast)]
+pub fn to_f32(v: Real) -> f32 {
+    v as f32
+}
+
+pub fn vec2_f32(v: RVec2) -> glam::Vec2 {
+    glam::vec2(to_f32(v.x), to_f32(v.y))
+}
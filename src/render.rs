@@ -0,0 +1,336 @@
+use ggez::{Context, GameResult, graphics};
+
+use crate::blackboard::{get_bool, get_f32};
+use crate::boid::{FLASH_DURATION, Steering};
+use crate::chains;
+use crate::epidemic::Health;
+use crate::geometry::Geometry;
+use crate::inspector;
+use crate::opinion;
+use crate::mates;
+use crate::render_cache::RenderCache;
+use crate::roost;
+use crate::scenario::Decoration;
+use crate::selection::Selection;
+use crate::simulation::Simulation;
+use crate::theme::Theme;
+
+/// Drawing backend. Simulation code never calls a backend directly; the front
+/// end hands each frame's simulations and text to one of these.
+pub trait Renderer {
+    type Error;
+    fn begin_frame(&mut self) -> Result<(), Self::Error>;
+    /// Draw the trails and boids of `sim`, shifted by `offset` world units
+    fn draw_boids(&mut self, sim: &Simulation, offset: glam::Vec2) -> Result<(), Self::Error>;
+    /// Text lines drawn over the flock
+    fn draw_overlay(&mut self, lines: &[String]) -> Result<(), Self::Error>;
+    /// Text lines in a panel with its top-left corner at `at`, in screen units
+    fn draw_overlay_at(&mut self, lines: &[String], at: glam::Vec2) -> Result<(), Self::Error>;
+    /// A message across the middle of the screen, `size` high
+    fn draw_banner(&mut self, text: &str, size: f32) -> Result<(), Self::Error>;
+    fn end_frame(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Window backend. Shapes are batched into one mesh, drawn before any overlay
+/// text or at the end of the frame along with the scenario's labels.
+pub struct GgezRenderer<'a> {
+    ctx: &'a mut Context,
+    /// Boid polygon at size 1, pointing up
+    points: &'a [glam::Vec2],
+    pub cache: &'a mut RenderCache,
+    selection: &'a Selection,
+    theme: &'a Theme,
+    /// Shear boids into their turns as if banking
+    pub banking: bool,
+    /// Tint boids by their strongest flocking rule instead of their own color
+    pub rule_colors: bool,
+    /// Draw the pheromone heat layer
+    pub trails: bool,
+    /// Sizes of the marks around boids, and the cull margin, at the display's scale
+    pub geometry: Geometry,
+    /// World to screen transform the batch and labels are drawn with, from
+    /// the camera while drawing the world and the identity for the HUD
+    pub view: glam::Mat4,
+    /// Part of the screen the world is drawn into, logical pixels; banners are centered over it
+    pub playfield: graphics::Rect,
+    mesh: graphics::MeshBuilder,
+    /// Whether anything was added to `mesh` since it was last drawn
+    dirty: bool,
+    /// Whether any text was queued to go with it
+    labels: bool,
+}
+
+impl<'a> GgezRenderer<'a> {
+    pub fn new(ctx: &'a mut Context, points: &'a [glam::Vec2], cache: &'a mut RenderCache, selection: &'a Selection,
+               theme: &'a Theme) -> GgezRenderer<'a> {
+        let playfield = graphics::screen_coordinates(ctx);
+        GgezRenderer { ctx, points, cache, selection, theme, banking: false, rule_colors: false, trails: true, geometry: Geometry::default(), view: glam::Mat4::IDENTITY, playfield, mesh: graphics::MeshBuilder::new(), dirty: false, labels: false }
+    }
+    fn mesh(&mut self) -> &mut graphics::MeshBuilder {
+        self.dirty = true;
+        &mut self.mesh
+    }
+    /// Add window-only shapes (selection box, fields, puffs) to the frame's batch
+    pub fn draw_shapes(&mut self, f: impl FnOnce(&mut graphics::MeshBuilder) -> GameResult) -> GameResult {
+        f(self.mesh())
+    }
+    /// Run a window-only pass (canvases, images, graphs) straight on the
+    /// context, after everything batched so far has been drawn
+    pub fn pass<T>(&mut self, f: impl FnOnce(&mut Context) -> GameResult<T>) -> GameResult<T> {
+        self.flush()?;
+        f(self.ctx)
+    }
+    /// Draw the shapes batched so far, leaving queued text for later
+    fn flush_shapes(&mut self) -> GameResult {
+        if self.dirty {
+            let m = self.mesh.build(self.ctx)?;
+            graphics::draw(self.ctx, &m, graphics::DrawParam::new().transform(self.view))?;
+            self.mesh = graphics::MeshBuilder::new();
+            self.dirty = false;
+        }
+        Ok(())
+    }
+    /// Draw what has been batched so far
+    pub fn flush(&mut self) -> GameResult {
+        self.flush_shapes()?;
+        if self.labels {
+            graphics::draw_queued_text(self.ctx, graphics::DrawParam::new().transform(self.view), None,
+                                       graphics::FilterMode::Linear)?;
+            self.labels = false;
+        }
+        Ok(())
+    }
+    /// World rectangle `view` shows on the screen, grown by `margin` on every side
+    fn visible(&self, margin: f32) -> graphics::Rect {
+        let screen = graphics::screen_coordinates(self.ctx);
+        let to_world = self.view.inverse();
+        let corners = [(screen.x, screen.y), (screen.right(), screen.y), (screen.x, screen.bottom()), (screen.right(), screen.bottom())]
+            .map(|(x, y)| to_world.transform_point3(glam::vec3(x, y, 0.0)).truncate());
+        let min = corners.iter().copied().reduce(glam::Vec2::min).unwrap() - glam::Vec2::splat(margin);
+        let max = corners.iter().copied().reduce(glam::Vec2::max).unwrap() + glam::Vec2::splat(margin);
+        graphics::Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+    /// Highlight the cursor at `pos`, over everything batched so far
+    pub fn draw_cursor(&mut self, pos: glam::Vec2) -> GameResult {
+        self.flush_shapes()?;
+        let view = self.view * glam::Mat4::from_translation(pos.extend(0.0));
+        let cursor = self.cache.cursor(self.ctx)?;
+        graphics::draw(self.ctx, cursor, graphics::DrawParam::new().transform(view))
+    }
+}
+
+/// Red, green or blue for whichever of separation, cohesion and alignment
+/// steered hardest, gray when none did
+fn rule_color(steering: &Steering) -> [f32; 3] {
+    let rules = [
+        (steering.separation, [1.0, 0.3, 0.3]),
+        (steering.cohesion, [0.3, 1.0, 0.3]),
+        (steering.alignment, [0.3, 0.5, 1.0]),
+    ];
+    let (force, rgb) = rules
+        .iter()
+        .max_by(|a, b| a.0.length_squared().total_cmp(&b.0.length_squared()))
+        .unwrap();
+    if *force == glam::Vec2::ZERO { [0.6; 3] } else { *rgb }
+}
+
+impl Renderer for GgezRenderer<'_> {
+    type Error = ggez::GameError;
+    fn begin_frame(&mut self) -> GameResult {
+        graphics::clear(self.ctx, self.theme.background.into());
+        Ok(())
+    }
+    fn draw_boids(&mut self, sim: &Simulation, offset: glam::Vec2) -> GameResult {
+        // the selection belongs to the unshifted simulation
+        let primary = offset == glam::Vec2::ZERO;
+        for decoration in &sim.scenario.decorations {
+            if let Decoration::Label { x, y, text, size, color } = decoration {
+                let text = graphics::Text::new(graphics::TextFragment {
+                    text: text.clone(),
+                    color: Some((*color).into()),
+                    font: Some(graphics::Font::default()),
+                    scale: Some(graphics::PxScale::from(*size)),
+                });
+                graphics::queue_text(self.ctx, &text, glam::vec2(*x, *y) + offset, None);
+                self.labels = true;
+            }
+        }
+        let (points, selection, banking, rule_colors, trails) = (self.points, self.selection, self.banking, self.rule_colors, self.trails);
+        let world = sim.world_bb.read().unwrap();
+        let mb = self.mesh();
+        /*Night sky and roosts..*/
+        let darkness = roost::darkness(sim.clock, get_f32(&world, "day_length"), get_f32(&world, "dusk"));
+        if darkness > 0.0 {
+            let rect = graphics::Rect::new(offset.x, offset.y, sim.width, sim.height);
+            mb.rectangle(graphics::DrawMode::fill(), rect, [0.0, 0.0, 0.1, 0.4 * darkness].into())?;
+        }
+        let roost_radius = get_f32(&world, "roost_radius");
+        for roost in &sim.roosts {
+            mb.circle(graphics::DrawMode::stroke(1.0), *roost + offset, roost_radius, 0.5, [0.6, 0.3, 1.0, 0.4].into())?;
+        }
+        if let Some(migration) = &sim.migration {
+            let target = migration.target + offset;
+            mb.circle(graphics::DrawMode::stroke(2.0), target, 12.0, 0.5, [0.2, 0.8, 0.7, 0.8].into())?;
+            mb.line(&[target, target + glam::vec2(migration.heading * 24.0, 0.0)], 2.0, [0.2, 0.8, 0.7, 0.8].into())?;
+        }
+        if let Some(nest) = sim.scenario.nest {
+            let nest_radius = get_f32(&world, "nest_radius");
+            mb.circle(graphics::DrawMode::fill(), glam::Vec2::from(nest) + offset, nest_radius, 0.5, [0.55, 0.35, 0.2, 0.6].into())?;
+        }
+        /*Scenario walls and lanes, built once per scenario..*/
+        self.flush_shapes()?;
+        let view = self.view * glam::Mat4::from_translation(offset.extend(0.0));
+        if let Some(scenery) = self.cache.scenery(self.ctx, &sim.scenario, sim.width)? {
+            graphics::draw(self.ctx, scenery, graphics::DrawParam::new().transform(view))?;
+        }
+        let visible = self.visible(0.0);
+        let mb = self.mesh();
+        /*Pheromone heat layer, the cells in view..*/
+        let max_trail = sim.trails.max_value();
+        if trails && max_trail > 0.0 {
+            let (size, cols) = (sim.trails.cell_size, sim.trails.cols);
+            let rows = sim.trails.values.len() / cols;
+            let first = ((glam::vec2(visible.x, visible.y) - offset) / size).floor().max(glam::Vec2::ZERO);
+            let last = ((glam::vec2(visible.right(), visible.bottom()) - offset) / size).ceil();
+            for row in first.y as usize..(last.y.max(0.0) as usize).min(rows) {
+                for col in first.x as usize..(last.x.max(0.0) as usize).min(cols) {
+                    let alpha = (sim.trails.values[row * cols + col] / max_trail) * 0.25;
+                    if alpha < 0.01 {
+                        continue;
+                    }
+                    let rect = graphics::Rect::new(col as f32 * size + offset.x, row as f32 * size + offset.y, size, size);
+                    mb.rectangle(graphics::DrawMode::fill(), rect, [1.0, 0.6, 0.2, alpha].into())?;
+                }
+            }
+        }
+        let food_radius = get_f32(&world, "food_radius");
+        let food_capacity = get_f32(&world, "food_capacity");
+        for patch in &sim.food.patches {
+            let fill = (patch.amount / food_capacity).clamp(0.0, 1.0);
+            mb.circle(graphics::DrawMode::fill(), patch.pos + offset, food_radius, 0.5, [0.4, 0.8, 0.3, 0.1 + 0.3 * fill].into())?;
+        }
+        for (i, j) in mates::pairs(&sim.boids) {
+            mb.line(&[sim.boids[i].pos() + offset, sim.boids[j].pos() + offset], 1.0, [1.0, 0.8, 0.9, 0.25].into())?;
+        }
+        for (i, j) in chains::links(&sim.boids) {
+            mb.line(&[sim.boids[i].pos() + offset, sim.boids[j].pos() + offset], 2.0, [0.9, 0.9, 0.9, 0.35].into())?;
+        }
+        self.flush_shapes()?;
+        /*Boids, one instance of the cached polygon each, with their marks batched on top..*/
+        let maturity_age = get_f32(&world, "maturity_age");
+        let lifespan = get_f32(&world, "lifespan");
+        let max_turn_rate = get_f32(&world, "max_turn_rate").to_radians();
+        let epidemic = get_bool(&world, "epidemic");
+        // only what the camera sees is drawn
+        let object_size = self.geometry.object_size;
+        let visible = self.visible(self.geometry.cull_margin);
+        let batch = self.cache.boids(self.ctx, points)?;
+        let mb = &mut self.mesh;
+        for boid in &sim.boids {
+            let selected = primary && selection.contains(boid.id);
+            if !visible.contains(boid.pos() + offset) && !selected {
+                continue;
+            }
+            let vel = boid.vel();
+            let mut rot = glam::Mat2::from_angle(vel.x.atan2(-vel.y));
+            if banking {
+                // the tail swings out of the turn
+                let bank = (boid.turn_rate / max_turn_rate).clamp(-1.0, 1.0) * 0.5;
+                rot *= glam::Mat2::from_cols(glam::vec2(1.0, 0.0), glam::vec2(-bank, 1.0));
+            }
+            let size = boid.drawn_size(maturity_age);
+            let pos = boid.pos() + offset;
+            let shape = rot * size;
+            let transform = glam::Mat4::from_cols(
+                shape.x_axis.extend(0.0).extend(0.0),
+                shape.y_axis.extend(0.0).extend(0.0),
+                glam::Vec4::Z,
+                pos.extend(0.0).extend(1.0),
+            );
+            let mut color = boid.color;
+            if let Some(opinion) = boid.opinion {
+                color = opinion::COLORS[opinion % opinion::COLORS.len()];
+            }
+            if epidemic {
+                color = Health::COLORS[boid.health.index()];
+            }
+            if rule_colors {
+                let [r, g, b] = rule_color(&boid.steering);
+                color = [r, g, b, color[3]];
+            }
+            color[3] *= boid.vitality(lifespan);
+            batch.add(graphics::DrawParam::new().transform(transform).color(color.into()));
+            if boid.flash > 0.0 {
+                // ripple spreading and fading out from the turn
+                let t = 1.0 - boid.flash / FLASH_DURATION;
+                let radius = object_size * size * (0.5 + t);
+                mb.circle(graphics::DrawMode::stroke(1.5), pos, radius, 0.5, [1.0, 1.0, 1.0, 0.6 * (1.0 - t)].into())?;
+                self.dirty = true;
+            }
+            if boid.frozen {
+                mb.circle(graphics::DrawMode::stroke(1.0), pos, object_size * size * 0.6, 0.5, [0.6, 0.9, 1.0, 0.8].into())?;
+                self.dirty = true;
+            }
+            if selected {
+                let polygon: Vec<glam::Vec2> = points.iter().map(|&p| shape * p + pos).collect();
+                mb.polygon(graphics::DrawMode::stroke(1.5), &polygon, graphics::Color::WHITE)?;
+                // planned route, if it was sent round obstacles
+                if boid.path.len() > 1 {
+                    let route: Vec<glam::Vec2> = std::iter::once(pos).chain(boid.path.iter().rev().copied()).collect();
+                    mb.line(&route, 1.0, [1.0, 0.4, 1.0, 0.4].into())?;
+                }
+                self.dirty = true;
+            }
+        }
+        batch.draw(self.ctx, graphics::DrawParam::new().transform(self.view))?;
+        Ok(())
+    }
+    fn draw_overlay(&mut self, lines: &[String]) -> GameResult {
+        self.flush()?;
+        inspector::draw(self.ctx, lines, &self.theme.hud)
+    }
+    fn draw_overlay_at(&mut self, lines: &[String], at: glam::Vec2) -> GameResult {
+        self.flush()?;
+        inspector::draw_at(self.ctx, lines, at, &self.theme.hud).map(|_| ())
+    }
+    fn draw_banner(&mut self, text: &str, size: f32) -> GameResult {
+        self.flush()?;
+        let text = self.cache.text("banner", text, size, self.theme.hud.text);
+        let (width, height) = (text.width(self.ctx), text.height(self.ctx));
+        let field = self.playfield;
+        let pos = glam::vec2(field.x + (field.w - width) / 2.0, field.y + (field.h - height) / 2.0);
+        graphics::draw(self.ctx, text, graphics::DrawParam::default().dest(pos))
+    }
+    fn end_frame(&mut self) -> GameResult {
+        self.flush()?;
+        graphics::present(self.ctx)
+    }
+}
+
+/// Backend that draws nothing, so front-end code written against `Renderer`
+/// runs in tests without a window
+#[cfg(test)]
+pub struct NullRenderer;
+
+#[cfg(test)]
+impl Renderer for NullRenderer {
+    type Error = std::convert::Infallible;
+    fn begin_frame(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn draw_boids(&mut self, _sim: &Simulation, _offset: glam::Vec2) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn draw_overlay(&mut self, _lines: &[String]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn draw_overlay_at(&mut self, _lines: &[String], _at: glam::Vec2) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn draw_banner(&mut self, _text: &str, _size: f32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn end_frame(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! Meshes and text kept from one frame to the next, rebuilt only when what
+//! they show changes
+use std::collections::HashMap;
+
+use ggez::{Context, GameResult, graphics};
+
+use crate::scenario::{Decoration, Scenario};
+
+/// Highlight drawn under the cursor
+const CURSOR_RADIUS: f32 = 10.0; // World units
+const CURSOR_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.5];
+/// Scenery meshes kept at once; a compare run shows at most two scenarios
+const SCENERY_SLOTS: usize = 4;
+
+#[derive(Default)]
+pub struct RenderCache {
+    /// White boid polygon at size 1, drawn once per boid with its own transform and color
+    boids: Option<graphics::MeshBatch>,
+    cursor: Option<graphics::Mesh>,
+    /// Walls, lanes and decorations, with the scenario and world width they were built
+    /// for; None when the scenario has nothing to draw
+    scenery: Vec<(Scenario, f32, Option<graphics::Mesh>)>,
+    /// Text by slot, with the string, scale and color it was laid out from
+    texts: HashMap<&'static str, (String, f32, [f32; 4], graphics::Text)>,
+}
+
+impl RenderCache {
+    /// Instance batch of the boid polygon `points`, emptied for this frame's boids
+    pub fn boids(&mut self, ctx: &mut Context, points: &[glam::Vec2]) -> GameResult<&mut graphics::MeshBatch> {
+        if self.boids.is_none() {
+            let mesh = graphics::Mesh::new_polygon(ctx, graphics::DrawMode::fill(), points, graphics::Color::WHITE)?;
+            self.boids = Some(graphics::MeshBatch::new(mesh)?);
+        }
+        let batch = self.boids.as_mut().unwrap();
+        batch.clear();
+        Ok(batch)
+    }
+    /// Cursor highlight centered on the origin
+    pub fn cursor(&mut self, ctx: &mut Context) -> GameResult<&graphics::Mesh> {
+        if self.cursor.is_none() {
+            let mesh = graphics::Mesh::new_circle(ctx, graphics::DrawMode::fill(), glam::Vec2::ZERO, CURSOR_RADIUS, 0.1, CURSOR_COLOR.into())?;
+            self.cursor = Some(mesh);
+        }
+        Ok(self.cursor.as_ref().unwrap())
+    }
+    /// The walls, lanes, lines and zones of `scenario` in a world `width` wide
+    pub fn scenery(&mut self, ctx: &mut Context, scenario: &Scenario, width: f32) -> GameResult<Option<&graphics::Mesh>> {
+        let index = match self.scenery.iter().position(|(s, w, _)| s == scenario && *w == width) {
+            Some(index) => index,
+            None => {
+                if self.scenery.len() >= SCENERY_SLOTS {
+                    self.scenery.remove(0);
+                }
+                let mesh = build_scenery(ctx, scenario, width)?;
+                self.scenery.push((scenario.clone(), width, mesh));
+                self.scenery.len() - 1
+            }
+        };
+        Ok(self.scenery[index].2.as_ref())
+    }
+    /// `text` at `scale` in `color`, laid out again only when one of them differs
+    /// from what was last asked of `slot`
+    pub fn text(&mut self, slot: &'static str, text: &str, scale: f32, color: [f32; 4]) -> &graphics::Text {
+        let stale = self.texts.get(slot).is_none_or(|(t, s, c, _)| t != text || *s != scale || *c != color);
+        if stale {
+            let built = graphics::Text::new(graphics::TextFragment {
+                text: text.to_string(),
+                color: Some(color.into()),
+                font: Some(graphics::Font::default()),
+                scale: Some(graphics::PxScale::from(scale)),
+            });
+            self.texts.insert(slot, (text.to_string(), scale, color, built));
+        }
+        &self.texts[slot].3
+    }
+}
+
+fn build_scenery(ctx: &mut Context, scenario: &Scenario, width: f32) -> GameResult<Option<graphics::Mesh>> {
+    let mut mb = graphics::MeshBuilder::new();
+    let mut empty = true;
+    for obstacle in &scenario.obstacles {
+        let rect = graphics::Rect::new(obstacle.x, obstacle.y, obstacle.w, obstacle.h);
+        mb.rectangle(graphics::DrawMode::fill(), rect, [0.35, 0.35, 0.4, 1.0].into())?;
+        empty = false;
+    }
+    for decoration in &scenario.decorations {
+        match decoration {
+            Decoration::Line { points, width, color, .. } if points.len() >= 2 => {
+                let points: Vec<glam::Vec2> = points.iter().map(|&p| glam::Vec2::from(p)).collect();
+                mb.polyline(graphics::DrawMode::stroke(*width), &points, (*color).into())?;
+                empty = false;
+            }
+            Decoration::Zone { x, y, w, h, color, .. } => {
+                mb.rectangle(graphics::DrawMode::fill(), graphics::Rect::new(*x, *y, *w, *h), (*color).into())?;
+                empty = false;
+            }
+            _ => {}
+        }
+    }
+    for lane in &scenario.lanes {
+        mb.line(&[glam::vec2(0.0, lane.y), glam::vec2(width, lane.y)], 1.0, [0.9, 0.9, 0.6, 0.15].into())?;
+        empty = false;
+    }
+    if empty { Ok(None) } else { mb.build(ctx).map(Some) }
+}
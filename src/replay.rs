@@ -0,0 +1,411 @@
+//! Replay and saved-state file format.
+//!
+//! All integers and floats are little-endian.
+//!
+//! ```text
+//! file    := magic header chunk*
+//! magic   := "BOIDRPL\0"                      8 bytes
+//! header  := major:u16 minor:u16 len:u32 body  body is `len` bytes
+//! body    := kind:u8 seed:u64 config_hash:u64 boid_count:u32 [fields added by later minors]
+//! chunk   := tag:u8 len:u32 payload            payload is `len` bytes
+//! ```
+//!
+//! `kind` is 0 for a replay and 1 for a single saved state. `config_hash` is
+//! an FNV-1a hash of the world blackboard the run started with.
+//!
+//! Chunk tags:
+//! - 1 keyframe: `dt:f32 count:u32` then per boid `id:u32 x y dx dy:f32 rgba:u8x4`
+//! - 2 delta:    `dt:f32 count:u32` then per boid `Δx Δy Δdx Δdy:f32 rgba:u8x4`,
+//!   relative to the previous frame, boids in keyframe order
+//!
+//! Compatibility: readers reject a newer `major`, but accept any `minor`,
+//! skipping header bytes and chunk tags they don't know. Writers only bump
+//! `minor` for additions that older readers can safely ignore.
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::blackboard::{BBValue, Blackboard};
+use crate::rewind::BoidState;
+
+pub const MAGIC: &[u8; 8] = b"BOIDRPL\0";
+pub const VERSION_MAJOR: u16 = 1;
+pub const VERSION_MINOR: u16 = 0;
+
+const TAG_KEYFRAME: u8 = 1;
+const TAG_DELTA: u8 = 2;
+/// Frames between keyframes in a replay
+const KEYFRAME_INTERVAL: usize = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplayKind {
+    Replay,
+    State,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayHeader {
+    pub version: (u16, u16),
+    pub kind: ReplayKind,
+    pub seed: u64,
+    pub config_hash: u64,
+    pub boid_count: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayFrame {
+    pub dt: f32,
+    pub boids: Vec<BoidState>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u16, u16),
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::BadMagic => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(major, minor) => {
+                write!(f, "replay version {}.{} is newer than supported {}.x", major, minor, VERSION_MAJOR)
+            }
+            ReplayError::Corrupt(what) => write!(f, "corrupt replay: {}", what),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+/// FNV-1a over the sorted world blackboard entries. Colors only change how
+/// boids look, so they are left out and themes can differ.
+pub fn config_hash(world: &Blackboard) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+    let mut keys: Vec<&String> = world.keys().collect();
+    keys.sort();
+    for key in keys {
+        if matches!(world[key], BBValue::Color(_)) {
+            continue;
+        }
+        feed(key.as_bytes());
+        match world[key] {
+            BBValue::F32(v) => feed(&v.to_le_bytes()),
+            BBValue::Vec2(v) => {
+                feed(&v.x.to_le_bytes());
+                feed(&v.y.to_le_bytes());
+            }
+            BBValue::Bool(v) => feed(&[v as u8]),
+            BBValue::Color(_) => {}
+        }
+    }
+    hash
+}
+
+fn color_bytes(color: [f32; 4]) -> [u8; 4] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn color_floats(bytes: [u8; 4]) -> [f32; 4] {
+    bytes.map(|b| b as f32 / 255.0)
+}
+
+/// Streams frames to `out`, writing keyframes periodically and deltas in between
+pub struct ReplayWriter<W: Write> {
+    out: W,
+    /// Frame as the reader will reconstruct it, so deltas never drift
+    previous: Vec<BoidState>,
+    frames: usize,
+}
+
+impl<W: Write> ReplayWriter<W> {
+    pub fn new(mut out: W, header: &ReplayHeader) -> io::Result<ReplayWriter<W>> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION_MAJOR.to_le_bytes())?;
+        out.write_all(&VERSION_MINOR.to_le_bytes())?;
+        let mut body = vec![match header.kind {
+            ReplayKind::Replay => 0u8,
+            ReplayKind::State => 1u8,
+        }];
+        body.extend_from_slice(&header.seed.to_le_bytes());
+        body.extend_from_slice(&header.config_hash.to_le_bytes());
+        body.extend_from_slice(&header.boid_count.to_le_bytes());
+        out.write_all(&(body.len() as u32).to_le_bytes())?;
+        out.write_all(&body)?;
+        Ok(ReplayWriter { out, previous: vec![], frames: 0 })
+    }
+    pub fn write_frame(&mut self, dt: f32, boids: &[BoidState]) -> io::Result<()> {
+        let keyframe = self.frames % KEYFRAME_INTERVAL == 0 || boids.len() != self.previous.len();
+        let mut payload = vec![];
+        payload.extend_from_slice(&dt.to_le_bytes());
+        payload.extend_from_slice(&(boids.len() as u32).to_le_bytes());
+        if keyframe {
+            for b in boids {
+                payload.extend_from_slice(&(b.id as u32).to_le_bytes());
+                for v in [b.x, b.y, b.dx, b.dy] {
+                    payload.extend_from_slice(&v.to_le_bytes());
+                }
+                payload.extend_from_slice(&color_bytes(b.color));
+            }
+            self.previous = boids
+                .iter()
+                .map(|b| BoidState { color: color_floats(color_bytes(b.color)), ..*b })
+                .collect();
+        } else {
+            for (b, prev) in boids.iter().zip(self.previous.iter_mut()) {
+                let deltas = [b.x - prev.x, b.y - prev.y, b.dx - prev.dx, b.dy - prev.dy];
+                for v in deltas {
+                    payload.extend_from_slice(&v.to_le_bytes());
+                }
+                payload.extend_from_slice(&color_bytes(b.color));
+                // apply exactly what the reader will
+                prev.x += deltas[0];
+                prev.y += deltas[1];
+                prev.dx += deltas[2];
+                prev.dy += deltas[3];
+                prev.color = color_floats(color_bytes(b.color));
+            }
+        }
+        self.out.write_all(&[if keyframe { TAG_KEYFRAME } else { TAG_DELTA }])?;
+        self.out.write_all(&(payload.len() as u32).to_le_bytes())?;
+        self.out.write_all(&payload)?;
+        self.frames += 1;
+        Ok(())
+    }
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Little-endian cursor over a byte slice
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ReplayError> {
+        if self.0.len() < n {
+            return Err(ReplayError::Corrupt("unexpected end of data"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+    fn u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, ReplayError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    fn u32(&mut self) -> Result<u32, ReplayError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn u64(&mut self) -> Result<u64, ReplayError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn f32(&mut self) -> Result<f32, ReplayError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn rgba(&mut self) -> Result<[u8; 4], ReplayError> {
+        Ok(self.take(4)?.try_into().unwrap())
+    }
+}
+
+/// Read a whole replay or saved state
+pub fn read<R: Read>(mut input: R) -> Result<(ReplayHeader, Vec<ReplayFrame>), ReplayError> {
+    let mut data = vec![];
+    input.read_to_end(&mut data)?;
+    let mut bytes = Bytes(&data);
+
+    if bytes.take(MAGIC.len())? != MAGIC {
+        return Err(ReplayError::BadMagic);
+    }
+    let major = bytes.u16()?;
+    let minor = bytes.u16()?;
+    if major > VERSION_MAJOR {
+        return Err(ReplayError::UnsupportedVersion(major, minor));
+    }
+    let header_len = bytes.u32()? as usize;
+    let mut body = Bytes(bytes.take(header_len)?);
+    let header = ReplayHeader {
+        version: (major, minor),
+        kind: match body.u8()? {
+            0 => ReplayKind::Replay,
+            1 => ReplayKind::State,
+            _ => return Err(ReplayError::Corrupt("unknown kind")),
+        },
+        seed: body.u64()?,
+        config_hash: body.u64()?,
+        boid_count: body.u32()?,
+    };
+    // anything left in `body` was added by a newer minor version
+
+    let mut frames: Vec<ReplayFrame> = vec![];
+    while !bytes.0.is_empty() {
+        let tag = bytes.u8()?;
+        let len = bytes.u32()? as usize;
+        let mut payload = Bytes(bytes.take(len)?);
+        match tag {
+            TAG_KEYFRAME => {
+                let dt = payload.f32()?;
+                let count = payload.u32()? as usize;
+                let mut boids = Vec::with_capacity(count);
+                for _ in 0..count {
+                    boids.push(BoidState {
+                        id: payload.u32()? as usize,
+                        x: payload.f32()?,
+                        y: payload.f32()?,
+                        dx: payload.f32()?,
+                        dy: payload.f32()?,
+                        color: color_floats(payload.rgba()?),
+                    });
+                }
+                frames.push(ReplayFrame { dt, boids });
+            }
+            TAG_DELTA => {
+                let previous = frames.last().ok_or(ReplayError::Corrupt("delta before keyframe"))?;
+                let dt = payload.f32()?;
+                let count = payload.u32()? as usize;
+                if count != previous.boids.len() {
+                    return Err(ReplayError::Corrupt("delta boid count mismatch"));
+                }
+                let mut boids = previous.boids.clone();
+                for b in &mut boids {
+                    b.x += payload.f32()?;
+                    b.y += payload.f32()?;
+                    b.dx += payload.f32()?;
+                    b.dy += payload.f32()?;
+                    b.color = color_floats(payload.rgba()?);
+                }
+                frames.push(ReplayFrame { dt, boids });
+            }
+            // chunk added by a newer minor version
+            _ => {}
+        }
+    }
+    Ok((header, frames))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(boid_count: u32) -> ReplayHeader {
+        ReplayHeader { version: (VERSION_MAJOR, VERSION_MINOR), kind: ReplayKind::Replay, seed: 42, config_hash: 7, boid_count }
+    }
+
+    /// `count` boids at whole-number positions, so deltas add back exactly
+    fn boids(count: usize, frame: usize) -> Vec<BoidState> {
+        (0..count)
+            .map(|id| {
+                let t = (frame + id) as f32;
+                BoidState { id, x: t, y: 2.0 * t, dx: 1.0, dy: -0.5, color: [1.0, 0.0, 1.0, 1.0] }
+            })
+            .collect()
+    }
+
+    fn write(frames: &[Vec<BoidState>]) -> Vec<u8> {
+        let mut out = vec![];
+        let mut writer = ReplayWriter::new(&mut out, &header(frames[0].len() as u32)).unwrap();
+        for boids in frames {
+            writer.write_frame(0.5, boids).unwrap();
+        }
+        writer.finish().unwrap();
+        out
+    }
+
+    /// Tags of the chunks after the header
+    fn tags(data: &[u8]) -> Vec<u8> {
+        let mut bytes = Bytes(&data[MAGIC.len() + 4..]);
+        let header_len = bytes.u32().unwrap() as usize;
+        bytes.take(header_len).unwrap();
+        let mut tags = vec![];
+        while !bytes.0.is_empty() {
+            tags.push(bytes.u8().unwrap());
+            let len = bytes.u32().unwrap() as usize;
+            bytes.take(len).unwrap();
+        }
+        tags
+    }
+
+    #[test]
+    fn round_trip() {
+        let frames: Vec<_> = (0..5).map(|f| boids(3, f)).collect();
+        let (header, read_back) = read(&write(&frames)[..]).unwrap();
+        assert_eq!(header.version, (VERSION_MAJOR, VERSION_MINOR));
+        assert_eq!(header.kind, ReplayKind::Replay);
+        assert_eq!((header.seed, header.config_hash, header.boid_count), (42, 7, 3));
+        assert_eq!(read_back.len(), frames.len());
+        for (read, written) in read_back.iter().zip(&frames) {
+            assert_eq!(read.dt, 0.5);
+            assert_eq!(&read.boids, written);
+        }
+    }
+
+    #[test]
+    fn keyframes_every_interval_and_on_count_change() {
+        let mut frames: Vec<_> = (0..KEYFRAME_INTERVAL + 2).map(|f| boids(2, f)).collect();
+        frames.push(boids(3, 0));
+        frames.push(boids(3, 1));
+        let tags = tags(&write(&frames));
+        assert_eq!(tags[0], TAG_KEYFRAME);
+        assert!(tags[1..KEYFRAME_INTERVAL].iter().all(|&t| t == TAG_DELTA));
+        assert_eq!(tags[KEYFRAME_INTERVAL], TAG_KEYFRAME);
+        assert_eq!(tags[KEYFRAME_INTERVAL + 1], TAG_DELTA);
+        assert_eq!(tags[KEYFRAME_INTERVAL + 2], TAG_KEYFRAME);
+        assert_eq!(tags[KEYFRAME_INTERVAL + 3], TAG_DELTA);
+    }
+
+    #[test]
+    fn truncated_file_is_corrupt() {
+        let data = write(&[boids(3, 0), boids(3, 1)]);
+        for len in [MAGIC.len() + 2, data.len() - 1] {
+            assert!(matches!(read(&data[..len]), Err(ReplayError::Corrupt(_))));
+        }
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut data = write(&[boids(1, 0)]);
+        data[0] = b'X';
+        assert!(matches!(read(&data[..]), Err(ReplayError::BadMagic)));
+    }
+
+    #[test]
+    fn newer_major_is_rejected() {
+        let mut data = write(&[boids(1, 0)]);
+        data[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(VERSION_MAJOR + 1).to_le_bytes());
+        assert!(matches!(read(&data[..]), Err(ReplayError::UnsupportedVersion(major, _)) if major == VERSION_MAJOR + 1));
+    }
+
+    #[test]
+    fn newer_minor_additions_are_skipped() {
+        let data = write(&[boids(2, 0), boids(2, 1)]);
+        let header_at = MAGIC.len() + 4;
+        let header_len = u32::from_le_bytes(data[header_at..header_at + 4].try_into().unwrap()) as usize;
+        let body_end = header_at + 4 + header_len;
+        // one more header byte and an unknown chunk, as a later minor might add
+        let mut newer = data[..MAGIC.len()].to_vec();
+        newer.extend_from_slice(&VERSION_MAJOR.to_le_bytes());
+        newer.extend_from_slice(&(VERSION_MINOR + 1).to_le_bytes());
+        newer.extend_from_slice(&(header_len as u32 + 1).to_le_bytes());
+        newer.extend_from_slice(&data[header_at + 4..body_end]);
+        newer.push(0xff);
+        newer.extend_from_slice(&[99, 2, 0, 0, 0, 0xab, 0xcd]);
+        newer.extend_from_slice(&data[body_end..]);
+        let (header, frames) = read(&newer[..]).unwrap();
+        assert_eq!(header.version, (VERSION_MAJOR, VERSION_MINOR + 1));
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].boids, boids(2, 1));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::boid::Boid;
+use crate::real::{real, to_f32};
+use crate::simulation::Simulation;
+
+/// Kinematic state of one boid at one tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoidState {
+    pub id: usize,
+    pub x: f32,
+    pub y: f32,
+    pub dx: f32,
+    pub dy: f32,
+    pub color: [f32; 4],
+}
+
+impl BoidState {
+    pub fn capture(boids: &[Boid]) -> Vec<BoidState> {
+        boids
+            .iter()
+            .map(|b| BoidState {
+                id: b.id,
+                x: to_f32(b.x),
+                y: to_f32(b.y),
+                dx: to_f32(b.dx),
+                dy: to_f32(b.dy),
+                color: b.color,
+            })
+            .collect()
+    }
+    /// Overwrite `boid`'s kinematics with this state, id included
+    pub fn apply(&self, boid: &mut Boid) {
+        boid.id = self.id;
+        boid.x = real(self.x);
+        boid.y = real(self.y);
+        boid.dx = real(self.dx);
+        boid.dy = real(self.dy);
+        boid.color = self.color;
+    }
+    /// Overwrite boids in order; only for flocks spawned fresh to `states.len()`
+    pub fn restore(states: &[BoidState], boids: &mut [Boid]) {
+        for (state, boid) in states.iter().zip(boids.iter_mut()) {
+            state.apply(boid);
+        }
+    }
+}
+
+/// One recorded tick; `compare` holds the B side in split-screen mode
+struct Frame {
+    dt: f32,
+    main: Vec<BoidState>,
+    compare: Option<Vec<BoidState>>,
+}
+
+/// Ring buffer of the last few seconds of flock states for scrubbing while paused
+pub struct RewindBuffer {
+    seconds: f32,
+    frames: VecDeque<Frame>,
+    recorded: f32,
+    /// Frame being shown while scrubbing, None when at the live end
+    position: Option<usize>,
+}
+
+impl RewindBuffer {
+    pub fn new(seconds: f32) -> RewindBuffer {
+        RewindBuffer {
+            seconds,
+            frames: VecDeque::new(),
+            recorded: 0.0,
+            position: None,
+        }
+    }
+    /// Bytes the recorded frames hold
+    pub fn bytes(&self) -> usize {
+        let states = |states: &Vec<BoidState>| states.capacity() * std::mem::size_of::<BoidState>();
+        self.frames.capacity() * std::mem::size_of::<Frame>()
+            + self.frames.iter().map(|f| states(&f.main) + f.compare.as_ref().map_or(0, states)).sum::<usize>()
+    }
+    pub fn record(&mut self, dt: f32, main: &[Boid], compare: Option<&[Boid]>) {
+        self.frames.push_back(Frame {
+            dt,
+            main: BoidState::capture(main),
+            compare: compare.map(BoidState::capture),
+        });
+        self.recorded += dt;
+        while self.recorded > self.seconds {
+            match self.frames.pop_front() {
+                Some(frame) => self.recorded -= frame.dt,
+                None => break,
+            }
+        }
+    }
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.recorded = 0.0;
+        self.position = None;
+    }
+    /// Move `steps` frames (negative = back in time) and restore that frame
+    pub fn scrub(&mut self, steps: isize, main: &mut Simulation, compare: Option<&mut Simulation>) {
+        if self.frames.is_empty() {
+            return;
+        }
+        let last = self.frames.len() as isize - 1;
+        let current = self.position.map(|p| p as isize).unwrap_or(last);
+        let target = (current + steps).clamp(0, last) as usize;
+        self.position = Some(target);
+
+        let frame = &self.frames[target];
+        main.restore_states(&frame.main);
+        if let (Some(states), Some(sim)) = (&frame.compare, compare) {
+            sim.restore_states(states);
+        }
+    }
+    /// Drop the frames after the scrub position so ticking resumes from there
+    pub fn resume(&mut self) {
+        if let Some(position) = self.position.take() {
+            while self.frames.len() > position + 1 {
+                if let Some(frame) = self.frames.pop_back() {
+                    self.recorded -= frame.dt;
+                }
+            }
+        }
+    }
+    /// Seconds behind the live end while scrubbing
+    pub fn offset(&self) -> Option<f32> {
+        self.position.map(|p| self.frames.iter().skip(p + 1).map(|f| f.dt).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bonsai_bt::BT;
+
+    use super::*;
+    use crate::boid::BoidBT;
+    use crate::simulation;
+
+    fn flock(count: usize) -> Simulation {
+        let boid_bt: BoidBT = BT::new(Boid::create_bt(), Boid::create_blackboard());
+        let world = simulation::create_world_blackboard(400.0, 300.0);
+        let mut sim = Simulation::new(boid_bt, world, 400.0, 300.0, 3);
+        sim.spawn(count);
+        sim
+    }
+
+    #[test]
+    fn scrubbing_back_past_a_flock_change_restores_every_boid() {
+        let mut sim = flock(6);
+        let mut rewind = RewindBuffer::new(10.0);
+        let recorded = BoidState::capture(&sim.boids);
+        rewind.record(0.1, &sim.boids, None);
+
+        // two boids die, one hatches, and the rest fly on
+        sim.despawn(2);
+        sim.despawn(4);
+        sim.spawn_boid();
+        assert_eq!(sim.boids.len(), 5);
+        for boid in &mut sim.boids {
+            boid.x += real(5.0);
+        }
+        let changed = BoidState::capture(&sim.boids);
+        rewind.record(0.1, &sim.boids, None);
+
+        rewind.scrub(-1, &mut sim, None);
+        assert_eq!(BoidState::capture(&sim.boids), recorded);
+        rewind.scrub(1, &mut sim, None);
+        assert_eq!(BoidState::capture(&sim.boids), changed);
+    }
+}
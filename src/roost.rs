@@ -0,0 +1,41 @@
+//! Day/night clock and the roosts boids settle on overnight
+use rand::Rng;
+
+/// Fraction of the day the sky takes to darken after dusk, and to lighten before dawn
+const TWILIGHT: f32 = 0.05;
+
+/// How far through the day `time` seconds is, from 0 at dawn up to 1
+pub fn time_of_day(time: f32, day_length: f32) -> f32 {
+    if day_length > 0.0 { (time / day_length).fract() } else { 0.0 }
+}
+
+/// Whether `time` falls between dusk (a fraction of the day) and the next dawn.
+/// A zero `day_length` means it is always day.
+pub fn is_night(time: f32, day_length: f32, dusk: f32) -> bool {
+    day_length > 0.0 && time_of_day(time, day_length) >= dusk
+}
+
+/// 0 by day, 1 in the depth of night, fading over the twilight either side
+pub fn darkness(time: f32, day_length: f32, dusk: f32) -> f32 {
+    if !is_night(time, day_length, dusk) {
+        return 0.0;
+    }
+    let t = time_of_day(time, day_length);
+    ((t - dusk) / TWILIGHT).min((1.0 - t) / TWILIGHT).clamp(0.0, 1.0)
+}
+
+/// `count` roosts at random spots well inside the world
+pub fn scatter<R: Rng>(count: usize, width: f32, height: f32, rng: &mut R) -> Vec<glam::Vec2> {
+    (0..count)
+        .map(|_| glam::vec2(
+            (0.2 + rng.gen::<f32>() * 0.6) * width,
+            (0.2 + rng.gen::<f32>() * 0.6) * height))
+        .collect()
+}
+
+pub fn nearest(roosts: &[glam::Vec2], pos: glam::Vec2) -> Option<glam::Vec2> {
+    roosts
+        .iter()
+        .copied()
+        .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+}
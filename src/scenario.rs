@@ -0,0 +1,237 @@
+//! Scenarios: obstacles, lanes, decorations and parameter overrides a world
+//! is set up with. `corridor`, `maze` and `ants` are built in; anything else is read
+//! from a JSON file holding a `Scenario`.
+use std::collections::HashMap;
+use std::fs;
+
+use serde::Deserialize;
+
+/// Solid rectangle boids steer around, in world units
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Obstacle {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Obstacle {
+    /// Point of the rectangle closest to `pos`; `pos` itself when inside
+    pub fn closest_point(&self, pos: glam::Vec2) -> glam::Vec2 {
+        glam::vec2(pos.x.clamp(self.x, self.x + self.w), pos.y.clamp(self.y, self.y + self.h))
+    }
+    pub fn contains(&self, pos: glam::Vec2) -> bool {
+        pos.x > self.x && pos.x < self.x + self.w && pos.y > self.y && pos.y < self.y + self.h
+    }
+    /// Smallest move that takes `pos` out through the nearest side
+    pub fn exit(&self, pos: glam::Vec2) -> glam::Vec2 {
+        let moves = [
+            glam::vec2(self.x - pos.x, 0.0),
+            glam::vec2(self.x + self.w - pos.x, 0.0),
+            glam::vec2(0.0, self.y - pos.y),
+            glam::vec2(0.0, self.y + self.h - pos.y),
+        ];
+        moves.into_iter().min_by(|a, b| a.length_squared().total_cmp(&b.length_squared())).unwrap()
+    }
+}
+
+/// Horizontal lane boids travel along, leaving at one end and coming back in at the other
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Lane {
+    /// Center line, world units from the top
+    pub y: f32,
+    pub half_width: f32,
+    /// 1 for eastbound (left to right), -1 for westbound
+    pub direction: f32,
+}
+
+fn default_width() -> f32 {
+    2.0
+}
+fn default_color() -> [f32; 4] {
+    [1.0, 1.0, 1.0, 1.0]
+}
+fn default_zone_color() -> [f32; 4] {
+    [1.0, 1.0, 1.0, 0.1]
+}
+fn default_label_size() -> f32 {
+    16.0
+}
+
+/// Something drawn over the world for the scenario's sake, tagged by `kind`
+/// in the file. Solid lines and zones are obstacles as well.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Decoration {
+    Line {
+        points: Vec<[f32; 2]>,
+        #[serde(default = "default_width")]
+        width: f32,
+        #[serde(default = "default_color")]
+        color: [f32; 4],
+        #[serde(default)]
+        solid: bool,
+    },
+    Label {
+        x: f32,
+        y: f32,
+        text: String,
+        #[serde(default = "default_label_size")]
+        size: f32,
+        #[serde(default = "default_color")]
+        color: [f32; 4],
+    },
+    Zone {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        #[serde(default = "default_zone_color")]
+        color: [f32; 4],
+        #[serde(default)]
+        solid: bool,
+    },
+}
+
+impl Decoration {
+    /// What boids have to steer round if the decoration is solid: a zone's
+    /// rectangle, or squares `width` across laid along each segment of a line
+    pub fn obstacles(&self) -> Vec<Obstacle> {
+        match self {
+            Decoration::Line { points, width, solid: true, .. } => points
+                .windows(2)
+                .flat_map(|segment| {
+                    let (a, b) = (glam::Vec2::from(segment[0]), glam::Vec2::from(segment[1]));
+                    let steps = (a.distance(b) / width.max(1.0)).ceil() as usize;
+                    (0..=steps).map(move |i| {
+                        let p = a.lerp(b, i as f32 / steps.max(1) as f32);
+                        Obstacle { x: p.x - width / 2.0, y: p.y - width / 2.0, w: *width, h: *width }
+                    })
+                })
+                .collect(),
+            &Decoration::Zone { x, y, w, h, solid: true, .. } => vec![Obstacle { x, y, w, h }],
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+    /// Boids are shared out over these instead of flocking freely
+    #[serde(default)]
+    pub lanes: Vec<Lane>,
+    #[serde(default)]
+    pub decorations: Vec<Decoration>,
+    /// Numeric world parameter overrides
+    #[serde(default)]
+    pub params: HashMap<String, f32>,
+    /// Ant colony the boids forage for: food picked up at the patches is carried back here
+    #[serde(default)]
+    pub nest: Option<[f32; 2]>,
+}
+
+impl Scenario {
+    /// Two corridors between walls, each carrying an eastbound and a westbound
+    /// lane. Boids keep right, so the streams pass each other.
+    pub fn corridor(width: f32, height: f32) -> Scenario {
+        let wall = |top: f32, bottom: f32| Obstacle { x: 0.0, y: top * height, w: width, h: (bottom - top) * height };
+        let lane = |y: f32, direction: f32| Lane { y: y * height, half_width: 0.07 * height, direction };
+        let params = [("day_length", 0.0), ("food_patches", 0.0), ("roost_count", 0.0), ("energy_drain", 0.0)];
+        Scenario {
+            name: "corridor".to_string(),
+            obstacles: vec![wall(0.0, 0.15), wall(0.475, 0.525), wall(0.85, 1.0)],
+            lanes: vec![lane(0.23, -1.0), lane(0.39, 1.0), lane(0.61, -1.0), lane(0.77, 1.0)],
+            decorations: vec![],
+            params: params.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+            nest: None,
+        }
+    }
+    /// Staggered walls reaching in from alternate sides, with a pocket open
+    /// towards the left that boids flying straight at the right side end up in
+    pub fn maze(width: f32, height: f32) -> Scenario {
+        let wall = |x: f32, y: f32, w: f32, h: f32| Obstacle { x: x * width, y: y * height, w: w * width, h: h * height };
+        let params = [("day_length", 0.0), ("food_patches", 0.0), ("roost_count", 0.0), ("energy_drain", 0.0)];
+        Scenario {
+            name: "maze".to_string(),
+            obstacles: vec![
+                wall(0.25, 0.0, 0.04, 0.7),
+                wall(0.5, 0.3, 0.04, 0.7),
+                // the pocket: a bracket open to the left
+                wall(0.7, 0.2, 0.2, 0.04),
+                wall(0.86, 0.2, 0.04, 0.6),
+                wall(0.7, 0.76, 0.2, 0.04),
+            ],
+            lanes: vec![],
+            decorations: vec![Decoration::Label {
+                x: 0.72 * width,
+                y: 0.48 * height,
+                text: "pocket".to_string(),
+                size: default_label_size(),
+                color: [1.0, 1.0, 1.0, 0.5],
+            }],
+            params: params.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+            nest: None,
+        }
+    }
+    /// Ant colony: slow agents that barely flock wander out from a nest, pick
+    /// up food at the patches and carry it home, laying pheromone only on the
+    /// way back, so others find the food along the trails
+    pub fn ants(width: f32, height: f32) -> Scenario {
+        let params = [
+            ("day_length", 0.0), ("roost_count", 0.0), ("energy_drain", 0.0),
+            ("speed_scale", 0.3), ("centering_factor", 0.0), ("matching_factor", 1.0),
+            ("flock_weight", 0.2), ("wander_weight", 1.0), ("forage_weight", 1.0),
+            ("food_patches", 4.0), ("food_capacity", 20.0), ("food_regrow", 0.02),
+            ("trail_deposit", 4.0), ("trail_decay", 0.05), ("trail_factor", 400.0),
+        ];
+        Scenario {
+            name: "ants".to_string(),
+            obstacles: vec![],
+            lanes: vec![],
+            decorations: vec![],
+            params: params.iter().map(|&(k, v)| (k.to_string(), v)).collect(),
+            nest: Some([0.5 * width, 0.5 * height]),
+        }
+    }
+    /// A built-in scenario by name, or one read from a file, with its solid
+    /// decorations added to the obstacles
+    pub fn load(name: &str, width: f32, height: f32) -> Result<Scenario, String> {
+        let mut scenario = match name {
+            "corridor" => Scenario::corridor(width, height),
+            "maze" => Scenario::maze(width, height),
+            "ants" => Scenario::ants(width, height),
+            _ => {
+                let text = fs::read_to_string(name).map_err(|e| format!("{}: {}", name, e))?;
+                serde_json::from_str(&text).map_err(|e| format!("{}: {}", name, e))?
+            }
+        };
+        // the nav grid is built from it, and a grid of zero-sized cells would never fit in memory
+        if let Some(&size) = scenario.params.get("nav_cell_size") {
+            if !(size.is_finite() && size > 0.0) {
+                return Err(format!("{}: nav_cell_size must be a positive number of world units, got {}", name, size));
+            }
+        }
+        let solid: Vec<Obstacle> = scenario.decorations.iter().flat_map(Decoration::obstacles).collect();
+        scenario.obstacles.extend(solid);
+        Ok(scenario)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_positive_nav_cell_size_is_rejected() {
+        let path = std::env::temp_dir().join(format!("smart-boids-scenario-{}.json", std::process::id()));
+        for (size, ok) in [("0", false), ("-4", false), ("8", true)] {
+            fs::write(&path, format!(r#"{{"name": "grid", "params": {{"nav_cell_size": {}}}}}"#, size)).unwrap();
+            let loaded = Scenario::load(path.to_str().unwrap(), 400.0, 300.0);
+            assert_eq!(loaded.is_ok(), ok, "nav_cell_size {}: {:?}", size, loaded.err());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use ggez::graphics;
+
+use crate::boid::Boid;
+
+/// Boids picked with a click-drag rectangle
+#[derive(Default)]
+pub struct Selection {
+    pub ids: HashSet<usize>,
+    drag_start: Option<glam::Vec2>,
+}
+
+/// Drags shorter than this pick the single boid under the cursor
+const CLICK_SIZE: f32 = 4.0; // Pixels
+
+/// Aggregate stats shown in the inspector
+pub struct SelectionStats {
+    pub count: usize,
+    pub centroid: glam::Vec2,
+    pub mean_speed: f32,
+}
+
+/// Id of the boid within `radius` of `at`, the nearest if there are several
+pub fn nearest(boids: &[Boid], at: glam::Vec2, radius: f32) -> Option<usize> {
+    boids
+        .iter()
+        .map(|b| (b.id, b.pos().distance(at)))
+        .filter(|&(_, d)| d < radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, _)| id)
+}
+
+impl Selection {
+    pub fn begin_drag(&mut self, at: glam::Vec2) {
+        self.drag_start = Some(at);
+    }
+    pub fn is_dragging(&self) -> bool {
+        self.drag_start.is_some()
+    }
+    /// Rectangle spanned by the drag so far
+    pub fn drag_rect(&self, cursor: glam::Vec2) -> Option<graphics::Rect> {
+        self.drag_start.map(|start| {
+            let min = start.min(cursor);
+            let max = start.max(cursor);
+            graphics::Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+        })
+    }
+    /// Replace the selection with the boids inside the dragged rectangle, or
+    /// with the boid within `pick_radius` when the drag was just a click
+    pub fn end_drag(&mut self, cursor: glam::Vec2, boids: &[Boid], pick_radius: f32) {
+        if let Some(rect) = self.drag_rect(cursor) {
+            if rect.w < CLICK_SIZE && rect.h < CLICK_SIZE {
+                self.ids = nearest(boids, cursor, pick_radius).into_iter().collect();
+            } else {
+                self.ids = boids
+                    .iter()
+                    .filter(|b| rect.contains(b.pos()))
+                    .map(|b| b.id)
+                    .collect();
+            }
+        }
+        self.drag_start = None;
+    }
+    /// The selected boid when exactly one is selected
+    pub fn single(&self) -> Option<usize> {
+        if self.ids.len() == 1 {
+            self.ids.iter().next().cloned()
+        } else {
+            None
+        }
+    }
+    pub fn contains(&self, id: usize) -> bool {
+        self.ids.contains(&id)
+    }
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.drag_start = None;
+    }
+    pub fn stats(&self, boids: &[Boid]) -> Option<SelectionStats> {
+        let mut count = 0;
+        let mut centroid = glam::Vec2::ZERO;
+        let mut speed = 0.0;
+        for boid in boids.iter().filter(|b| self.contains(b.id)) {
+            count += 1;
+            centroid += boid.pos();
+            speed += boid.vel().length();
+        }
+        if count == 0 {
+            return None;
+        }
+        Some(SelectionStats {
+            count,
+            centroid: centroid / count as f32,
+            mean_speed: speed / count as f32,
+        })
+    }
+}
@@ -0,0 +1,42 @@
+//! Hit feedback: a short screen shake and flash whenever the cursor catches
+//! boids. The shake is a camera offset that dies away over a few frames.
+
+/// Largest camera offset, at full strength, in screen pixels
+const MAX_OFFSET: f32 = 8.0;
+/// Strength lost per second
+const DECAY: f32 = 4.0;
+/// Strength a single catch adds
+const HIT: f32 = 0.5;
+
+#[derive(Default)]
+pub struct Shake {
+    /// 0 to 1; the offset grows with its square so small hits stay subtle
+    strength: f32,
+    /// Flash brightness, 0 to 1
+    pub flash: f32,
+    time: f32,
+    /// Catch count seen last frame
+    seen: usize,
+}
+
+impl Shake {
+    /// Kick off feedback for every catch out of `catches` since the last call,
+    /// and let the current one die away by `dt` seconds
+    pub fn update(&mut self, dt: f32, catches: usize) {
+        let new = catches.saturating_sub(self.seen);
+        self.seen = catches;
+        if new > 0 {
+            self.strength = (self.strength + HIT * new as f32).min(1.0);
+            self.flash = 1.0;
+        }
+        self.time += dt;
+        self.strength = (self.strength - DECAY * dt).max(0.0);
+        self.flash = (self.flash - DECAY * 2.0 * dt).max(0.0);
+    }
+    /// Camera offset for this frame
+    pub fn offset(&self) -> glam::Vec2 {
+        // two out-of-step wobbles, so the camera doesn't just rattle along a line
+        let wobble = glam::vec2((self.time * 73.0).sin(), (self.time * 59.0).cos());
+        wobble * MAX_OFFSET * self.strength * self.strength
+    }
+}
@@ -0,0 +1,837 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use ggez::mint::Point2;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::blackboard::{BBValue, Blackboard, WorldBlackboard, get_bool, get_f32};
+use crate::boid::{Boid, BoidBT, Neighbor};
+use crate::chains;
+use crate::controller::Loop;
+use crate::epidemic::{self, Health};
+use crate::events::EventBus;
+use crate::food::FoodPatches;
+use crate::formation;
+use crate::integrator::Integrator;
+use crate::mates;
+use crate::metrics::{self, Spacing};
+use crate::migration::Migration;
+use crate::pheromone::PheromoneGrid;
+#[cfg(feature = "rapier")]
+use crate::physics::Physics;
+use crate::real::{RVec2, real, vec2_f32};
+use crate::rewind::BoidState;
+use crate::navgrid::{FlowField, NavGrid};
+use crate::opinion;
+use crate::scenario::{Lane, Scenario};
+use crate::roost;
+use crate::theme::Palette;
+
+const TRAIL_CELL_SIZE: f32 = 16.0; // World units
+/// Boids allocated up front unless `capacity` is changed
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Cursor position used when nobody is steering (headless runs)
+pub const NO_CURSOR: Point2<f32> = Point2 { x: -1.0e6, y: -1.0e6 };
+
+/// Default shared parameters for a world of the given size
+pub fn create_world_blackboard(width: f32, height: f32) -> Blackboard {
+    let mut blackboard: Blackboard = HashMap::new();
+    blackboard.insert("win_width".to_string(), BBValue::F32(width));
+    blackboard.insert("win_height".to_string(), BBValue::F32(height));
+    // steering coefficients, as accelerations (per second, or world units per second squared)
+    blackboard.insert("avoid_factor".to_string(), BBValue::F32(30.0));
+    blackboard.insert("centering_factor".to_string(), BBValue::F32(3.0));
+    blackboard.insert("matching_factor".to_string(), BBValue::F32(6.0));
+    blackboard.insert("edge_buffer".to_string(), BBValue::F32(40.0));
+    blackboard.insert("turn_factor".to_string(), BBValue::F32(960.0));
+    blackboard.insert("flee_factor".to_string(), BBValue::F32(2400.0));
+    blackboard.insert("edge_brake".to_string(), BBValue::F32(12.0)); // Per second, outside the edge buffer
+    blackboard.insert("cursor_push".to_string(), BBValue::F32(60.0)); // Per second, away from the cursor
+    blackboard.insert("wind".to_string(), BBValue::Vec2(glam::Vec2::ZERO)); // World units per second squared
+    blackboard.insert("max_force".to_string(), BBValue::F32(3000.0)); // World units per second squared
+    blackboard.insert("max_turn_rate".to_string(), BBValue::F32(540.0)); // Degrees per second
+    blackboard.insert("flash_turn_rate".to_string(), BBValue::F32(360.0)); // Degrees per second
+    // pheromone trails
+    blackboard.insert("trail_factor".to_string(), BBValue::F32(240.0));
+    blackboard.insert("trail_deposit".to_string(), BBValue::F32(1.0));
+    blackboard.insert("trail_decay".to_string(), BBValue::F32(0.5));
+    // branches picked at each decision point, by relative weight
+    blackboard.insert("branch_interval".to_string(), BBValue::F32(2.0)); // Seconds
+    blackboard.insert("flock_weight".to_string(), BBValue::F32(1.0));
+    blackboard.insert("wander_weight".to_string(), BBValue::F32(0.0));
+    blackboard.insert("forage_weight".to_string(), BBValue::F32(0.0));
+    blackboard.insert("wander_factor".to_string(), BBValue::F32(600.0));
+    blackboard.insert("forage_factor".to_string(), BBValue::F32(1200.0));
+    // energy and food; food_patches takes effect on the next spawn
+    blackboard.insert("energy_drain".to_string(), BBValue::F32(0.01)); // Per second
+    blackboard.insert("hunger_threshold".to_string(), BBValue::F32(0.3));
+    blackboard.insert("sated_threshold".to_string(), BBValue::F32(0.9));
+    blackboard.insert("eat_rate".to_string(), BBValue::F32(0.5)); // Per second
+    blackboard.insert("food_factor".to_string(), BBValue::F32(1800.0));
+    blackboard.insert("food_radius".to_string(), BBValue::F32(24.0)); // World units
+    blackboard.insert("food_patches".to_string(), BBValue::F32(3.0));
+    blackboard.insert("food_capacity".to_string(), BBValue::F32(10.0));
+    blackboard.insert("food_regrow".to_string(), BBValue::F32(0.5)); // Per second
+    // ant foraging, in scenarios with a nest; pheromone is only laid carrying food home
+    blackboard.insert("ant_load".to_string(), BBValue::F32(0.1));
+    blackboard.insert("nest_radius".to_string(), BBValue::F32(30.0)); // World units
+    blackboard.insert("nest_factor".to_string(), BBValue::F32(900.0));
+    // every boid's top speed is scaled by this
+    blackboard.insert("speed_scale".to_string(), BBValue::F32(1.0));
+    // growth: food eaten grows a boid until it splits in two with mutated traits
+    blackboard.insert("growth".to_string(), BBValue::Bool(false));
+    blackboard.insert("growth_rate".to_string(), BBValue::F32(0.5)); // Size per unit of food
+    blackboard.insert("split_size".to_string(), BBValue::F32(1.6));
+    blackboard.insert("split_mutation".to_string(), BBValue::F32(0.05));
+    // day and night; boids roost from dusk (a fraction of the day) until dawn
+    blackboard.insert("day_length".to_string(), BBValue::F32(120.0)); // Seconds, 0 for endless day
+    blackboard.insert("dusk".to_string(), BBValue::F32(0.7));
+    blackboard.insert("roost_count".to_string(), BBValue::F32(2.0)); // Takes effect on the next spawn
+    blackboard.insert("roost_radius".to_string(), BBValue::F32(40.0)); // World units
+    blackboard.insert("roost_factor".to_string(), BBValue::F32(4.0)); // Per second
+    blackboard.insert("perch_delay".to_string(), BBValue::F32(6.0)); // Seconds
+    // migration: a target crosses the world until migration_goal of the flock reaches the far edge
+    blackboard.insert("migration".to_string(), BBValue::Bool(false));
+    blackboard.insert("migration_loop".to_string(), BBValue::Bool(true));
+    blackboard.insert("migration_speed".to_string(), BBValue::F32(30.0)); // World units per second
+    blackboard.insert("migration_factor".to_string(), BBValue::F32(300.0));
+    blackboard.insert("migration_goal".to_string(), BBValue::F32(0.8));
+    // formation flight in the upwash behind and beside the boid ahead
+    blackboard.insert("formation".to_string(), BBValue::Bool(false));
+    blackboard.insert("formation_range".to_string(), BBValue::F32(64.0)); // World units
+    blackboard.insert("slot_spacing".to_string(), BBValue::F32(20.0)); // World units
+    blackboard.insert("formation_factor".to_string(), BBValue::F32(4.0)); // Per second
+    // pair bonds: nearby boids pair off and each pair is held together by a damped spring
+    blackboard.insert("mates".to_string(), BBValue::Bool(false));
+    blackboard.insert("mate_range".to_string(), BBValue::F32(48.0)); // World units
+    blackboard.insert("spring_length".to_string(), BBValue::F32(20.0)); // World units
+    blackboard.insert("spring_stiffness".to_string(), BBValue::F32(6.0)); // Per second squared
+    blackboard.insert("spring_damping".to_string(), BBValue::F32(2.0)); // Per second
+    // snake mode: boids link up into chains and only the heads flock
+    blackboard.insert("chains".to_string(), BBValue::Bool(false));
+    blackboard.insert("chain_length".to_string(), BBValue::F32(8.0)); // Segments
+    blackboard.insert("chain_range".to_string(), BBValue::F32(64.0)); // World units
+    blackboard.insert("link_length".to_string(), BBValue::F32(10.0)); // World units
+    // scenario obstacles and lanes
+    blackboard.insert("obstacle_margin".to_string(), BBValue::F32(24.0)); // World units
+    blackboard.insert("obstacle_factor".to_string(), BBValue::F32(2400.0));
+    blackboard.insert("lane_speed".to_string(), BBValue::F32(120.0)); // World units per second
+    blackboard.insert("lane_factor".to_string(), BBValue::F32(3.0)); // Per second
+    blackboard.insert("nav_cell_size".to_string(), BBValue::F32(16.0)); // World units
+    // energy a boid loses each time it runs into an obstacle
+    #[cfg(feature = "rapier")]
+    blackboard.insert("impact_damage".to_string(), BBValue::F32(0.05));
+    // one shared flow field per goal instead of an A* path per boid
+    blackboard.insert("flow_field".to_string(), BBValue::Bool(false));
+    // milling: tangential steering around the flock's centroid
+    blackboard.insert("milling".to_string(), BBValue::Bool(false));
+    // SIR epidemic: one boid falls ill when it is turned on, contact within min_distance passes it on
+    blackboard.insert("epidemic".to_string(), BBValue::Bool(false));
+    blackboard.insert("transmission_rate".to_string(), BBValue::F32(10.0)); // Per second of contact
+    blackboard.insert("recovery_time".to_string(), BBValue::F32(15.0)); // Seconds
+    // opinion dynamics: boids take up the opinion most held among their neighbors
+    blackboard.insert("opinions".to_string(), BBValue::Bool(false));
+    blackboard.insert("opinion_count".to_string(), BBValue::F32(3.0)); // 2 to 6
+    blackboard.insert("opinion_rate".to_string(), BBValue::F32(1.0)); // Per second
+    blackboard.insert("mill_factor".to_string(), BBValue::F32(400.0));
+    // feedback control: matching_factor is retuned to hold polarization at hold_polarization,
+    // centering_factor to hold the mean nearest-neighbor distance at hold_spacing; 0 for off
+    blackboard.insert("hold_polarization".to_string(), BBValue::F32(0.0));
+    blackboard.insert("hold_spacing".to_string(), BBValue::F32(0.0)); // World units
+    blackboard.insert("control_p".to_string(), BBValue::F32(2.0));
+    blackboard.insert("control_i".to_string(), BBValue::F32(0.5));
+    blackboard.insert("control_d".to_string(), BBValue::F32(0.0));
+    // evaluate these rules only every so many milliseconds, 0 for every tick
+    blackboard.insert("separation_cooldown".to_string(), BBValue::F32(0.0));
+    blackboard.insert("cohesion_cooldown".to_string(), BBValue::F32(0.0));
+    blackboard.insert("alignment_cooldown".to_string(), BBValue::F32(0.0));
+    blackboard.insert("trail_cooldown".to_string(), BBValue::F32(0.0));
+    // metrics
+    blackboard.insert("cluster_distance".to_string(), BBValue::F32(32.0));
+    blackboard.insert("collision_distance".to_string(), BBValue::F32(8.0));
+    // swerve from neighbors set to pass within collision_distance this soon
+    blackboard.insert("reaction_time".to_string(), BBValue::F32(0.25)); // Seconds
+    blackboard.insert("dodge_factor".to_string(), BBValue::F32(2400.0));
+    // group commands
+    blackboard.insert("seek_factor".to_string(), BBValue::F32(2400.0));
+    blackboard.insert("goal_radius".to_string(), BBValue::F32(24.0));
+    // color convergence
+    blackboard.insert("color_blend".to_string(), BBValue::Bool(false));
+    blackboard.insert("color_blend_rate".to_string(), BBValue::F32(0.5));
+    blackboard.insert("color_mutation_rate".to_string(), BBValue::F32(0.01));
+    // alarm propagation
+    blackboard.insert("threat_range".to_string(), BBValue::F32(100.0));
+    // the cursor catches boids it gets this close to (0 = never)
+    blackboard.insert("catch_radius".to_string(), BBValue::F32(0.0)); // World units
+    blackboard.insert("hearing_range".to_string(), BBValue::F32(48.0));
+    blackboard.insert("alarm_duration".to_string(), BBValue::F32(1.0));
+    blackboard.insert("alarm_hops".to_string(), BBValue::F32(3.0));
+    // perception noise, standard deviations (0 = perfect senses)
+    blackboard.insert("position_noise".to_string(), BBValue::F32(0.0)); // World units
+    blackboard.insert("velocity_noise".to_string(), BBValue::F32(0.0)); // World units per second
+    // life cycle
+    blackboard.insert("lifespan".to_string(), BBValue::F32(180.0)); // Seconds
+    blackboard.insert("maturity_age".to_string(), BBValue::F32(20.0)); // Seconds
+    // colors of new boids, set by the theme
+    blackboard.insert("palette_low".to_string(), BBValue::Color([128.0 / 255.0; 3]));
+    blackboard.insert("palette_high".to_string(), BBValue::Color([1.0; 3]));
+    blackboard.insert("boid_alpha".to_string(), BBValue::F32(0.5));
+    blackboard
+}
+
+/// Entries milling mode retunes, on top of turning `milling` on: a tighter,
+/// less aligned flock circles instead of streaming off
+pub const MILLING_PRESET: &[(&str, f32)] = &[("centering_factor", 6.0), ("matching_factor", 3.0)];
+
+/// Write `preset` into `world`, returning the values it replaced
+pub fn apply_preset(world: &mut Blackboard, preset: &[(&str, f32)]) -> Vec<(String, f32)> {
+    preset
+        .iter()
+        .map(|&(key, value)| {
+            let previous = get_f32(world, key);
+            world.insert(key.to_string(), BBValue::F32(value));
+            (key.to_string(), previous)
+        })
+        .collect()
+}
+
+/// Parse `key=value` pairs overriding numeric world parameters
+pub fn parse_overrides(args: &[String]) -> Result<Vec<(String, f32)>, String> {
+    args.iter()
+        .map(|arg| {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", arg))?;
+            let value = value
+                .parse()
+                .map_err(|_| format!("invalid value for {}: '{}'", key, value))?;
+            Ok((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Renderer-independent flock state, advanced one tick at a time
+#[derive(Clone)]
+pub struct Simulation {
+    pub width: f32,
+    pub height: f32,
+    pub boids: Vec<Boid>,
+    pub boid_bt: BoidBT,
+    pub world_bb: WorldBlackboard,
+    pub events: EventBus,
+    pub trails: PheromoneGrid,
+    pub food: FoodPatches,
+    pub roosts: Vec<glam::Vec2>,
+    /// Seconds since the flock was spawned, driving the day/night cycle
+    pub clock: f32,
+    /// Under way while the `migration` world entry is on
+    pub migration: Option<Migration>,
+    pub scenario: Scenario,
+    /// Grid goals are planned over when the scenario has obstacles
+    pub nav: NavGrid,
+    /// Obstacle colliders and boid bodies
+    #[cfg(feature = "rapier")]
+    pub physics: Physics,
+    /// Field towards the last goal boids were sent to, with flow_field on
+    pub flow: Option<FlowField>,
+    /// Lane boids that ran off the far end of their lane since the last reset
+    pub passages: usize,
+    /// Feedback loops running for hold_polarization and hold_spacing
+    pub polarization_hold: Option<Loop>,
+    pub spacing_hold: Option<Loop>,
+    pub rng: StdRng,
+    /// Seed the RNG was last seeded with
+    pub seed: u64,
+    pub integrator: Integrator,
+    /// Pairs of boid ids currently closer than the collision distance
+    contacts: HashSet<(usize, usize)>,
+    /// Contacts started since the last reset
+    pub collision_events: usize,
+    /// Boids the cursor caught since the last reset
+    pub catches: usize,
+    /// Boids that grew big enough to split in two since the last reset
+    pub splits: usize,
+    /// Loads of food ants brought back to the nest since the last reset
+    pub delivered: usize,
+    /// Whether the epidemic's first boid has been infected since it was turned on
+    pub outbreak: bool,
+    /// Clock time opinion dynamics were turned on at, while they are
+    pub opinions_since: Option<f32>,
+    /// Seconds it took the flock to first agree on one opinion
+    pub consensus_after: Option<f32>,
+    /// Boid being dragged with the mouse: it stays where it is put and can't be caught
+    pub held: Option<usize>,
+    /// Id given to the next boid hatched
+    next_id: usize,
+    /// Most boids alive at once; `spawn` preallocates storage for this many
+    pub capacity: usize,
+    /// Despawned or preallocated boids waiting to be hatched again
+    spares: Vec<Boid>,
+    /// Every boid as steering sees it, refilled each tick without allocating
+    neighbors: Vec<Neighbor>,
+    /// The same with perception noise added, for one boid at a time
+    noisy: Vec<Neighbor>,
+}
+
+impl Simulation {
+    pub fn new(boid_bt: BoidBT, world: Blackboard, width: f32, height: f32, seed: u64) -> Simulation {
+        Simulation {
+            width,
+            height,
+            boids: vec![],
+            boid_bt,
+            world_bb: Arc::new(RwLock::new(world)),
+            events: EventBus::default(),
+            trails: PheromoneGrid::new(width, height, TRAIL_CELL_SIZE),
+            food: FoodPatches::default(),
+            roosts: vec![],
+            clock: 0.0,
+            migration: None,
+            scenario: Scenario::default(),
+            nav: NavGrid::default(),
+            #[cfg(feature = "rapier")]
+            physics: Physics::default(),
+            flow: None,
+            passages: 0,
+            polarization_hold: None,
+            spacing_hold: None,
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            integrator: Integrator::default(),
+            contacts: HashSet::new(),
+            collision_events: 0,
+            catches: 0,
+            splits: 0,
+            delivered: 0,
+            outbreak: false,
+            opinions_since: None,
+            consensus_after: None,
+            held: None,
+            next_id: 0,
+            capacity: DEFAULT_CAPACITY,
+            spares: vec![],
+            neighbors: vec![],
+            noisy: vec![],
+        }
+    }
+    /// Set the world up for `scenario`, which applies from the next spawn
+    pub fn set_scenario(&mut self, mut scenario: Scenario) {
+        let mut world = self.world_bb.write().unwrap();
+        for (key, value) in &scenario.params {
+            world.insert(key.clone(), BBValue::F32(*value));
+        }
+        let cell_size = get_f32(&world, "nav_cell_size");
+        drop(world);
+        self.nav = NavGrid::build(&scenario.obstacles, self.width, self.height, cell_size, cell_size);
+        self.flow = self.flow.as_ref().map(|field| self.nav.flow_field(field.goal));
+        #[cfg(feature = "rapier")]
+        self.physics.set_obstacles(&scenario.obstacles);
+        // obstacles go into the last scenario's storage rather than a new buffer
+        let mut obstacles = std::mem::take(&mut self.scenario.obstacles);
+        obstacles.clear();
+        obstacles.append(&mut scenario.obstacles);
+        scenario.obstacles = obstacles;
+        self.scenario = scenario;
+    }
+    /// Send boid `index` to `goal`, along a planned path or down a flow field
+    /// if there are obstacles in the way; straight there if there are none or
+    /// no way round is found
+    pub fn send_to(&mut self, index: usize, goal: glam::Vec2) {
+        let flow_field = get_bool(&self.world_bb.read().unwrap(), "flow_field");
+        if flow_field && self.nav.has_obstacles() && self.flow.as_ref().is_none_or(|field| field.goal != goal) {
+            self.flow = Some(self.nav.flow_field(goal));
+        }
+        let boid = &mut self.boids[index];
+        let mut path = match self.nav.has_obstacles() && !flow_field {
+            true => self.nav.find_path(boid.pos(), goal).unwrap_or_else(|| vec![goal]),
+            false => vec![goal],
+        };
+        path.reverse();
+        boid.set_goal(*path.last().unwrap());
+        boid.path = path;
+    }
+    /// Drop `boid` somewhere along lane `index` of the scenario, cruising its way
+    fn place_in_lane(boid: &mut Boid, lane: &Lane, width: f32, speed: f32, rng: &mut StdRng) {
+        boid.x = real(rng.gen::<f32>() * width);
+        boid.y = real(lane.y + (rng.gen::<f32>() * 2.0 - 1.0) * lane.half_width);
+        boid.dx = real(lane.direction * speed);
+        boid.dy = 0.0;
+        boid.join_lane(Some(lane));
+    }
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.seed = seed;
+    }
+    /// Overwrite boid kinematics from recorded states, respawning if the count differs
+    pub fn apply_states(&mut self, states: &[BoidState]) {
+        if self.boids.len() != states.len() {
+            self.spawn(states.len());
+        }
+        BoidState::restore(states, &mut self.boids);
+    }
+    /// Put the flock back as it was at a recorded tick. Boids the tick didn't
+    /// have go back to the pool and ones that have died since are hatched
+    /// again, so every boid gets its own recorded state whatever the flock
+    /// size did in between.
+    pub fn restore_states(&mut self, states: &[BoidState]) {
+        let recorded: HashSet<usize> = states.iter().map(|s| s.id).collect();
+        let mut alive = HashMap::new();
+        for boid in self.boids.drain(..) {
+            // back to the pool first, so they can be hatched as the dead ones
+            if recorded.contains(&boid.id) {
+                alive.insert(boid.id, boid);
+            } else {
+                self.spares.push(boid);
+            }
+        }
+        let palette = Palette::from_world(&self.world_bb.read().unwrap());
+        for state in states {
+            let boid = match alive.remove(&state.id) {
+                Some(boid) => boid,
+                None => match self.spares.pop() {
+                    Some(mut boid) => {
+                        boid.hatch(state.id, self.width, self.height, &palette, &mut self.rng);
+                        boid
+                    }
+                    None => continue,
+                },
+            };
+            self.boids.push(boid);
+            state.apply(self.boids.last_mut().unwrap());
+        }
+    }
+    /// Bring `view` up to this state for drawing while this one steps on. Only
+    /// the boids' trees aren't copied, that being most of a clone's cost, but
+    /// the `inspected` boid's blackboard is.
+    pub fn copy_view(&mut self, view: &mut Simulation, inspected: Option<usize>) {
+        let boids = std::mem::take(&mut self.boids);
+        let spares = std::mem::take(&mut self.spares);
+        let mut view_boids = std::mem::take(&mut view.boids);
+        *view = self.clone();
+        self.boids = boids;
+        self.spares = spares;
+        view_boids.truncate(self.boids.len());
+        let kept = view_boids.len();
+        for (to, from) in view_boids.iter_mut().zip(&mut self.boids) {
+            to.copy_view(from, inspected == Some(from.id));
+        }
+        view_boids.extend(self.boids[kept..].iter().cloned());
+        view.boids = view_boids;
+    }
+    /// Bytes the live and spare boids take up, not counting what their trees allocate
+    pub fn boid_bytes(&self) -> usize {
+        (self.boids.capacity() + self.spares.capacity()) * std::mem::size_of::<Boid>()
+    }
+    pub fn spawn(&mut self, count: usize) {
+        let palette = Palette::from_world(&self.world_bb.read().unwrap());
+        self.boids = Boid::create_boids(
+            &self.boid_bt,
+            count,
+            self.width,
+            self.height,
+            &palette,
+            &mut self.rng);
+        // stagger ages so the first generation doesn't die out together
+        let lifespan = get_f32(&self.world_bb.read().unwrap(), "lifespan");
+        for boid in &mut self.boids {
+            boid.age = self.rng.gen::<f32>() * lifespan;
+        }
+        self.next_id = count;
+
+        // fill the pool so spawning at runtime doesn't allocate
+        self.capacity = self.capacity.max(count);
+        self.boids.reserve(self.capacity - count);
+        self.neighbors.reserve(self.capacity);
+        self.noisy.reserve(self.capacity);
+        self.spares.truncate(self.capacity - count);
+        while self.boids.len() + self.spares.len() < self.capacity {
+            self.spares.push(Boid::new(0, self.width, self.height, self.boid_bt.clone(), &palette, &mut self.rng));
+        }
+        let world = self.world_bb.read().unwrap();
+        let (patches, capacity) = (get_f32(&world, "food_patches").max(0.0) as usize, get_f32(&world, "food_capacity"));
+        self.food.scatter(patches, self.width, self.height, capacity, &mut self.rng);
+        let roosts = get_f32(&world, "roost_count").max(0.0) as usize;
+        self.roosts = roost::scatter(roosts, self.width, self.height, &mut self.rng);
+        self.clock = 0.0;
+        // lanes take turns getting boids
+        let lane_speed = get_f32(&world, "lane_speed");
+        for (i, boid) in self.boids.iter_mut().enumerate() {
+            if let Some(lane) = self.scenario.lanes.get(i % self.scenario.lanes.len().max(1)) {
+                Self::place_in_lane(boid, lane, self.width, lane_speed, &mut self.rng);
+            }
+        }
+    }
+    /// Hatch a boid from the pool at a random spot in the spawn area.
+    /// Returns None when `capacity` boids are already alive.
+    pub fn spawn_boid(&mut self) -> Option<&mut Boid> {
+        if self.boids.len() >= self.capacity {
+            return None;
+        }
+        let mut boid = self.spares.pop()?;
+        let palette = Palette::from_world(&self.world_bb.read().unwrap());
+        boid.hatch(self.next_id, self.width, self.height, &palette, &mut self.rng);
+        let lanes = &self.scenario.lanes;
+        if !lanes.is_empty() {
+            let lane_speed = get_f32(&self.world_bb.read().unwrap(), "lane_speed");
+            Self::place_in_lane(&mut boid, &lanes[self.next_id % lanes.len()], self.width, lane_speed, &mut self.rng);
+        }
+        self.next_id += 1;
+        self.boids.push(boid);
+        self.boids.last_mut()
+    }
+    /// Remove boid `id`, returning it to the pool
+    pub fn despawn(&mut self, id: usize) -> bool {
+        match self.boids.iter().position(|b| b.id == id) {
+            Some(i) => {
+                let boid = self.boids.remove(i);
+                self.spares.push(boid);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Put boid `id` at `pos`, flying at `vel`
+    pub fn place(&mut self, id: usize, pos: glam::Vec2, vel: glam::Vec2) {
+        if let Some(boid) = self.boids.iter_mut().find(|b| b.id == id) {
+            (boid.x, boid.y) = (real(pos.x), real(pos.y));
+            (boid.dx, boid.dy) = (real(vel.x), real(vel.y));
+        }
+    }
+    /// Give every boid the traits of boid `id`, returning how many took them on
+    pub fn propagate_traits(&mut self, id: usize) -> usize {
+        let Some(traits) = self.boids.iter_mut().find(|b| b.id == id).map(|b| b.traits()) else { return 0 };
+        let mut count = 0;
+        for boid in self.boids.iter_mut().filter(|b| b.id != id) {
+            boid.set_traits(traits);
+            count += 1;
+        }
+        count
+    }
+    /// Freeze the boids in `ids` unless all of them are frozen already, in which case thaw them
+    pub fn toggle_frozen(&mut self, ids: &HashSet<usize>) {
+        let selected = || self.boids.iter().filter(|b| ids.contains(&b.id));
+        let freeze = !selected().all(|b| b.frozen);
+        for boid in self.boids.iter_mut().filter(|b| ids.contains(&b.id)) {
+            boid.frozen = freeze;
+        }
+    }
+    pub fn reset(&mut self) {
+        self.spares.append(&mut self.boids);
+        self.held = None;
+        self.events.clear();
+        self.trails.clear();
+        self.food.clear();
+        self.roosts.clear();
+        self.clock = 0.0;
+        self.migration = None;
+        self.flow = None;
+        self.passages = 0;
+        let mut world = self.world_bb.write().unwrap();
+        for hold in [self.polarization_hold.take(), self.spacing_hold.take()].into_iter().flatten() {
+            hold.stop(&mut world);
+        }
+        drop(world);
+        self.contacts.clear();
+        self.collision_events = 0;
+        self.catches = 0;
+        self.splits = 0;
+        self.delivered = 0;
+        self.outbreak = false;
+        self.opinions_since = None;
+        self.consensus_after = None;
+    }
+    pub fn step(&mut self, dt: Duration, cursor: Point2<f32>) {
+        let world = self.world_bb.read().unwrap();
+        let dt = dt.as_secs_f32();
+        let trail_deposit = get_f32(&world, "trail_deposit");
+        let trail_decay = get_f32(&world, "trail_decay");
+        let flash_turn_rate = get_f32(&world, "flash_turn_rate").to_radians();
+
+        self.clock += dt;
+        if !get_bool(&world, "migration") {
+            self.migration = None;
+        } else if self.migration.is_none() {
+            self.migration = Some(Migration::start(&self.boids, self.height));
+        }
+        let migration_target = self.migration.as_ref().filter(|m| !m.finished).map(|m| m.target);
+        let slots = if get_bool(&world, "formation") {
+            formation::assign_slots(&self.boids, get_f32(&world, "formation_range"), get_f32(&world, "slot_spacing"))
+        } else {
+            vec![None; self.boids.len()]
+        };
+        let obstacle_margin = get_f32(&world, "obstacle_margin");
+        let night = roost::is_night(self.clock, get_f32(&world, "day_length"), get_f32(&world, "dusk"));
+        let nest = self.scenario.nest.map(glam::Vec2::from);
+
+        // alarms heard this tick were emitted during the last one
+        self.events.advance();
+        let threat = glam::vec2(cursor.x, cursor.y);
+        for (boid, slot) in self.boids.iter_mut().zip(slots) {
+            if let Some(alarm) = boid.listen(
+                dt,
+                threat,
+                self.events.events(),
+                &world) {
+                self.events.emit(alarm);
+            }
+            boid.metabolize(dt, &mut self.food, &world);
+            if boid.haul(nest, &mut self.food, &world) {
+                self.delivered += 1;
+            }
+            boid.observe_sky(night, &self.roosts);
+            boid.observe_migration(migration_target);
+            boid.observe_formation(slot);
+            boid.observe_obstacles(&self.scenario.obstacles, obstacle_margin);
+            boid.next_waypoint();
+            boid.observe_flow(&self.nav, self.flow.as_ref());
+        }
+        self.food.regrow(dt, get_f32(&world, "food_regrow"), get_f32(&world, "food_capacity"));
+        if get_bool(&world, "mates") {
+            mates::pair(&mut self.boids, get_f32(&world, "mate_range"));
+            mates::pull(&mut self.boids, dt, get_f32(&world, "spring_length"),
+                        get_f32(&world, "spring_stiffness"), get_f32(&world, "spring_damping"));
+        } else {
+            for boid in &mut self.boids {
+                boid.mate = None;
+            }
+        }
+        if get_bool(&world, "epidemic") {
+            let recovery_time = get_f32(&world, "recovery_time");
+            if !self.outbreak {
+                epidemic::infect_one(&mut self.boids, recovery_time, &mut self.rng);
+                self.outbreak = true;
+            } else {
+                epidemic::spread(&mut self.boids, dt, get_f32(&world, "transmission_rate"), recovery_time, &mut self.rng);
+            }
+        } else if self.outbreak {
+            self.outbreak = false;
+            for boid in &mut self.boids {
+                boid.health = Health::Susceptible;
+            }
+        }
+        if get_bool(&world, "opinions") {
+            let count = opinion::count(&world);
+            let since = *self.opinions_since.get_or_insert(self.clock);
+            opinion::assign(&mut self.boids, count, &mut self.rng);
+            opinion::update(&mut self.boids, dt, count, get_f32(&world, "opinion_rate"), &mut self.rng);
+            if self.consensus_after.is_none() && opinion::consensus(&self.boids).is_some() {
+                self.consensus_after = Some(self.clock - since);
+            }
+        } else if self.opinions_since.take().is_some() {
+            self.consensus_after = None;
+            for boid in &mut self.boids {
+                boid.opinion = None;
+            }
+        }
+        let chained = get_bool(&world, "chains");
+        if chained {
+            chains::link(&mut self.boids, get_f32(&world, "chain_length").max(1.0) as usize, get_f32(&world, "chain_range"));
+        } else {
+            for boid in &mut self.boids {
+                boid.leader = None;
+            }
+        }
+
+        self.neighbors.clear();
+        self.neighbors.extend(self.boids.iter().map(Boid::neighbor));
+        for i in 0..(self.boids).len() {
+            // segments are moved along by their chain after everyone else
+            if self.boids[i].frozen || self.boids[i].leader.is_some() {
+                continue;
+            }
+            let boid = &mut self.boids[i];
+            let v0 = RVec2::new(boid.dx, boid.dy);
+            #[cfg(feature = "rapier")]
+            let from = boid.pos();
+            Boid::game_tick(
+                dt,
+                cursor,
+                boid,
+                &self.neighbors,
+                &mut self.noisy,
+                &world,
+                &self.trails,
+                &mut self.rng,
+            );
+            if self.held == Some(boid.id) {
+                (boid.dx, boid.dy) = (0.0, 0.0);
+            }
+
+            //Convert new velocity to postion change
+            let pos = self.integrator.advance(
+                RVec2::new(boid.x, boid.y),
+                v0,
+                RVec2::new(boid.dx, boid.dy),
+                real(dt));
+            boid.x = pos.x;
+            boid.y = pos.y;
+
+            // the move stops at the first obstacle in the way and slides along it
+            #[cfg(feature = "rapier")]
+            {
+                let (to, wall) = self.physics.sweep(from, boid.pos() - from);
+                (boid.x, boid.y) = (real(to.x), real(to.y));
+                if let Some(normal) = wall {
+                    let vel = vec2_f32(RVec2::new(boid.dx, boid.dy));
+                    let vel = vel - normal * vel.dot(normal).min(0.0);
+                    (boid.dx, boid.dy) = (real(vel.x), real(vel.y));
+                }
+            }
+            // anything that still got into an obstacle is put back out and stopped against it
+            for obstacle in &self.scenario.obstacles {
+                if obstacle.contains(boid.pos()) {
+                    let exit = obstacle.exit(boid.pos());
+                    boid.x += real(exit.x);
+                    boid.y += real(exit.y);
+                    if exit.x != 0.0 {
+                        boid.dx = 0.0;
+                    } else {
+                        boid.dy = 0.0;
+                    }
+                }
+            }
+            // lane boids that run off the far end come back in at the near end
+            if let Some(direction) = boid.lane_direction() {
+                let width = real(self.width);
+                if direction > 0.0 && boid.x > width {
+                    boid.x -= width;
+                    self.passages += 1;
+                } else if direction < 0.0 && boid.x < 0.0 {
+                    boid.x += width;
+                    self.passages += 1;
+                }
+            }
+
+            // ants mark only the way from food back to the nest
+            if nest.is_none() || boid.is_carrying() {
+                self.trails.deposit(boid.pos(), trail_deposit * dt);
+            }
+            boid.track_turn(vec2_f32(v0), dt, flash_turn_rate);
+            // boids later in the tick see this one where it has just moved to
+            self.neighbors[i] = boid.neighbor();
+        }
+        self.trails.decay(dt, trail_decay);
+        if chained {
+            chains::follow(&mut self.boids, dt, get_f32(&world, "link_length"));
+        }
+
+        if let Some(migration) = &mut self.migration {
+            migration.advance(dt, &self.boids, self.width, get_f32(&world, "migration_speed"),
+                              get_f32(&world, "migration_goal"), get_bool(&world, "migration_loop"));
+        }
+
+        let lifespan = get_f32(&world, "lifespan");
+        let collision_distance = get_f32(&world, "collision_distance");
+        let catch_radius = get_f32(&world, "catch_radius");
+        let (split_size, split_mutation) = (get_f32(&world, "split_size"), get_f32(&world, "split_mutation"));
+        let growth = get_bool(&world, "growth");
+        #[cfg(feature = "rapier")]
+        let impact_damage = get_f32(&world, "impact_damage");
+        drop(world);
+
+        // old boids and those the cursor caught die and are replaced by hatchlings
+        let mut i = 0;
+        while i < self.boids.len() {
+            if !self.boids[i].frozen {
+                self.boids[i].age += dt;
+            }
+            let caught = self.held != Some(self.boids[i].id) && self.boids[i].pos().distance(threat) < catch_radius;
+            if caught {
+                self.catches += 1;
+            }
+            if caught || self.boids[i].age > lifespan {
+                let id = self.boids[i].id;
+                self.despawn(id);
+                self.spawn_boid();
+            } else {
+                i += 1;
+            }
+        }
+        if growth {
+            self.split_grown(split_size, split_mutation);
+        }
+        #[cfg(feature = "rapier")]
+        self.collide(dt, impact_damage);
+        self.count_collisions(collision_distance);
+        self.regulate(dt);
+    }
+    /// Split every boid grown to `split_size` into two of half its size, side
+    /// by side, each with the parent's traits mutated by up to `mutation`.
+    /// Boids wait at full size while the pool has no room for another.
+    fn split_grown(&mut self, split_size: f32, mutation: f32) {
+        let grown: Vec<usize> = self.boids.iter().filter(|b| b.size >= split_size).map(|b| b.id).collect();
+        for id in grown {
+            if self.boids.len() >= self.capacity {
+                break;
+            }
+            let Some(parent) = self.boids.iter_mut().find(|b| b.id == id) else { continue };
+            parent.grow(0.5);
+            let traits = parent.traits();
+            let energy = get_f32(parent.bt.get_blackboard().get_db(), "energy");
+            let (pos, vel, color) = (parent.pos(), parent.vel(), parent.color);
+            let side = vel.perp().normalize_or_zero() * get_f32(parent.bt.get_blackboard().get_db(), "min_distance") / 2.0;
+            parent.set_traits(traits.mutated(mutation, &mut self.rng));
+            parent.x -= real(side.x);
+            parent.y -= real(side.y);
+            let traits = traits.mutated(mutation, &mut self.rng);
+            let Some(child) = self.spawn_boid() else { break };
+            child.set_traits(traits);
+            child.color = color;
+            (child.x, child.y) = (real(pos.x + side.x), real(pos.y + side.y));
+            (child.dx, child.dy) = (real(vel.x), real(vel.y));
+            child.bt.get_blackboard().get_db().insert("energy".to_string(), BBValue::F32(energy));
+            self.splits += 1;
+        }
+    }
+    /// Run the feedback loops whose targets are set, starting them when a
+    /// target is set and handing the factor back when it is cleared
+    fn regulate(&mut self, dt: f32) {
+        let mut world = self.world_bb.write().unwrap();
+        let gains = [get_f32(&world, "control_p"), get_f32(&world, "control_i"), get_f32(&world, "control_d")];
+        let target = get_f32(&world, "hold_polarization");
+        if target > 0.0 {
+            let hold = self.polarization_hold.get_or_insert_with(|| Loop::start("matching_factor", &world));
+            hold.update(&mut world, target - metrics::polarization(&self.boids), dt, gains);
+        } else if let Some(hold) = self.polarization_hold.take() {
+            hold.stop(&mut world);
+        }
+        let target = get_f32(&world, "hold_spacing");
+        if target > 0.0 {
+            // relative error, so the same gains suit any spacing; too spread out pulls in harder
+            let error = (Spacing::measure(&self.boids).mean - target) / target;
+            let hold = self.spacing_hold.get_or_insert_with(|| Loop::start("centering_factor", &world));
+            hold.update(&mut world, error, dt, gains);
+        } else if let Some(hold) = self.spacing_hold.take() {
+            hold.stop(&mut world);
+        }
+    }
+    /// Count pairs that came into contact this tick
+    /// Bring the boids' bodies up to date and take `damage` energy off each
+    /// boid that ran into an obstacle
+    #[cfg(feature = "rapier")]
+    fn collide(&mut self, dt: f32, damage: f32) {
+        self.physics.sync(&self.boids, dt);
+        for impact in &self.physics.impacts {
+            if let Some(boid) = self.boids.iter_mut().find(|b| b.id == impact.id) {
+                let db = boid.bt.get_blackboard().get_db();
+                let energy = (get_f32(db, "energy") - damage).max(0.0);
+                db.insert("energy".to_string(), BBValue::F32(energy));
+            }
+        }
+    }
+    fn count_collisions(&mut self, distance: f32) {
+        let mut contacts = HashSet::new();
+        for (i, a) in self.boids.iter().enumerate() {
+            for b in &self.boids[i + 1..] {
+                if a.distance(&b.neighbor()) < real(distance) {
+                    contacts.insert((a.id.min(b.id), a.id.max(b.id)));
+                }
+            }
+        }
+        self.collision_events += contacts.difference(&self.contacts).count();
+        self.contacts = contacts;
+    }
+}
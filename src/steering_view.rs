@@ -0,0 +1,55 @@
+use ggez::{GameResult, graphics};
+
+use crate::boid::{Boid, Steering};
+use crate::locale::tr;
+use crate::velocity_field::arrow;
+
+/// Length of the arrow for a force of `max_force`, in pixels
+const ARROW_LENGTH: f32 = 80.0;
+
+struct Rule {
+    name: &'static str,
+    /// Arrow color, and its name for the legend
+    color: ([f32; 4], &'static str),
+    force: fn(&Steering) -> glam::Vec2,
+}
+
+const RULES: [Rule; 18] = [
+    Rule { name: "separation", color: ([1.0, 0.3, 0.3, 1.0], "red"), force: |s| s.separation },
+    Rule { name: "cohesion", color: ([0.3, 1.0, 0.3, 1.0], "green"), force: |s| s.cohesion },
+    Rule { name: "alignment", color: ([0.3, 0.5, 1.0, 1.0], "blue"), force: |s| s.alignment },
+    Rule { name: "bounds", color: ([1.0, 0.9, 0.2, 1.0], "yellow"), force: |s| s.bounds },
+    Rule { name: "cursor", color: ([1.0, 1.0, 1.0, 1.0], "white"), force: |s| s.cursor },
+    Rule { name: "seek goal", color: ([1.0, 0.4, 1.0, 1.0], "magenta"), force: |s| s.goal },
+    Rule { name: "trails", color: ([1.0, 0.6, 0.2, 1.0], "orange"), force: |s| s.trail },
+    Rule { name: "flee", color: ([0.3, 1.0, 1.0, 1.0], "cyan"), force: |s| s.flee },
+    Rule { name: "dodge", color: ([1.0, 0.6, 0.8, 1.0], "pink"), force: |s| s.dodge },
+    Rule { name: "wander", color: ([0.7, 0.7, 0.7, 1.0], "gray"), force: |s| s.wander },
+    Rule { name: "forage", color: ([0.6, 0.4, 0.2, 1.0], "brown"), force: |s| s.forage },
+    Rule { name: "seek food", color: ([0.6, 0.9, 0.2, 1.0], "lime"), force: |s| s.food },
+    Rule { name: "roost", color: ([0.6, 0.3, 1.0, 1.0], "purple"), force: |s| s.roost },
+    Rule { name: "migrate", color: ([0.2, 0.8, 0.7, 1.0], "teal"), force: |s| s.migration },
+    Rule { name: "formation", color: ([0.85, 0.75, 0.55, 1.0], "tan"), force: |s| s.formation },
+    Rule { name: "mill", color: ([0.8, 0.5, 1.0, 1.0], "lavender"), force: |s| s.mill },
+    Rule { name: "obstacles", color: ([0.9, 0.3, 0.1, 1.0], "rust"), force: |s| s.obstacle },
+    Rule { name: "lane", color: ([0.9, 0.9, 0.6, 1.0], "cream"), force: |s| s.lane },
+];
+
+/// One arrow per rule from the boid, scaled so `max_force` is `ARROW_LENGTH` long
+pub fn draw_arrows(mb: &mut graphics::MeshBuilder, boid: &Boid, max_force: f32) -> GameResult {
+    let pos = boid.pos();
+    for rule in &RULES {
+        let force = (rule.force)(&boid.steering);
+        let len = (force.length() / max_force).min(1.0) * ARROW_LENGTH;
+        arrow(mb, pos, pos + force.normalize_or_zero() * len, rule.color.0)?;
+    }
+    Ok(())
+}
+
+/// Legend for the arrows with each rule's force this tick
+pub fn force_lines(steering: &Steering) -> Vec<String> {
+    RULES
+        .iter()
+        .map(|rule| format!("  {:<10} {:<8} {:.0}", tr(rule.name), tr(rule.color.1), (rule.force)(steering).length()))
+        .collect()
+}
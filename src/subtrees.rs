@@ -0,0 +1,144 @@
+//! Behavior fragments the boid tree is composed from. Each returns a
+//! subtree that can be dropped into a `While` body or a `Sequence`.
+use bonsai_bt::{Action, AlwaysSucceed, Behavior, Invert, Select, Sequence, WaitForever, WhenAll, While};
+
+use crate::boid::{BoidAction, BoidCondition};
+
+fn check(condition: BoidCondition) -> Behavior<BoidAction> {
+    Action(BoidAction::Check(condition))
+}
+
+/// Run `action` only when `condition` holds; succeeds either way
+fn guarded(condition: BoidCondition, action: BoidAction) -> Behavior<BoidAction> {
+    AlwaysSucceed(Box::new(Sequence(vec![check(condition), Action(action)])))
+}
+
+/// Succeeds while nothing calls for an emergency response
+pub fn calm() -> Behavior<BoidAction> {
+    Invert(Box::new(Select(vec![
+        check(BoidCondition::PredatorNear),
+        check(BoidCondition::CollisionImminent),
+    ])))
+}
+
+/// Succeeds unless the boid is hungry
+pub fn sated() -> Behavior<BoidAction> {
+    Invert(Box::new(check(BoidCondition::Hungry)))
+}
+
+/// Succeeds unless the boid is carrying food to the nest
+pub fn empty_handed() -> Behavior<BoidAction> {
+    Invert(Box::new(check(BoidCondition::Carrying)))
+}
+
+/// Succeeds until dusk
+pub fn daytime() -> Behavior<BoidAction> {
+    Invert(Box::new(check(BoidCondition::Night)))
+}
+
+/// Separate from neighbors inside the boid's personal space
+pub fn keep_apart() -> Behavior<BoidAction> {
+    guarded(BoidCondition::Crowded, BoidAction::AvoidOthers)
+}
+
+/// Run directly away from the remembered threat point
+pub fn flee_from_point() -> Behavior<BoidAction> {
+    guarded(BoidCondition::PredatorNear, BoidAction::Flee)
+}
+
+/// Head for the goal point, if the boid has one
+pub fn steer_to_point() -> Behavior<BoidAction> {
+    guarded(BoidCondition::HasGoal, BoidAction::SeekGoal)
+}
+
+/// Head for the migration target while one is under way
+pub fn migrate() -> Behavior<BoidAction> {
+    guarded(BoidCondition::Migrating, BoidAction::Migrate)
+}
+
+/// Circle the flock's centroid in milling mode
+pub fn mill() -> Behavior<BoidAction> {
+    guarded(BoidCondition::Milling, BoidAction::Mill)
+}
+
+/// Steer clear of obstacles, turn back from the edges and get out from under the cursor
+pub fn stay_in_bounds() -> Behavior<BoidAction> {
+    Sequence(vec![
+        guarded(BoidCondition::NearObstacle, BoidAction::AvoidObstacles),
+        guarded(BoidCondition::OutOfBounds, BoidAction::KeepWithinBounds),
+        guarded(BoidCondition::CursorClose, BoidAction::AvoidCursor),
+    ])
+}
+
+/// Swerve from an imminent collision, then flee any threat
+pub fn evade() -> Behavior<BoidAction> {
+    Sequence(vec![guarded(BoidCondition::CollisionImminent, BoidAction::Dodge), flee_from_point()])
+}
+
+/// Pick a branch at the decision point, then run the first whose condition
+/// holds. Boids in formation hold their slot; the rest match the flock's
+/// velocity.
+pub fn choose_branch() -> Behavior<BoidAction> {
+    Sequence(vec![
+        Action(BoidAction::PickBranch),
+        Select(vec![
+            Sequence(vec![check(BoidCondition::InFormation), Action(BoidAction::HoldSlot)]),
+            Sequence(vec![check(BoidCondition::Wandering), Action(BoidAction::Wander)]),
+            Sequence(vec![check(BoidCondition::Foraging), Action(BoidAction::Forage)]),
+            Action(BoidAction::MatchVelocity),
+        ]),
+    ])
+}
+
+/// While hungry and calm, leave the flock for the nearest food patch,
+/// keeping clear of the other boids feeding there
+pub fn feed() -> Behavior<BoidAction> {
+    let hungry = WhenAll(vec![calm(), check(BoidCondition::Hungry), Action(BoidAction::AvoidOthers)]);
+    While(Box::new(hungry), vec![Action(BoidAction::SeekFood), stay_in_bounds(), move_and_limit()])
+}
+
+/// While carrying food and calm, make straight for the nest, keeping clear
+/// of the other ants on the way
+pub fn carry_home() -> Behavior<BoidAction> {
+    let carrying = WhenAll(vec![calm(), check(BoidCondition::Carrying), Action(BoidAction::AvoidOthers)]);
+    While(Box::new(carrying), vec![Action(BoidAction::ReturnToNest), Action(BoidAction::FollowTrail),
+                                   stay_in_bounds(), move_and_limit()])
+}
+
+/// Through the night, fly to the nearest roost, circle it for a while and then
+/// perch, until dawn or an emergency
+pub fn roost() -> Behavior<BoidAction> {
+    // perched boids hold their spots rather than spreading out
+    let spread = AlwaysSucceed(Box::new(Sequence(vec![
+        Invert(Box::new(check(BoidCondition::Perched))),
+        Action(BoidAction::AvoidOthers),
+    ])));
+    let night = WhenAll(vec![calm(), check(BoidCondition::Night), spread, WaitForever]);
+    let settle = Select(vec![
+        Sequence(vec![check(BoidCondition::Perched), Action(BoidAction::Perch)]),
+        Sequence(vec![check(BoidCondition::AtRoost), Action(BoidAction::CircleRoost)]),
+        Action(BoidAction::Arrive),
+    ]);
+    While(Box::new(night), vec![settle, stay_in_bounds(), move_and_limit()])
+}
+
+/// Boids given a lane cruise along it instead of flocking, keeping clear
+/// of oncoming boids and the walls; lanes run off the edges, so no bounds
+pub fn follow_lane() -> Behavior<BoidAction> {
+    let in_lane = WhenAll(vec![calm(), check(BoidCondition::InLane), Action(BoidAction::AvoidOthers)]);
+    While(Box::new(in_lane), vec![
+        Action(BoidAction::KeepLane),
+        guarded(BoidCondition::NearObstacle, BoidAction::AvoidObstacles),
+        move_and_limit(),
+    ])
+}
+
+/// Apply the accumulated force, then cap turning and speed. Ends Running,
+/// so it goes last.
+pub fn move_and_limit() -> Behavior<BoidAction> {
+    Sequence(vec![
+        Action(BoidAction::ApplyForce),
+        Action(BoidAction::LimitTurnRate),
+        Action(BoidAction::LimitSpeed),
+    ])
+}
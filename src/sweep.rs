@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::batch;
+use crate::blackboard::BBValue;
+use crate::integrator::Integrator;
+use crate::simulation;
+use crate::{WORLD_HEIGHT, WORLD_WIDTH};
+
+/// Sweep description read from a JSON file, e.g.
+/// `{"ticks": 600, "runs": 3, "output": "sweep.csv", "integrator": "verlet",
+///   "params": {"centering_factor": [1.0, 3.0], "matching_factor": [3.0, 6.0]}}`
+#[derive(Deserialize)]
+pub struct SweepConfig {
+    #[serde(default = "default_ticks")]
+    pub ticks: usize,
+    #[serde(default = "default_runs")]
+    pub runs: usize,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default = "default_output")]
+    pub output: String,
+    /// By name, as for `--batch`
+    #[serde(default, deserialize_with = "integrator_name")]
+    pub integrator: Integrator,
+    /// World blackboard entry -> values to try
+    pub params: BTreeMap<String, Vec<f32>>,
+}
+
+fn default_ticks() -> usize { 1000 }
+fn default_runs() -> usize { 1 }
+fn default_output() -> String { "sweep.csv".to_string() }
+
+fn integrator_name<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Integrator, D::Error> {
+    Integrator::parse(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+impl SweepConfig {
+    pub fn load(path: &str) -> Result<SweepConfig, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let config: SweepConfig = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        let world = simulation::create_world_blackboard(WORLD_WIDTH, WORLD_HEIGHT);
+        for (key, values) in &config.params {
+            if !matches!(world.get(key), Some(BBValue::F32(_))) {
+                return Err(format!("unknown numeric parameter '{}'", key));
+            }
+            if values.is_empty() {
+                return Err(format!("no values given for '{}'", key));
+            }
+        }
+        Ok(config)
+    }
+    /// Every combination of parameter values
+    fn combinations(&self) -> Vec<Vec<(String, f32)>> {
+        let mut combos = vec![vec![]];
+        for (key, values) in &self.params {
+            combos = combos
+                .iter()
+                .flat_map(|combo: &Vec<(String, f32)>| values.iter().map(move |v| {
+                    let mut next = combo.clone();
+                    next.push((key.clone(), *v));
+                    next
+                }))
+                .collect();
+        }
+        combos
+    }
+}
+
+/// Run every combination `runs` times and write one CSV row per run
+pub fn run(config: &SweepConfig) -> std::io::Result<()> {
+    let mut out = fs::File::create(&config.output)?;
+    let names: Vec<&str> = config.params.keys().map(String::as_str).collect();
+    writeln!(out, "{},run,seed,polarization,angular_momentum,mean_speed,clusters,collisions,nn_min,nn_mean,nn_median,nn_p90",
+             names.join(","))?;
+
+    let combos = config.combinations();
+    for (i, combo) in combos.iter().enumerate() {
+        eprintln!("sweep: combination {}/{}", i + 1, combos.len());
+        let values: Vec<String> = combo.iter().map(|(_, v)| v.to_string()).collect();
+        for r in 0..config.runs {
+            let seed = config.seed + r as u64;
+            let result = batch::run_once(config.ticks, seed, config.integrator, combo);
+            let spacing = result.spacing;
+            writeln!(out, "{},{},{},{},{},{},{},{},{},{},{},{}",
+                     values.join(","), r, seed,
+                     result.polarization, result.angular_momentum, result.mean_speed, result.clusters, result.collisions,
+                     spacing.min, spacing.mean, spacing.median, spacing.p90)?;
+        }
+    }
+    eprintln!("sweep: wrote {}", config.output);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<SweepConfig, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn integrator_is_read_by_name() {
+        assert_eq!(parse(r#"{"params": {}}"#).unwrap().integrator, Integrator::default());
+        let config = parse(r#"{"integrator": "verlet", "params": {}}"#).unwrap();
+        assert_eq!(config.integrator, Integrator::VelocityVerlet);
+        assert!(parse(r#"{"integrator": "rk4", "params": {}}"#).is_err());
+    }
+}
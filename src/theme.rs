@@ -0,0 +1,95 @@
+use std::fs;
+
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::blackboard::{BBValue, Blackboard, get_color, get_f32, set};
+
+/// Optional user themes, added to the built-in ones (replacing any with the same name)
+const THEME_FILE: &str = "themes.json";
+
+/// Range boid colors are drawn from
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Palette {
+    pub low: [f32; 3],
+    pub high: [f32; 3],
+    pub alpha: f32,
+}
+
+impl Palette {
+    /// Palette stored in the world blackboard
+    pub fn from_world(world: &Blackboard) -> Palette {
+        Palette {
+            low: get_color(world, "palette_low"),
+            high: get_color(world, "palette_high"),
+            alpha: get_f32(world, "boid_alpha"),
+        }
+    }
+    /// Write the palette into the world blackboard
+    pub fn store(&self, world: &mut Blackboard) {
+        set(world, "palette_low", BBValue::Color(self.low));
+        set(world, "palette_high", BBValue::Color(self.high));
+        set(world, "boid_alpha", BBValue::F32(self.alpha));
+    }
+    /// A random color inside the palette; always three draws from `rng`
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> [f32; 4] {
+        let mut channel = |i: usize| self.low[i] + rng.gen::<f32>() * (self.high[i] - self.low[i]);
+        [channel(0), channel(1), channel(2), self.alpha]
+    }
+}
+
+/// Colors of text and panels drawn over the playfield
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct HudColors {
+    pub text: [f32; 4],
+    pub panel: [f32; 4],
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: [f32; 4],
+    pub palette: Palette,
+    pub hud: HudColors,
+}
+
+pub fn builtin() -> Vec<Theme> {
+    vec![
+        Theme {
+            name: "classic".to_string(),
+            background: [0.15, 0.2, 0.22, 1.0],
+            palette: Palette { low: [128.0 / 255.0; 3], high: [1.0; 3], alpha: 0.5 },
+            hud: HudColors { text: [1.0, 1.0, 1.0, 1.0], panel: [0.0, 0.0, 0.0, 0.5] },
+        },
+        Theme {
+            name: "night".to_string(),
+            background: [0.02, 0.03, 0.08, 1.0],
+            palette: Palette { low: [0.2, 0.5, 0.7], high: [0.5, 0.9, 1.0], alpha: 0.7 },
+            hud: HudColors { text: [0.7, 0.85, 1.0, 1.0], panel: [0.0, 0.05, 0.15, 0.6] },
+        },
+        Theme {
+            name: "paper".to_string(),
+            background: [0.93, 0.91, 0.86, 1.0],
+            palette: Palette { low: [0.1, 0.1, 0.15], high: [0.5, 0.35, 0.3], alpha: 0.8 },
+            hud: HudColors { text: [0.1, 0.1, 0.1, 1.0], panel: [1.0, 1.0, 1.0, 0.6] },
+        },
+    ]
+}
+
+/// Built-in themes plus any from `themes.json` (a list of themes)
+pub fn load() -> Result<Vec<Theme>, String> {
+    let mut themes = builtin();
+    let text = match fs::read_to_string(THEME_FILE) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(themes),
+        Err(e) => return Err(format!("{}: {}", THEME_FILE, e)),
+    };
+    let custom: Vec<Theme> = serde_json::from_str(&text).map_err(|e| format!("{}: {}", THEME_FILE, e))?;
+    for theme in custom {
+        match themes.iter_mut().find(|t| t.name == theme.name) {
+            Some(existing) => *existing = theme,
+            None => themes.push(theme),
+        }
+    }
+    Ok(themes)
+}
@@ -0,0 +1,159 @@
+//! Human-readable run transcript for reviewing behavior changes with a plain
+//! text diff. A headless run prints a line whenever boids hatch or die and a
+//! metrics line every few ticks, rounded so float noise doesn't show up.
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::Duration;
+
+use bonsai_bt::BT;
+
+use crate::blackboard::get_f32;
+use crate::boid::{Boid, BoidBT};
+use crate::integrator::Integrator;
+use crate::metrics::Metrics;
+use crate::replay;
+use crate::scenario::Scenario;
+use crate::simulation::{self, NO_CURSOR, Simulation};
+use crate::{OBJECT_COUNT, WORLD_HEIGHT, WORLD_WIDTH};
+
+const TRANSCRIPT_DT: f64 = 1.0 / 60.0; // Seconds
+
+/// Options for `--transcript [ticks=N] [seed=S] [every=K] [integrator=NAME] [scenario=NAME|FILE] [output=FILE]`
+pub struct TranscriptConfig {
+    pub ticks: usize,
+    pub seed: u64,
+    /// Ticks between metrics lines
+    pub every: usize,
+    pub integrator: Integrator,
+    /// Scenario to set the world up with, see `Scenario::load`
+    pub scenario: Option<String>,
+    /// File to write, stdout if not given
+    pub output: Option<String>,
+}
+
+impl TranscriptConfig {
+    pub fn parse(args: &[String]) -> Result<TranscriptConfig, String> {
+        let mut config = TranscriptConfig {
+            ticks: 600,
+            seed: 0,
+            every: 60,
+            integrator: Integrator::default(),
+            scenario: None,
+            output: None,
+        };
+        for arg in args {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", arg))?;
+            let bad = |_| format!("invalid value for {}: '{}'", key, value);
+            match key {
+                "ticks" => config.ticks = value.parse().map_err(bad)?,
+                "seed" => config.seed = value.parse().map_err(bad)?,
+                "every" => config.every = value.parse().map_err(bad)?,
+                "integrator" => config.integrator = Integrator::parse(value)?,
+                "scenario" => config.scenario = Some(value.to_string()),
+                "output" => config.output = Some(value.to_string()),
+                _ => return Err(format!("unknown transcript option '{}'", key)),
+            }
+        }
+        if config.every == 0 {
+            return Err("every must be at least 1".to_string());
+        }
+        Ok(config)
+    }
+}
+
+fn id_list(ids: &[usize]) -> String {
+    ids.iter().map(|id| format!("#{}", id)).collect::<Vec<String>>().join(" ")
+}
+
+/// Simulate headless and write the transcript
+pub fn run(config: &TranscriptConfig) -> io::Result<()> {
+    let mut out: Box<dyn Write> = match &config.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    let boid_bt: BoidBT = BT::new(Boid::create_bt(), Boid::create_blackboard());
+    let world = simulation::create_world_blackboard(WORLD_WIDTH, WORLD_HEIGHT);
+    let mut sim = Simulation::new(boid_bt, world, WORLD_WIDTH, WORLD_HEIGHT, config.seed);
+    sim.integrator = config.integrator;
+    if let Some(name) = &config.scenario {
+        let scenario = Scenario::load(name, WORLD_WIDTH, WORLD_HEIGHT)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        sim.set_scenario(scenario);
+    }
+    let config_hash = replay::config_hash(&sim.world_bb.read().unwrap());
+    sim.spawn(OBJECT_COUNT);
+    let cluster_distance = get_f32(&sim.world_bb.read().unwrap(), "cluster_distance");
+
+    writeln!(out, "# smart-boids transcript")?;
+    writeln!(out, "# seed {}  boids {}  ticks {}  dt {:.6}  {:?}  config {:016x}",
+             config.seed, OBJECT_COUNT, config.ticks, TRANSCRIPT_DT, config.integrator, config_hash)?;
+    let dt = Duration::from_secs_f64(TRANSCRIPT_DT);
+    let mut alive: HashSet<usize> = sim.boids.iter().map(|b| b.id).collect();
+    let (mut collisions, mut alarms, mut crossings, mut passages, mut delivered) = (0, 0, 0, 0, 0);
+    let mut milling = false;
+    let mut consensus = false;
+    for tick in 1..=config.ticks {
+        sim.step(dt, NO_CURSOR);
+        alarms += sim.events.events().len();
+        if let Some(migration) = &sim.migration {
+            if migration.crossings > crossings {
+                crossings = migration.crossings;
+                writeln!(out, "{:>6}  migration crossing {}", tick, crossings)?;
+            }
+            if migration.finished {
+                writeln!(out, "{:>6}  migration finished", tick)?;
+                break;
+            }
+        }
+
+        let now: HashSet<usize> = sim.boids.iter().map(|b| b.id).collect();
+        let mut died: Vec<usize> = alive.difference(&now).copied().collect();
+        let mut hatched: Vec<usize> = now.difference(&alive).copied().collect();
+        died.sort_unstable();
+        hatched.sort_unstable();
+        if !died.is_empty() {
+            writeln!(out, "{:>6}  died {}", tick, id_list(&died))?;
+        }
+        if !hatched.is_empty() {
+            writeln!(out, "{:>6}  hatched {}", tick, id_list(&hatched))?;
+        }
+        alive = now;
+
+        if let Some(after) = sim.consensus_after.filter(|_| !consensus) {
+            consensus = true;
+            writeln!(out, "{:>6}  consensus after {:.2}s", tick, after)?;
+        }
+
+        let metrics = Metrics::measure(&sim.boids, cluster_distance);
+        if metrics.is_milling() != milling {
+            milling = !milling;
+            writeln!(out, "{:>6}  milling {}", tick, if milling { "started" } else { "stopped" })?;
+        }
+
+        if tick % config.every == 0 {
+            write!(out, "{:>6}  boids {}  polarization {:.3}  angular momentum {:.3}  speed {:.1}  clusters {}  \
+                         collisions +{}  alarms +{}",
+                   tick, sim.boids.len(), metrics.polarization, metrics.milling, metrics.mean_speed, metrics.clusters,
+                   sim.collision_events - collisions, alarms)?;
+            if !sim.scenario.lanes.is_empty() {
+                write!(out, "  passages +{}", sim.passages - passages)?;
+                passages = sim.passages;
+            }
+            if sim.scenario.nest.is_some() {
+                write!(out, "  delivered +{}", sim.delivered - delivered)?;
+                delivered = sim.delivered;
+            }
+            if sim.outbreak {
+                let [s, i, r] = metrics.health;
+                write!(out, "  susceptible {}  infected {}  recovered {}", s, i, r)?;
+            }
+            writeln!(out)?;
+            collisions = sim.collision_events;
+            alarms = 0;
+        }
+    }
+    out.flush()
+}
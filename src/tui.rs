@@ -0,0 +1,290 @@
+//! Terminal renderer: steps the same `Simulation` as the window and draws it
+//! with ANSI escapes, so the flock can be watched over SSH. Each character
+//! cell shows the heading of a boid in it as an arrow, or with `style=braille`
+//! a 2x4 dot plot of every boid.
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bonsai_bt::BT;
+
+use crate::blackboard::get_f32;
+use crate::boid::{Boid, BoidBT};
+use crate::locale::{fill, tr};
+use crate::metrics::Metrics;
+use crate::render::Renderer;
+use crate::simulation::{self, NO_CURSOR, Simulation};
+use crate::{OBJECT_COUNT, WORLD_HEIGHT, WORLD_WIDTH};
+
+const TUI_DT: f64 = 1.0 / 30.0; // Seconds
+/// Rows under the grid kept for the overlay
+const STATUS_ROWS: usize = 1;
+const ARROWS: [char; 8] = ['→', '↘', '↓', '↙', '←', '↖', '↑', '↗'];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TuiStyle {
+    Arrows,
+    Braille,
+}
+
+/// Options for `--tui [style=arrows|braille] [seed=S] [ticks=N] [size=COLSxROWS]`
+pub struct TuiConfig {
+    pub style: TuiStyle,
+    pub seed: u64,
+    /// Stop after this many ticks, 0 to run until interrupted
+    pub ticks: usize,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+/// Set by ctrl-c so the loop can stop and put the terminal back itself
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Shows the cursor again and resets the colors when dropped, so the terminal
+/// is left usable however the run ends, a panic included
+struct RestoreTerminal;
+
+impl RestoreTerminal {
+    fn new(out: &mut impl Write) -> io::Result<RestoreTerminal> {
+        // clear the screen and hide the cursor
+        write!(out, "\x1b[2J\x1b[?25l")?;
+        #[cfg(unix)]
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+        unsafe {
+            libc::signal(libc::SIGINT, interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+        Ok(RestoreTerminal)
+    }
+}
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        let _ = write!(out, "\x1b[0m\x1b[?25h\r\n");
+        let _ = out.flush();
+    }
+}
+
+/// Terminal size as the tty reports it, or from `$COLUMNS`/`$LINES` when
+/// stdout isn't one (piped, or on a platform without the ioctl)
+fn terminal_size() -> (usize, usize) {
+    #[cfg(unix)]
+    {
+        let mut size = libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+        // SAFETY: TIOCGWINSZ only writes a winsize through the pointer, which points at one
+        let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+        if ok && size.ws_col > 0 && size.ws_row > 0 {
+            return (size.ws_col as usize, size.ws_row as usize);
+        }
+    }
+    let var = |name, default| std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+    (var("COLUMNS", 80), var("LINES", 24))
+}
+
+impl TuiConfig {
+    pub fn parse(args: &[String]) -> Result<TuiConfig, String> {
+        let (columns, rows) = terminal_size();
+        let mut config = TuiConfig { style: TuiStyle::Arrows, seed: rand::random(), ticks: 0, columns, rows };
+        for arg in args {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", arg))?;
+            let bad = || format!("invalid value for {}: '{}'", key, value);
+            match key {
+                "style" => config.style = match value {
+                    "arrows" => TuiStyle::Arrows,
+                    "braille" => TuiStyle::Braille,
+                    _ => return Err(bad()),
+                },
+                "seed" => config.seed = value.parse().map_err(|_| bad())?,
+                "ticks" => config.ticks = value.parse().map_err(|_| bad())?,
+                "size" => {
+                    let (c, r) = value.split_once('x').ok_or_else(bad)?;
+                    config.columns = c.parse().map_err(|_| bad())?;
+                    config.rows = r.parse().map_err(|_| bad())?;
+                }
+                _ => return Err(format!("unknown tui option '{}'", key)),
+            }
+        }
+        if config.columns == 0 || config.rows <= STATUS_ROWS {
+            return Err("terminal too small".to_string());
+        }
+        Ok(config)
+    }
+}
+
+fn arrow(boid: &Boid) -> char {
+    let v = boid.vel();
+    let octant = (v.y.atan2(v.x) / std::f32::consts::FRAC_PI_4).round() as i32;
+    ARROWS[octant.rem_euclid(8) as usize]
+}
+
+/// Foreground color escape for a boid's color
+fn ansi_color(color: [f32; 4]) -> String {
+    let c = |v: f32| (v.clamp(0.0, 1.0) * 255.0) as u8;
+    format!("\x1b[38;2;{};{};{}m", c(color[0]), c(color[1]), c(color[2]))
+}
+
+/// Terminal backend; boids are plotted into a cell grid that is written out at the end of the frame
+struct TerminalRenderer<W: Write> {
+    out: W,
+    style: TuiStyle,
+    columns: usize,
+    rows: usize,
+    /// World size mapped onto the grid
+    world: glam::Vec2,
+    /// Arrow and color of a boid in each cell
+    arrows: Vec<Option<(char, [f32; 4])>>,
+    /// Braille dot bits of each cell
+    dots: Vec<u8>,
+    overlay: Vec<String>,
+}
+
+impl<W: Write> TerminalRenderer<W> {
+    fn new(out: W, style: TuiStyle, columns: usize, rows: usize, world: glam::Vec2) -> TerminalRenderer<W> {
+        TerminalRenderer {
+            out,
+            style,
+            columns,
+            rows,
+            world,
+            arrows: vec![None; columns * rows],
+            dots: vec![0; columns * rows],
+            overlay: vec![],
+        }
+    }
+}
+
+impl<W: Write> Renderer for TerminalRenderer<W> {
+    type Error = io::Error;
+    fn begin_frame(&mut self) -> io::Result<()> {
+        self.arrows.fill(None);
+        self.dots.fill(0);
+        self.overlay.clear();
+        Ok(())
+    }
+    fn draw_boids(&mut self, sim: &Simulation, offset: glam::Vec2) -> io::Result<()> {
+        // braille dot bits by (row, column) within the 2x4 cell
+        const DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+        let (sub_x, sub_y) = match self.style {
+            TuiStyle::Arrows => (1, 1),
+            TuiStyle::Braille => (2, 4),
+        };
+        let (w, h) = (self.columns * sub_x, self.rows * sub_y);
+        for boid in &sim.boids {
+            let p = (boid.pos() + offset) / self.world;
+            let x = (p.x * w as f32).floor();
+            let y = (p.y * h as f32).floor();
+            if x < 0.0 || y < 0.0 || x as usize >= w || y as usize >= h {
+                continue;
+            }
+            let (x, y) = (x as usize, y as usize);
+            let cell = y / sub_y * self.columns + x / sub_x;
+            match self.style {
+                TuiStyle::Arrows => self.arrows[cell] = Some((arrow(boid), boid.color)),
+                TuiStyle::Braille => self.dots[cell] |= DOTS[y % 4][x % 2],
+            }
+        }
+        Ok(())
+    }
+    fn draw_overlay(&mut self, lines: &[String]) -> io::Result<()> {
+        self.overlay.extend_from_slice(lines);
+        Ok(())
+    }
+    fn draw_overlay_at(&mut self, lines: &[String], _at: glam::Vec2) -> io::Result<()> {
+        // there is only the status area under the grid to put text in
+        self.draw_overlay(lines)
+    }
+    fn draw_banner(&mut self, text: &str, _size: f32) -> io::Result<()> {
+        self.overlay.extend(text.lines().map(str::to_string));
+        Ok(())
+    }
+    fn end_frame(&mut self) -> io::Result<()> {
+        let mut frame = String::from("\x1b[H");
+        for row in 0..self.rows {
+            if row > 0 {
+                frame.push_str("\r\n");
+            }
+            let cells = row * self.columns..(row + 1) * self.columns;
+            match self.style {
+                TuiStyle::Arrows => {
+                    for cell in &self.arrows[cells] {
+                        match cell {
+                            Some((arrow, color)) => frame.push_str(&format!("{}{}", ansi_color(*color), arrow)),
+                            None => frame.push(' '),
+                        }
+                    }
+                    frame.push_str("\x1b[0m");
+                }
+                TuiStyle::Braille => {
+                    frame.extend(self.dots[cells].iter().map(|&bits| char::from_u32(0x2800 + bits as u32).unwrap()));
+                }
+            }
+        }
+        // no newline after the last line, or a full-height frame would scroll the terminal
+        for line in self.overlay.iter().take(STATUS_ROWS) {
+            frame.push_str(&format!("\r\n\x1b[2K{}", line));
+        }
+        self.out.write_all(frame.as_bytes())?;
+        self.out.flush()
+    }
+}
+
+/// Simulate and draw in the terminal until `ticks` run out or the user interrupts
+pub fn run(config: &TuiConfig) -> io::Result<()> {
+    let boid_bt: BoidBT = BT::new(Boid::create_bt(), Boid::create_blackboard());
+    let world = simulation::create_world_blackboard(WORLD_WIDTH, WORLD_HEIGHT);
+    let mut sim = Simulation::new(boid_bt, world, WORLD_WIDTH, WORLD_HEIGHT, config.seed);
+    sim.spawn(OBJECT_COUNT);
+    let cluster_distance = get_f32(&sim.world_bb.read().unwrap(), "cluster_distance");
+
+    let dt = Duration::from_secs_f64(TUI_DT);
+    let mut out = io::stdout().lock();
+    let _restore = RestoreTerminal::new(&mut out)?;
+    let world = glam::vec2(sim.width, sim.height);
+    let mut renderer = TerminalRenderer::new(out, config.style, config.columns, config.rows - STATUS_ROWS, world);
+    let mut tick = 0;
+    while (config.ticks == 0 || tick < config.ticks) && !INTERRUPTED.load(Ordering::Relaxed) {
+        let start = Instant::now();
+        sim.step(dt, NO_CURSOR);
+        tick += 1;
+
+        let metrics = Metrics::measure(&sim.boids, cluster_distance);
+        renderer.begin_frame()?;
+        renderer.draw_boids(&sim, glam::Vec2::ZERO)?;
+        renderer.draw_overlay(&[fill(tr("tick {}  boids {}  polarization {}  clusters {}  (ctrl-c to quit)"), &[
+            tick.to_string(),
+            sim.boids.len().to_string(),
+            format!("{:.2}", metrics.polarization),
+            metrics.clusters.to_string(),
+        ])])?;
+        renderer.end_frame()?;
+        if let Some(rest) = dt.checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_fills_the_terminal_without_scrolling() {
+        let (columns, rows) = (20, 6);
+        let mut renderer = TerminalRenderer::new(vec![], TuiStyle::Arrows, columns, rows - STATUS_ROWS, glam::vec2(100.0, 100.0));
+        renderer.begin_frame().unwrap();
+        renderer.draw_overlay(&["status".to_string(), "more than fits".to_string()]).unwrap();
+        renderer.end_frame().unwrap();
+        let frame = String::from_utf8(renderer.out).unwrap();
+        assert_eq!(frame.matches("\r\n").count(), rows - 1);
+        assert!(frame.ends_with("status"));
+    }
+}
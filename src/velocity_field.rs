@@ -0,0 +1,68 @@
+use ggez::{GameResult, graphics};
+
+use crate::boid::Boid;
+
+/// Average flock velocity sampled on a coarse grid
+pub struct VelocityField {
+    pub cell_size: f32,
+    pub cols: usize,
+    pub cells: Vec<glam::Vec2>,
+}
+
+impl VelocityField {
+    pub fn sample(boids: &[Boid], width: f32, height: f32, cell_size: f32) -> VelocityField {
+        let cols = (width / cell_size).ceil() as usize;
+        let rows = (height / cell_size).ceil() as usize;
+        let mut sums = vec![glam::Vec2::ZERO; cols * rows];
+        let mut counts = vec![0u32; cols * rows];
+        for boid in boids {
+            let pos = boid.pos();
+            if pos.x < 0.0 || pos.y < 0.0 {
+                continue;
+            }
+            let col = (pos.x / cell_size) as usize;
+            let row = (pos.y / cell_size) as usize;
+            if col < cols && row < rows {
+                sums[row * cols + col] += boid.vel();
+                counts[row * cols + col] += 1;
+            }
+        }
+        let cells = sums
+            .iter()
+            .zip(counts.iter())
+            .map(|(sum, &n)| if n > 0 { *sum / n as f32 } else { glam::Vec2::ZERO })
+            .collect();
+        VelocityField { cell_size, cols, cells }
+    }
+    /// Add one arrow per occupied cell, scaled so `max_speed` spans most of a cell
+    pub fn draw_arrows(&self, mb: &mut graphics::MeshBuilder, max_speed: f32) -> GameResult {
+        for (i, v) in self.cells.iter().enumerate() {
+            let speed = v.length();
+            if speed < f32::EPSILON {
+                continue;
+            }
+            let center = glam::vec2(
+                ((i % self.cols) as f32 + 0.5) * self.cell_size,
+                ((i / self.cols) as f32 + 0.5) * self.cell_size,
+            );
+            let dir = *v / speed;
+            let len = (speed / max_speed).min(1.0) * self.cell_size * 0.8;
+            arrow(mb, center - dir * len / 2.0, center + dir * len / 2.0, [0.8, 0.9, 1.0, 0.6])?;
+        }
+        Ok(())
+    }
+}
+
+/// Line from `tail` to `tip` with an arrowhead at the tip
+pub fn arrow(mb: &mut graphics::MeshBuilder, tail: glam::Vec2, tip: glam::Vec2, color: [f32; 4]) -> GameResult {
+    let len = tail.distance(tip);
+    if len < f32::EPSILON {
+        return Ok(());
+    }
+    let dir = (tip - tail) / len;
+    let side = glam::vec2(-dir.y, dir.x) * len * 0.2;
+    let head = tip - dir * len * 0.3;
+    mb.line(&[tail, tip], 1.0, color.into())?;
+    mb.line(&[head + side, tip, head - side], 1.0, color.into())?;
+    Ok(())
+}
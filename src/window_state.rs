@@ -0,0 +1,61 @@
+//! Window size, position and fullscreen state, saved on exit and put back on
+//! launch so a window resized to taste stays that way between runs
+use std::fs;
+
+use ggez::{conf, Context, GameResult, graphics};
+use ggez::winit::dpi::PhysicalPosition;
+use serde::{Deserialize, Serialize};
+
+pub const WINDOW_FILE: &str = "window.json";
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct WindowState {
+    /// Inner size, physical pixels
+    pub width: f32,
+    pub height: f32,
+    /// Outer top-left corner on the desktop, physical pixels
+    pub x: i32,
+    pub y: i32,
+    pub fullscreen: bool,
+}
+
+impl WindowState {
+    /// The window as it is now. Fullscreen, the size and position are kept
+    /// from `windowed`, so leaving fullscreen next run goes back to them.
+    pub fn capture(ctx: &Context, windowed: Option<WindowState>) -> WindowState {
+        let window = graphics::window(ctx);
+        let fullscreen = window.fullscreen().is_some();
+        match windowed {
+            Some(windowed) if fullscreen => WindowState { fullscreen, ..windowed },
+            _ => {
+                let (width, height) = graphics::drawable_size(ctx);
+                let position = window.outer_position().unwrap_or_default();
+                WindowState { width, height, x: position.x, y: position.y, fullscreen }
+            }
+        }
+    }
+    /// Resize and move the window to match, going fullscreen if it was
+    pub fn apply(&self, ctx: &mut Context) -> GameResult {
+        graphics::set_drawable_size(ctx, self.width, self.height)?;
+        graphics::set_window_position(ctx, PhysicalPosition::new(self.x, self.y))?;
+        if self.fullscreen {
+            graphics::set_fullscreen(ctx, conf::FullscreenType::Desktop)?;
+        }
+        Ok(())
+    }
+}
+
+/// The state saved by the last run, if any
+pub fn load() -> Result<Option<WindowState>, String> {
+    let text = match fs::read_to_string(WINDOW_FILE) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("{}: {}", WINDOW_FILE, e)),
+    };
+    serde_json::from_str(&text).map(Some).map_err(|e| format!("{}: {}", WINDOW_FILE, e))
+}
+
+pub fn save(state: &WindowState) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(WINDOW_FILE, text + "\n").map_err(|e| format!("{}: {}", WINDOW_FILE, e))
+}